@@ -0,0 +1,123 @@
+use crate::{
+    rebuild_chain_for_target, CachedMessage, ClientTrait, ExtensionsTrait,
+    FriendMessageRecallEventProcess, GroupMessageRecallEventProcess, HandleResult, MessageCache,
+    MessageTarget, Module, ModuleEventHandler, ModuleEventProcess, TextEleParseTrait,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use ricq::client::event::{FriendMessageRecallEvent, GroupMessageRecallEvent};
+use ricq_core::msg::MessageChain;
+use std::sync::Arc;
+
+#[derive(Clone)]
+struct AntiRecallHandler {
+    target: MessageTarget,
+}
+
+impl AntiRecallHandler {
+    async fn repost(
+        &self,
+        client: &ricq::Client,
+        title: String,
+        cached: Option<CachedMessage>,
+    ) -> Result<()> {
+        let mut chain = MessageChain::new(title.parse_text());
+        match cached {
+            Some(cached) => {
+                // 图片需要重新上传时可能失败, 失败时只发送文字
+                let content =
+                    match rebuild_chain_for_target(client, &cached.elements, self.target).await {
+                        Ok(content) => content,
+                        Err(err) => {
+                            tracing::warn!("撤回的消息转发失败 : {:?}", err);
+                            MessageChain::new(cached.elements.to_string().parse_text())
+                        }
+                    };
+                chain.0.extend(content.0);
+            }
+            None => chain.push("(消息不在缓存中)".parse_text()),
+        }
+        client.send_message_to_target(&self.target, chain).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GroupMessageRecallEventProcess for AntiRecallHandler {
+    async fn handle(&self, event: &GroupMessageRecallEvent) -> Result<bool> {
+        Ok(self.handle_result(event).await? == HandleResult::Stop)
+    }
+
+    async fn handle_result(&self, event: &GroupMessageRecallEvent) -> Result<HandleResult> {
+        let recall = &event.inner;
+        let cache = match event.client.extensions().get::<MessageCache>() {
+            Some(cache) => cache,
+            None => return Ok(HandleResult::Continue),
+        };
+        let cached = cache.find_group_message(recall.group_code, recall.msg_seq);
+        let author = cached
+            .as_ref()
+            .map(|cached| format!("{}({})", cached.sender_name, recall.author_uin))
+            .unwrap_or_else(|| recall.author_uin.to_string());
+        let title = if recall.operator_uin == recall.author_uin {
+            format!("{} 在群 {} 撤回了消息 :\n", author, recall.group_code)
+        } else {
+            format!(
+                "{} 在群 {} 撤回了 {} 的消息 :\n",
+                recall.operator_uin, recall.group_code, author
+            )
+        };
+        self.repost(&event.client, title, cached).await?;
+        Ok(HandleResult::Handled)
+    }
+}
+
+#[async_trait]
+impl FriendMessageRecallEventProcess for AntiRecallHandler {
+    async fn handle(&self, event: &FriendMessageRecallEvent) -> Result<bool> {
+        Ok(self.handle_result(event).await? == HandleResult::Stop)
+    }
+
+    async fn handle_result(&self, event: &FriendMessageRecallEvent) -> Result<HandleResult> {
+        let recall = &event.inner;
+        let cache = match event.client.extensions().get::<MessageCache>() {
+            Some(cache) => cache,
+            None => return Ok(HandleResult::Continue),
+        };
+        let cached = cache.find_friend_message(recall.friend_uin, recall.msg_seq);
+        let title = format!("好友 {} 撤回了消息 :\n", recall.friend_uin);
+        self.repost(&event.client, title, cached).await?;
+        Ok(HandleResult::Handled)
+    }
+}
+
+/// 内置的防撤回模块, 将撤回的消息转发到target (日志群或管理员)
+///
+/// 需要通过`ClientBuilder::message_cache`开启消息缓存, 只能找回缓存中的消息.
+/// 处理后不会拦截撤回事件, 其他模块仍然可以收到.
+///
+/// ```ignore
+/// let modules = vec![anti_recall_module(MessageTarget::Private(master)), ...];
+/// ClientBuilder::new().message_cache(MessageCache::new(2000)).modules(modules);
+/// ```
+pub fn anti_recall_module(target: MessageTarget) -> Module {
+    let handler = AntiRecallHandler { target };
+    Module {
+        id: "proc_qq_anti_recall".to_owned(),
+        name: "防撤回".to_owned(),
+        handles: vec![
+            ModuleEventHandler {
+                name: "group_recall".to_owned(),
+                process: ModuleEventProcess::GroupMessageRecall(Box::new(handler.clone())),
+                command: None,
+            },
+            ModuleEventHandler {
+                name: "friend_recall".to_owned(),
+                process: ModuleEventProcess::FriendMessageRecall(Box::new(handler)),
+                command: None,
+            },
+        ],
+        data: Arc::new(Default::default()),
+        priority: 0,
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{
+    ExtensionsTrait, MessageContentTrait, MessageEvent, MessageSendToSourceTrait, Permissions,
+    Role, RoleSubjectTrait, TextEleParseTrait,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Local, TimeZone, Utc};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// 被记录的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Mute,
+    Unmute,
+    Kick,
+    Recall,
+    ConfigChange,
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AuditAction::Mute => "禁言",
+            AuditAction::Unmute => "解除禁言",
+            AuditAction::Kick => "踢出",
+            AuditAction::Recall => "撤回",
+            AuditAction::ConfigChange => "修改配置",
+        })
+    }
+}
+
+/// 审计记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// unix毫秒
+    pub time: i64,
+    /// 操作者, None为机器人自己
+    pub actor: Option<i64>,
+    /// 不是QQ用户发起的操作的来源, 例如`web_admin`
+    #[serde(default)]
+    pub operator: Option<String>,
+    pub action: AuditAction,
+    pub group_code: Option<i64>,
+    pub target: Option<i64>,
+    pub reason: String,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction) -> Self {
+        Self {
+            time: Utc::now().timestamp_millis(),
+            actor: None,
+            operator: None,
+            action,
+            group_code: None,
+            target: None,
+            reason: String::new(),
+        }
+    }
+
+    pub fn actor<E: Into<Option<i64>>>(mut self, actor: E) -> Self {
+        self.actor = actor.into();
+        self
+    }
+
+    pub fn operator(mut self, operator: impl Into<String>) -> Self {
+        self.operator = Some(operator.into());
+        self
+    }
+
+    pub fn group_code<E: Into<Option<i64>>>(mut self, group_code: E) -> Self {
+        self.group_code = group_code.into();
+        self
+    }
+
+    pub fn target<E: Into<Option<i64>>>(mut self, target: E) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = reason.into();
+        self
+    }
+}
+
+impl Display for AuditEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let time = Local
+            .timestamp_millis_opt(self.time)
+            .single()
+            .map(|t| t.format("%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        write!(f, "{} ", time)?;
+        match (self.actor, &self.operator) {
+            (Some(actor), _) => write!(f, "{} ", actor)?,
+            (None, Some(operator)) => write!(f, "{} ", operator)?,
+            (None, None) => write!(f, "机器人 ")?,
+        }
+        write!(f, "{}", self.action)?;
+        if let Some(target) = self.target {
+            write!(f, " {}", target)?;
+        }
+        if let Some(group_code) = self.group_code {
+            write!(f, " (群 {})", group_code)?;
+        }
+        if !self.reason.is_empty() {
+            write!(f, " : {}", self.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// 审计日志
+///
+/// 记录通过框架进行的禁言、踢人、撤回以及配置修改. 设置文件时以JSON Lines追加写入,
+/// 内存中保留最近的记录用于查询. Admin以上的用户可以使用`/audit recent`查看.
+pub struct AuditLog {
+    path: Option<String>,
+    capacity: usize,
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            capacity: 1000,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// 追加写入文件, 构建客户端时加载最近的记录
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    /// 内存中保留的记录数量
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub(crate) async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            if !tokio::fs::try_exists(path).await? {
+                return Ok(());
+            }
+            let content = tokio::fs::read_to_string(path).await?;
+            let mut entries = self.entries.lock().await;
+            for line in content.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str::<AuditEntry>(line) {
+                    Ok(entry) => {
+                        entries.push_back(entry);
+                        if entries.len() > self.capacity {
+                            entries.pop_front();
+                        }
+                    }
+                    Err(err) => tracing::warn!("审计日志解析失败 : {:?}", err),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn record(&self, entry: AuditEntry) -> Result<()> {
+        if let Some(path) = &self.path {
+            let mut line = serde_json::to_string(&entry)?;
+            line.push('\n');
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+        }
+        let mut entries = self.entries.lock().await;
+        entries.push_back(entry);
+        if entries.len() > self.capacity {
+            entries.pop_front();
+        }
+        Ok(())
+    }
+
+    /// 最近的记录, 新的在前. group_code不为None时只返回该群的记录
+    pub async fn recent(&self, group_code: Option<i64>, limit: usize) -> Vec<AuditEntry> {
+        self.query(
+            |entry| group_code.is_none() || entry.group_code == group_code,
+            limit,
+        )
+        .await
+    }
+
+    /// 按条件查询内存中的记录, 新的在前
+    pub async fn query<F: Fn(&AuditEntry) -> bool>(
+        &self,
+        filter: F,
+        limit: usize,
+    ) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .await
+            .iter()
+            .rev()
+            .filter(|entry| filter(entry))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// 处理`/audit recent [条数]`, 群中只显示该群的记录
+    pub(crate) async fn handle_command(
+        &self,
+        event: &MessageEvent,
+        permissions: &Permissions,
+    ) -> Result<bool> {
+        let content = event.message_content();
+        let mut sp = content.split_whitespace();
+        if sp.next() != Some("/audit") {
+            return Ok(false);
+        }
+        let (group_code, uin) = event.role_subject();
+        if !permissions.has_role(group_code, uin, Role::Admin).await {
+            return Ok(false);
+        }
+        let args: Vec<&str> = sp.collect();
+        let reply = match args.as_slice() {
+            [] | ["recent"] => Some(10),
+            ["recent", limit] => limit.parse::<usize>().ok(),
+            _ => None,
+        };
+        let reply = match reply {
+            Some(limit) => {
+                let entries = self.recent(group_code, limit.min(50)).await;
+                if entries.is_empty() {
+                    "没有记录".to_owned()
+                } else {
+                    entries
+                        .iter()
+                        .map(|entry| entry.to_string())
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                }
+            }
+            None => "/audit recent [条数]".to_owned(),
+        };
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+}
+
+/// 记录到客户端的审计日志, 失败时只打印日志
+pub async fn record_audit(client: &ricq::Client, entry: AuditEntry) {
+    if let Some(audit_log) = client.extensions().get::<AuditLog>() {
+        if let Err(err) = audit_log.record(entry).await {
+            tracing::warn!("审计日志写入失败 : {:?}", err);
+        }
+    }
+}
+
+/// 记录消息中的管理指令
+pub(crate) async fn record_config_change(event: &MessageEvent) {
+    let (group_code, uin) = event.role_subject();
+    record_audit(
+        &event.client(),
+        AuditEntry::new(AuditAction::ConfigChange)
+            .actor(uin)
+            .group_code(group_code)
+            .reason(event.message_content()),
+    )
+    .await;
+}
+
+/// 会被记录到审计日志的管理操作, actor为None时表示机器人自己
+#[async_trait]
+pub trait AuditedActionsTrait {
+    async fn audited_mute(
+        &self,
+        actor: Option<i64>,
+        group_code: i64,
+        uin: i64,
+        duration: Duration,
+        reason: &str,
+    ) -> Result<()>;
+
+    async fn audited_kick(
+        &self,
+        actor: Option<i64>,
+        group_code: i64,
+        uin: i64,
+        block: bool,
+        reason: &str,
+    ) -> Result<()>;
+
+    async fn audited_recall(
+        &self,
+        actor: Option<i64>,
+        group_code: i64,
+        target: Option<i64>,
+        receipt: MessageReceipt,
+        reason: &str,
+    ) -> Result<()>;
+}
+
+#[async_trait]
+impl AuditedActionsTrait for ricq::Client {
+    async fn audited_mute(
+        &self,
+        actor: Option<i64>,
+        group_code: i64,
+        uin: i64,
+        duration: Duration,
+        reason: &str,
+    ) -> Result<()> {
+        self.group_mute(group_code, uin, duration).await?;
+        let (action, reason) = if duration.is_zero() {
+            (AuditAction::Unmute, reason.to_owned())
+        } else if reason.is_empty() {
+            (AuditAction::Mute, format!("{}秒", duration.as_secs()))
+        } else {
+            (
+                AuditAction::Mute,
+                format!("{}秒, {}", duration.as_secs(), reason),
+            )
+        };
+        record_audit(
+            self,
+            AuditEntry::new(action)
+                .actor(actor)
+                .group_code(group_code)
+                .target(uin)
+                .reason(reason),
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn audited_kick(
+        &self,
+        actor: Option<i64>,
+        group_code: i64,
+        uin: i64,
+        block: bool,
+        reason: &str,
+    ) -> Result<()> {
+        self.group_kick(group_code, vec![uin], reason, block)
+            .await?;
+        record_audit(
+            self,
+            AuditEntry::new(AuditAction::Kick)
+                .actor(actor)
+                .group_code(group_code)
+                .target(uin)
+                .reason(reason),
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn audited_recall(
+        &self,
+        actor: Option<i64>,
+        group_code: i64,
+        target: Option<i64>,
+        receipt: MessageReceipt,
+        reason: &str,
+    ) -> Result<()> {
+        self.recall_group_message(group_code, receipt.seqs, receipt.rands)
+            .await?;
+        record_audit(
+            self,
+            AuditEntry::new(AuditAction::Recall)
+                .actor(actor)
+                .group_code(group_code)
+                .target(target)
+                .reason(reason),
+        )
+        .await;
+        Ok(())
+    }
+}
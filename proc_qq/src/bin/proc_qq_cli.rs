@@ -0,0 +1,162 @@
+//! 部署调试用的命令行工具
+//!
+//! ```text
+//! proc-qq-cli gen-device [device.json] [--force]
+//! proc-qq-cli login-test (--uin QQ号 --password 密码 | --qr) [--device device.json] [--session session.token] [--protocol phone|watch|ipad|macos]
+//! proc-qq-cli dump-session [session.token]
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use proc_qq::re_exports::ricq::version::{Version, ANDROID_PHONE, ANDROID_WATCH, IPAD, MACOS};
+use proc_qq::re_exports::ricq_core::protocol::device::Device;
+use proc_qq::{
+    bytes_to_token, login_test, Authentication, ClientBuilder, DeviceSource, FileSessionStore,
+    LoginMethod, ShowQR,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+static USAGE: &str = "proc-qq-cli gen-device [device.json] [--force]
+proc-qq-cli login-test (--uin QQ号 --password 密码 | --qr) [--device device.json] [--session session.token] [--protocol phone|watch|ipad|macos]
+proc-qq-cli dump-session [session.token]";
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("gen-device") => gen_device(&args[1..]).await,
+        Some("login-test") => login(&args[1..]).await,
+        Some("dump-session") => dump_session(&args[1..]).await,
+        _ => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("{:?}", err);
+        std::process::exit(1);
+    }
+}
+
+// 解析 --key value 和 --flag, 其余为位置参数
+fn parse_args(args: &[String]) -> (Vec<String>, HashMap<String, Option<String>>) {
+    let mut positional = vec![];
+    let mut options = HashMap::new();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        if let Some(key) = arg.strip_prefix("--") {
+            let value = match iter.peek() {
+                Some(value) if !value.starts_with("--") => iter.next().cloned(),
+                _ => None,
+            };
+            options.insert(key.to_owned(), value);
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+    (positional, options)
+}
+
+fn option<'a>(options: &'a HashMap<String, Option<String>>, key: &str) -> Option<&'a str> {
+    options.get(key).and_then(|value| value.as_deref())
+}
+
+async fn gen_device(args: &[String]) -> Result<()> {
+    let (positional, options) = parse_args(args);
+    let path = positional
+        .first()
+        .map(String::as_str)
+        .unwrap_or("device.json");
+    if Path::new(path).exists() && !options.contains_key("force") {
+        return Err(anyhow!("文件已存在 : {} (使用 --force 覆盖)", path));
+    }
+    let device = Device::random();
+    tokio::fs::write(path, serde_json::to_string_pretty(&device)?)
+        .await
+        .with_context(|| format!("写入文件失败 : {}", path))?;
+    println!("已生成设备信息 : {}", path);
+    Ok(())
+}
+
+fn protocol(name: &str) -> Result<&'static Version> {
+    match name {
+        "phone" => Ok(&ANDROID_PHONE),
+        "watch" => Ok(&ANDROID_WATCH),
+        "ipad" => Ok(&IPAD),
+        "macos" => Ok(&MACOS),
+        _ => Err(anyhow!("未知的协议 : {}", name)),
+    }
+}
+
+async fn login(args: &[String]) -> Result<()> {
+    let (_, options) = parse_args(args);
+    let authentication = if options.contains_key("qr") {
+        Authentication::QRCode
+    } else {
+        let uin = option(&options, "uin")
+            .with_context(|| USAGE)?
+            .parse::<i64>()
+            .with_context(|| "QQ号格式错误")?;
+        let password = option(&options, "password").with_context(|| USAGE)?;
+        Authentication::UinPassword(uin, password.to_owned())
+    };
+    let device = option(&options, "device").unwrap_or("device.json");
+    let session = option(&options, "session").unwrap_or("session.token");
+    let client = ClientBuilder::new()
+        .authentication(authentication)
+        .device(DeviceSource::JsonFile(device.to_owned()))
+        .version(protocol(option(&options, "protocol").unwrap_or("phone"))?)
+        .session_store(FileSessionStore::boxed(session))
+        .show_rq(ShowQR::SaveToFile)
+        .build()
+        .await?;
+    let client = Arc::new(client);
+    match login_test(client.clone()).await {
+        Ok(method) => {
+            println!("登录成功 : {}", client.rq_client.uin().await);
+            match method {
+                LoginMethod::Token => println!("使用了保存的session : {}", session),
+                LoginMethod::Authentication => println!("session已保存 : {}", session),
+            }
+            Ok(())
+        }
+        Err(err) => {
+            println!("登录失败, 可能触发了风控 (需要滑块/设备锁验证, 或者协议/设备被限制)");
+            Err(err)
+        }
+    }
+}
+
+async fn dump_session(args: &[String]) -> Result<()> {
+    let (positional, _) = parse_args(args);
+    let path = positional
+        .first()
+        .map(String::as_str)
+        .unwrap_or("session.token");
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("读取文件失败 : {}", path))?;
+    if data.len() < 8 {
+        return Err(anyhow!("session文件格式错误 : {}", path));
+    }
+    let token = bytes_to_token(data);
+    // 只显示长度, 不输出密钥
+    println!("uin : {}", token.uin);
+    println!("d2 : {} bytes", token.d2.len());
+    println!("d2key : {} bytes", token.d2key.len());
+    println!("tgt : {} bytes", token.tgt.len());
+    println!("srm_token : {} bytes", token.srm_token.len());
+    println!("t133 : {} bytes", token.t133.len());
+    println!("encrypted_a1 : {} bytes", token.encrypted_a1.len());
+    println!(
+        "wt_session_ticket_key : {} bytes",
+        token.wt_session_ticket_key.len()
+    );
+    println!(
+        "out_packet_session_id : {} bytes",
+        token.out_packet_session_id.len()
+    );
+    println!("tgtgt_key : {} bytes", token.tgtgt_key.len());
+    Ok(())
+}
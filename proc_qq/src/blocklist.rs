@@ -0,0 +1,211 @@
+use crate::audit::record_config_change;
+use crate::persist::{load_json, save_json};
+use crate::{MessageContentTrait, MessageEvent, MessageSendToSourceTrait, TextEleParseTrait};
+use anyhow::Result;
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::sync::RwLock;
+
+/// 黑白名单数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlocklistData {
+    /// 被屏蔽的用户
+    pub users: HashSet<i64>,
+    /// 被屏蔽的群
+    pub groups: HashSet<i64>,
+    /// 白名单模式, 开启后只有白名单中的用户和群才能触发事件
+    pub whitelist_mode: bool,
+    /// 白名单用户 (私聊)
+    pub whitelist_users: HashSet<i64>,
+    /// 白名单群
+    pub whitelist_groups: HashSet<i64>,
+}
+
+/// 黑白名单
+///
+/// 在调度器中对事件进行过滤, 被屏蔽的用户或群的事件不会传递给任何模块.
+/// 主人(ClientBuilder::masters以及被设置为Master角色的用户)不受黑白名单限制, 并且可以使用`/blocklist`指令进行管理.
+pub struct Blocklist {
+    path: Option<String>,
+    data: RwLock<BlocklistData>,
+}
+
+impl Blocklist {
+    /// 仅保存在内存中的黑白名单
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            data: RwLock::new(BlocklistData::default()),
+        }
+    }
+
+    /// 保存在文件中的黑白名单, 构建客户端时加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            data: RwLock::new(BlocklistData::default()),
+        }
+    }
+
+    pub(crate) async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            *self.data.write().await = load_json(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn modify<F: FnOnce(&mut BlocklistData)>(&self, f: F) -> Result<()> {
+        let mut data = self.data.write().await;
+        let mut modified = data.clone();
+        f(&mut modified);
+        if let Some(path) = &self.path {
+            save_json(path, &modified).await?;
+        }
+        *data = modified;
+        Ok(())
+    }
+
+    /// 当前数据的快照
+    pub async fn snapshot(&self) -> BlocklistData {
+        self.data.read().await.clone()
+    }
+
+    pub async fn add_user(&self, uin: i64) -> Result<()> {
+        self.modify(|data| {
+            data.users.insert(uin);
+        })
+        .await
+    }
+
+    pub async fn remove_user(&self, uin: i64) -> Result<()> {
+        self.modify(|data| {
+            data.users.remove(&uin);
+        })
+        .await
+    }
+
+    pub async fn add_group(&self, group_code: i64) -> Result<()> {
+        self.modify(|data| {
+            data.groups.insert(group_code);
+        })
+        .await
+    }
+
+    pub async fn remove_group(&self, group_code: i64) -> Result<()> {
+        self.modify(|data| {
+            data.groups.remove(&group_code);
+        })
+        .await
+    }
+
+    pub async fn allow_user(&self, uin: i64) -> Result<()> {
+        self.modify(|data| {
+            data.whitelist_users.insert(uin);
+        })
+        .await
+    }
+
+    pub async fn disallow_user(&self, uin: i64) -> Result<()> {
+        self.modify(|data| {
+            data.whitelist_users.remove(&uin);
+        })
+        .await
+    }
+
+    pub async fn allow_group(&self, group_code: i64) -> Result<()> {
+        self.modify(|data| {
+            data.whitelist_groups.insert(group_code);
+        })
+        .await
+    }
+
+    pub async fn disallow_group(&self, group_code: i64) -> Result<()> {
+        self.modify(|data| {
+            data.whitelist_groups.remove(&group_code);
+        })
+        .await
+    }
+
+    pub async fn set_whitelist_mode(&self, whitelist_mode: bool) -> Result<()> {
+        self.modify(|data| data.whitelist_mode = whitelist_mode)
+            .await
+    }
+
+    /// 判断事件是否应该被屏蔽, group_code为None时表示私聊
+    pub async fn is_blocked(&self, group_code: Option<i64>, uin: i64) -> bool {
+        let data = self.data.read().await;
+        if data.users.contains(&uin) {
+            return true;
+        }
+        match group_code {
+            Some(group_code) => {
+                data.groups.contains(&group_code)
+                    || (data.whitelist_mode && !data.whitelist_groups.contains(&group_code))
+            }
+            None => data.whitelist_mode && !data.whitelist_users.contains(&uin),
+        }
+    }
+
+    /// 处理主人发出的管理指令, 返回是否为管理指令
+    pub(crate) async fn handle_command(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let mut sp = content.split_whitespace();
+        if sp.next() != Some("/blocklist") {
+            return Ok(false);
+        }
+        let args: Vec<&str> = sp.collect();
+        let reply = match args.as_slice() {
+            [] | ["list"] => {
+                let data = self.snapshot().await;
+                format!(
+                    "白名单模式 : {}\n屏蔽用户 : {:?}\n屏蔽群 : {:?}\n白名单用户 : {:?}\n白名单群 : {:?}",
+                    if data.whitelist_mode { "开启" } else { "关闭" },
+                    data.users,
+                    data.groups,
+                    data.whitelist_users,
+                    data.whitelist_groups,
+                )
+            }
+            ["whitelist", "on"] => {
+                self.set_whitelist_mode(true).await?;
+                "已开启白名单模式".to_owned()
+            }
+            ["whitelist", "off"] => {
+                self.set_whitelist_mode(false).await?;
+                "已关闭白名单模式".to_owned()
+            }
+            [action, kind, id] => match id.parse::<i64>() {
+                Ok(id) => match (*action, *kind) {
+                    ("add", "user") => self.add_user(id).await.map(|_| "OK".to_owned())?,
+                    ("add", "group") => self.add_group(id).await.map(|_| "OK".to_owned())?,
+                    ("remove", "user") => self.remove_user(id).await.map(|_| "OK".to_owned())?,
+                    ("remove", "group") => self.remove_group(id).await.map(|_| "OK".to_owned())?,
+                    ("allow", "user") => self.allow_user(id).await.map(|_| "OK".to_owned())?,
+                    ("allow", "group") => self.allow_group(id).await.map(|_| "OK".to_owned())?,
+                    ("disallow", "user") => {
+                        self.disallow_user(id).await.map(|_| "OK".to_owned())?
+                    }
+                    ("disallow", "group") => {
+                        self.disallow_group(id).await.map(|_| "OK".to_owned())?
+                    }
+                    _ => BLOCKLIST_USAGE.to_owned(),
+                },
+                Err(_) => BLOCKLIST_USAGE.to_owned(),
+            },
+            _ => BLOCKLIST_USAGE.to_owned(),
+        };
+        if reply == "OK" || reply.starts_with("已") {
+            record_config_change(event).await;
+        }
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+}
+
+static BLOCKLIST_USAGE: &str = "/blocklist list\n\
+/blocklist add|remove user|group 号码\n\
+/blocklist allow|disallow user|group 号码\n\
+/blocklist whitelist on|off";
@@ -0,0 +1,115 @@
+use crate::{ExtensionsTrait, MessageEvent};
+use chrono::{DateTime, Local, TimeZone};
+use ricq::client::event::GroupMessageEvent;
+use ricq::structs::GroupInfo;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// 向禁言中的群发送消息时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutedSendPolicy {
+    /// 照常发送 (默认)
+    Attempt,
+    /// 直接丢弃, 返回空的回执
+    Drop,
+    /// 使用延时发送, 在禁言结束后发送
+    Defer,
+}
+
+/// 机器人在各个群中的禁言状态
+///
+/// 根据禁言通知、登录时的群列表以及发送失败时查询的群成员信息更新, 在客户端的扩展中取得.
+/// 全员禁言不会被记录.
+#[derive(Default)]
+pub struct BotMuteTracker {
+    // 群号 -> 禁言结束的时间 (unix秒)
+    muted: RwLock<HashMap<i64, i64>>,
+}
+
+impl BotMuteTracker {
+    /// 禁言结束的时间, 没有被禁言时返回None
+    pub fn muted_until(&self, group_code: i64) -> Option<DateTime<Local>> {
+        let until = *self.muted.read().unwrap().get(&group_code)?;
+        if until <= Local::now().timestamp() {
+            return None;
+        }
+        Local.timestamp_opt(until, 0).single()
+    }
+
+    pub fn is_muted(&self, group_code: i64) -> bool {
+        self.muted_until(group_code).is_some()
+    }
+
+    /// 设置禁言结束的时间, until不晚于当前时间时视为解除禁言
+    pub(crate) fn set(&self, group_code: i64, until: i64) {
+        let mut muted = self.muted.write().unwrap();
+        if until > Local::now().timestamp() {
+            muted.insert(group_code, until);
+        } else {
+            muted.remove(&group_code);
+        }
+    }
+
+    /// 使用群列表中机器人的禁言时间更新
+    pub(crate) fn update_from_groups(&self, groups: &[GroupInfo]) {
+        for group in groups {
+            self.set(group.code, group.my_shut_up_timestamp);
+        }
+    }
+
+    /// 发送失败时查询机器人在群中的禁言时间
+    pub(crate) async fn refresh(&self, client: &ricq::Client, group_code: i64) {
+        let uin = client.uin().await;
+        match client.get_group_member_info(group_code, uin).await {
+            Ok(info) => self.set(group_code, info.shut_up_timestamp),
+            Err(err) => tracing::debug!("查询禁言状态失败 : {:?}", err),
+        }
+    }
+}
+
+/// 查询机器人的禁言状态
+pub trait BotMuteTrait {
+    /// 机器人在群中的禁言结束时间, 没有被禁言时返回None
+    fn bot_muted_until(&self, group_code: i64) -> Option<DateTime<Local>>;
+}
+
+impl BotMuteTrait for ricq::Client {
+    fn bot_muted_until(&self, group_code: i64) -> Option<DateTime<Local>> {
+        self.extensions()
+            .get_or_insert_with(BotMuteTracker::default)
+            .muted_until(group_code)
+    }
+}
+
+impl BotMuteTrait for crate::Client {
+    fn bot_muted_until(&self, group_code: i64) -> Option<DateTime<Local>> {
+        self.rq_client.bot_muted_until(group_code)
+    }
+}
+
+/// 在群消息中查询机器人在该群的禁言状态
+///
+/// ```ignore
+/// if let Some(until) = event.bot_muted_until() {
+///     tracing::info!("在群中被禁言到 {}", until);
+/// }
+/// ```
+pub trait GroupBotMuteTrait {
+    /// 机器人在消息所在的群中的禁言结束时间, 没有被禁言或不是群消息时返回None
+    fn bot_muted_until(&self) -> Option<DateTime<Local>>;
+}
+
+impl GroupBotMuteTrait for GroupMessageEvent {
+    fn bot_muted_until(&self) -> Option<DateTime<Local>> {
+        self.client.bot_muted_until(self.inner.group_code)
+    }
+}
+
+impl GroupBotMuteTrait for MessageEvent {
+    fn bot_muted_until(&self) -> Option<DateTime<Local>> {
+        match self {
+            MessageEvent::GroupMessage(event) => event.bot_muted_until(),
+            _ => None,
+        }
+    }
+}
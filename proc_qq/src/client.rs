@@ -1,27 +1,38 @@
-use crate::handler::EventSender;
+use crate::extensions::{register_extensions, ClientRef};
+use crate::handler::{EventDedup, EventSender};
+use crate::module_manager::effective_priority;
 use crate::DeviceSource::{JsonFile, JsonString};
 use crate::{
-    Authentication, ClientHandler, DeviceLockVerification, DeviceSource, EventResultHandler,
-    Module, SessionStore, ShowQR, ShowSlider,
+    AccountReadyEvent, AuditLog, Authentication, Blocklist, BotMuteTracker, ClientHandler,
+    ContactCache, DelayedSender, DeviceLockVerification, DeviceSource, EventResultHandler,
+    Extensions, ExtensionsTrait, HealthReport, I18n, MembershipTracker, MessageCache,
+    MessageTarget, Middleware, Module, ModuleManager, ModuleToggles, Permissions, Quotas,
+    ReconnectHooks, ReconnectPolicy, ReconnectedInfo, SessionStore, ShowQR, ShowSlider, Stats,
 };
 use anyhow::{anyhow, Context, Result};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use chrono::{DateTime, TimeZone};
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use rand::prelude::IteratorRandom;
+use ricq::client::NetworkStatus;
 use ricq::ext::common::after_login;
+use ricq::handler::Handler;
 use ricq_core::binary::{BinaryReader, BinaryWriter};
 use ricq_core::command::wtlogin::{
     LoginDeviceLocked, LoginNeedCaptcha, LoginResponse, LoginSuccess, LoginUnknownStatus,
     QRCodeConfirmed, QRCodeImageFetch, QRCodeState,
 };
+use ricq_core::msg::MessageChain;
 use ricq_core::protocol::device::Device;
 use ricq_core::protocol::version::{Version, ANDROID_PHONE};
 use ricq_core::{RQError, RQResult, Token};
-use std::cmp::min;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpStream;
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
@@ -38,7 +49,7 @@ pub struct Client {
     pub rq_client: Arc<ricq::Client>,
     pub authentication: Authentication,
     pub session_store: Arc<Option<Box<dyn SessionStore + Sync + Send>>>,
-    pub(crate) modules: Arc<Vec<Module>>,
+    pub(crate) modules: Arc<Vec<Arc<Module>>>,
     pub(crate) result_handlers: Arc<Vec<EventResultHandler>>,
     pub show_qr: ShowQR,
     pub show_slider: ShowSlider,
@@ -47,9 +58,135 @@ pub struct Client {
     #[cfg(feature = "connect_handler")]
     pub connection_handler: Arc<Option<Box<dyn ConnectionHandler + Sync + Send>>>,
     pub reconnect_duration: Duration,
+    pub masters: Arc<Vec<i64>>,
+    pub(crate) blocklist: Arc<Blocklist>,
+    pub(crate) quotas: Arc<Quotas>,
+    pub(crate) permissions: Arc<Permissions>,
+    pub(crate) audit_log: Arc<AuditLog>,
+    pub(crate) stats: Option<Arc<Stats>>,
+    pub(crate) membership_tracker: Option<Arc<MembershipTracker>>,
+    pub(crate) message_cache: Option<Arc<MessageCache>>,
+    pub(crate) module_toggles: Arc<ModuleToggles>,
+    pub(crate) module_manager: ModuleManager,
+    pub(crate) extensions: Arc<Extensions>,
+    pub(crate) delayed_sender: Arc<DelayedSender>,
+    pub(crate) reconnect_hooks: Arc<ReconnectHooks>,
+    pub(crate) error_handlers: Arc<Vec<Arc<dyn ErrorHandler>>>,
 }
 
 impl Client {
+    /// 黑白名单, 可以在运行时进行管理
+    pub fn blocklist(&self) -> Arc<Blocklist> {
+        self.blocklist.clone()
+    }
+
+    /// 取得指令配额
+    pub fn quotas(&self) -> Arc<Quotas> {
+        self.quotas.clone()
+    }
+
+    /// 角色权限
+    pub fn permissions(&self) -> Arc<Permissions> {
+        self.permissions.clone()
+    }
+
+    /// 审计日志
+    pub fn audit_log(&self) -> Arc<AuditLog> {
+        self.audit_log.clone()
+    }
+
+    /// 消息统计, 没有开启时为None
+    pub fn stats(&self) -> Option<Arc<Stats>> {
+        self.stats.clone()
+    }
+
+    /// 群成员变动记录, 没有开启时为None
+    pub fn membership_tracker(&self) -> Option<Arc<MembershipTracker>> {
+        self.membership_tracker.clone()
+    }
+
+    /// 最近收到的消息, 没有开启时为None
+    pub fn message_cache(&self) -> Option<Arc<MessageCache>> {
+        self.message_cache.clone()
+    }
+
+    /// 模块开关
+    pub fn module_toggles(&self) -> Arc<ModuleToggles> {
+        self.module_toggles.clone()
+    }
+
+    /// 在运行时列出、开启和关闭模块
+    pub fn module_manager(&self) -> ModuleManager {
+        self.module_manager.clone()
+    }
+
+    /// 多语言文本和每个群的语言设置
+    pub fn i18n(&self) -> Arc<I18n> {
+        self.rq_client
+            .extensions()
+            .get_or_insert_with(I18n::default)
+    }
+
+    /// 发送一次心跳, 返回往返耗时
+    pub async fn ping(&self) -> Result<Duration> {
+        crate::health::ping(&self.rq_client).await
+    }
+
+    /// 运行状态: 最近发送消息的耗时和失败率, 重连次数
+    pub fn health(&self) -> HealthReport {
+        crate::health::health(&self.rq_client).report()
+    }
+
+    /// 所有模块的(id, 名称)
+    pub fn modules(&self) -> Vec<(String, String)> {
+        self.modules
+            .iter()
+            .map(|m| (m.id.clone(), m.name.clone()))
+            .collect()
+    }
+
+    /// 延时发送消息, 返回用于取消的id
+    pub async fn send_later<S: Into<MessageChain>>(
+        &self,
+        target: MessageTarget,
+        message: S,
+        delay: Duration,
+    ) -> Result<u64> {
+        self.delayed_sender
+            .send_later(self.rq_client.clone(), target, message, delay)
+            .await
+    }
+
+    /// 定时发送消息, 返回用于取消的id
+    pub async fn send_at<S: Into<MessageChain>, Tz: TimeZone>(
+        &self,
+        target: MessageTarget,
+        message: S,
+        time: DateTime<Tz>,
+    ) -> Result<u64> {
+        self.delayed_sender
+            .send_at(self.rq_client.clone(), target, message, time)
+            .await
+    }
+
+    /// 带有去重key的定时发送, 已经有相同key的消息在等待发送时返回已有消息的id
+    pub async fn send_at_with_key<S: Into<MessageChain>, Tz: TimeZone>(
+        &self,
+        key: impl Into<String>,
+        target: MessageTarget,
+        message: S,
+        time: DateTime<Tz>,
+    ) -> Result<u64> {
+        self.delayed_sender
+            .send_at_with_key(self.rq_client.clone(), key, target, message, time)
+            .await
+    }
+
+    /// 取消延时或定时发送的消息
+    pub async fn cancel_send(&self, id: u64) -> Result<bool> {
+        self.delayed_sender.cancel(id).await
+    }
+
     /// 将session写入Store
     pub async fn write_token_to_store(&self) -> Result<()> {
         if let Some(session_store) = self.session_store.as_deref() {
@@ -60,6 +197,55 @@ impl Client {
             Ok(())
         }
     }
+
+    /// 运行客户端(同`run_client`), signal完成时断开连接并返回.
+    /// 用于将机器人嵌入到已有的服务中, 例如 `client.run_until_shutdown(tokio::signal::ctrl_c().map(|_| ()))`
+    pub async fn run_until_shutdown<F: Future<Output = ()>>(
+        self: Arc<Self>,
+        signal: F,
+    ) -> Result<()> {
+        tokio::select! {
+            result = run_client(self.clone()) => result,
+            _ = signal => {
+                tracing::info!("停止客户端");
+                self.rq_client.stop(NetworkStatus::Stop);
+                Ok(())
+            }
+        }
+    }
+
+    /// 在后台运行客户端(同`run_client`), 不阻塞当前任务.
+    /// 返回的JoinHandle在客户端停止时完成, ClientHandle用于停止客户端.
+    pub fn start_in_background(self: Arc<Self>) -> (JoinHandle<Result<()>>, ClientHandle) {
+        let shutdown = Arc::new(Notify::new());
+        let handle = ClientHandle {
+            client: self.clone(),
+            shutdown: shutdown.clone(),
+        };
+        let join_handle = tokio::spawn(async move {
+            self.run_until_shutdown(async move { shutdown.notified().await })
+                .await
+        });
+        (join_handle, handle)
+    }
+}
+
+/// 在后台运行的客户端, 见`Client::start_in_background`
+#[derive(Clone)]
+pub struct ClientHandle {
+    client: Arc<Client>,
+    shutdown: Arc<Notify>,
+}
+
+impl ClientHandle {
+    pub fn client(&self) -> Arc<Client> {
+        self.client.clone()
+    }
+
+    /// 停止客户端, 断开连接
+    pub fn shutdown(&self) {
+        self.shutdown.notify_one();
+    }
 }
 
 /// 运行客户端，并尽可能的断线重连
@@ -68,57 +254,75 @@ impl Client {
 /// 2. 如果没有设置token存储则使用账号密码或扫码登录
 /// 3. 登录失败则异常退出
 /// 4. 登录成功则保存token，并开始分发事件
-/// 5. 断开连接时停止分发事件, 并尝试使用token再次登录
-/// 6. 如果token失效则使用密码登录并转到4，未设置密码（例如扫码登录）则程序异常退出
+/// 5. 断开连接时停止分发事件, 按`ReconnectPolicy`重新连接, 并尝试使用token再次登录
+/// 6. 如果token失效则按`ReconnectPolicy`使用密码(或扫码)重新登录并转到4, 不允许重新登录时返回错误
 pub async fn run_client(c: Arc<Client>) -> Result<()> {
     // 连接到服务器
     let mut handle = connection(c.clone()).await?;
     // 优先使用token登录
-    if !token_login(c.as_ref()).await {
+    let mut resumed_session = token_login(c.as_ref()).await;
+    if !resumed_session {
         login_authentication(&c).await?;
         c.write_token_to_store().await?;
     }
+    c.delayed_sender.restore(c.rq_client.clone()).await;
+    #[cfg(feature = "scheduler")]
+    crate::features::start_scheduler(&c.rq_client);
+    #[cfg(feature = "metrics")]
+    crate::features::start_metrics_server(&c.rq_client);
     let event_sender = EventSender {
         modules: c.modules.clone(),
         result_handlers: c.result_handlers.clone(),
+        module_toggles: c.module_toggles.clone(),
+        error_handlers: c.error_handlers.clone(),
+        #[cfg(feature = "metrics")]
+        metrics: c.extensions.get::<crate::features::Metrics>(),
     };
     loop {
         // 每次轮询d
         after_login(&c.rq_client.clone()).await;
+        let _ = event_sender
+            .send_account_ready(&account_ready_event(&c.rq_client, resumed_session).await)
+            .await;
         // 直到连接断开
         tracing::info!("开始接收消息");
-        let err = match loop_events(handle, &event_sender).await {
+        let reason = match loop_events(handle, &event_sender).await {
             Ok(_) => {
                 tracing::warn!("连接已断开");
-                anyhow::Error::msg("what's up")
+                "连接已断开".to_owned()
             }
             Err(err) => {
                 tracing::warn!("连接已断开 {:?}", err);
-                err.into()
+                format!("{:?}", err)
             }
         };
-        handle = re_connection(c.clone()).await?;
+        c.reconnect_hooks.disconnected(&c.rq_client, &reason).await;
+        let policy = c.extensions.get_or_insert_with(ReconnectPolicy::default);
+        let (new_handle, attempts) = re_connection(c.clone(), &policy).await?;
+        handle = new_handle;
         tracing::info!("恢复连接");
-        if token_login(c.as_ref()).await {
+        resumed_session = token_login(c.as_ref()).await;
+        if resumed_session {
             tracing::info!("恢复会话");
         } else {
             tracing::warn!("未能恢复会话");
-            let login = match c.authentication {
-                Authentication::UinPassword(_, _) => {
-                    tracing::info!("使用账号密码重新登录");
-                    login_authentication(&c)
-                }
-                Authentication::UinPasswordMd5(_, _) => {
-                    tracing::info!("使用账号密码重新登录");
-                    login_authentication(&c)
-                }
-                _ => {
-                    tracing::error!("当前登录方式不支持重新登录");
-                    return Err(err);
-                }
-            };
-            login.await?;
+            if !policy.can_relogin(&c.authentication) {
+                tracing::error!("当前登录方式不支持重新登录");
+                return Err(anyhow::Error::msg(format!("未能恢复会话 : {}", reason)));
+            }
+            tracing::info!("重新登录");
+            login_authentication(&c).await?;
+            c.write_token_to_store().await?;
         }
+        c.reconnect_hooks
+            .reconnected(
+                &c.rq_client,
+                &ReconnectedInfo {
+                    attempts,
+                    resumed_session,
+                },
+            )
+            .await;
     }
 }
 
@@ -126,7 +330,8 @@ pub async fn run_client_once(client: Arc<Client>) -> Result<()> {
     // connect to server
     let handle = connection(client.clone()).await?;
     // token login if allow and file exists
-    if !token_login(&client).await {
+    let resumed_session = token_login(&client).await;
+    if !resumed_session {
         // authentication if token login failed or not set
         // The error of login failure is fatal
         login_authentication(&client).await?;
@@ -135,24 +340,76 @@ pub async fn run_client_once(client: Arc<Client>) -> Result<()> {
     after_login(&client.rq_client.clone()).await;
     // save session, IO errors are fatal.
     client.write_token_to_store().await?;
+    client
+        .delayed_sender
+        .restore(client.rq_client.clone())
+        .await;
+    #[cfg(feature = "scheduler")]
+    crate::features::start_scheduler(&client.rq_client);
+    #[cfg(feature = "metrics")]
+    crate::features::start_metrics_server(&client.rq_client);
     let event_sender = EventSender {
         modules: client.modules.clone(),
         result_handlers: client.result_handlers.clone(),
+        module_toggles: client.module_toggles.clone(),
+        error_handlers: client.error_handlers.clone(),
+        #[cfg(feature = "metrics")]
+        metrics: client.extensions.get::<crate::features::Metrics>(),
     };
+    let _ = event_sender
+        .send_account_ready(&account_ready_event(&client.rq_client, resumed_session).await)
+        .await;
     loop_events(handle, &event_sender).await
 }
 
-async fn re_connection(client: Arc<Client>) -> Result<JoinHandle<()>> {
+/// 登录使用的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginMethod {
+    /// 使用保存的session
+    Token,
+    /// 使用设置的验证方式 (密码/扫码等)
+    Authentication,
+}
+
+/// 只进行登录, 不分发事件, 用于测试账号和设备能否正常登录.
+/// 登录成功后保存session并断开连接, 失败时返回的错误中包含需要验证(滑块/设备锁)等风控信息.
+pub async fn login_test(client: Arc<Client>) -> Result<LoginMethod> {
+    let handle = connection(client.clone()).await?;
+    let method = if token_login(&client).await {
+        LoginMethod::Token
+    } else {
+        login_authentication(&client).await?;
+        LoginMethod::Authentication
+    };
+    client.write_token_to_store().await?;
+    handle.abort();
+    Ok(method)
+}
+
+/// 按重连策略重新连接, 返回连接和重连的次数
+async fn re_connection(
+    client: Arc<Client>,
+    policy: &ReconnectPolicy,
+) -> Result<(JoinHandle<()>, u32)> {
     let mut times = 0;
     loop {
         times += 1;
-        let d = client.reconnect_duration + Duration::from_secs(min(5, times - 1));
-        tracing::info!("{}秒后进行{}次重连", d.as_secs(), times);
+        if policy.exhausted(times) {
+            return Err(anyhow::Error::msg(format!("重连{}次后仍未成功", times - 1)));
+        }
+        let d = client.reconnect_duration + policy.delay(times);
+        tracing::info!("{}毫秒后进行第{}次重连", d.as_millis(), times);
         sleep(d).await;
-        let res = connection(client.clone()).await;
-        match res {
-            Ok(jh) => return Ok(jh),
-            Err(_) => (),
+        match connection(client.clone()).await {
+            Ok(jh) => {
+                crate::health::health(&client.rq_client).record_reconnect();
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = client.extensions.get::<crate::features::Metrics>() {
+                    metrics.record_reconnect();
+                }
+                return Ok((jh, times));
+            }
+            Err(err) => tracing::warn!("重连失败 : {:?}", err),
         }
     }
 }
@@ -193,6 +450,53 @@ async fn connection(client: Arc<Client>) -> Result<JoinHandle<()>> {
     Ok(handle)
 }
 
+/// 登录后刷新群和好友列表的缓存, 生成AccountReadyEvent
+async fn account_ready_event(
+    rq_client: &Arc<ricq::Client>,
+    resumed_session: bool,
+) -> AccountReadyEvent {
+    let cache = rq_client
+        .extensions()
+        .get_or_insert_with(ContactCache::default);
+    cache.invalidate();
+    let friend_count = match cache.friends(rq_client).await {
+        Ok(friends) => friends.len(),
+        Err(err) => {
+            tracing::warn!("获取好友列表失败 : {:?}", err);
+            0
+        }
+    };
+    let group_count = match cache.groups(rq_client).await {
+        Ok(groups) => {
+            rq_client
+                .extensions()
+                .get_or_insert_with(BotMuteTracker::default)
+                .update_from_groups(&groups);
+            groups.len()
+        }
+        Err(err) => {
+            tracing::warn!("获取群列表失败 : {:?}", err);
+            0
+        }
+    };
+    let event = AccountReadyEvent {
+        client: rq_client.clone(),
+        uin: rq_client.uin().await,
+        nickname: rq_client.account_info.read().await.nickname.clone(),
+        friend_count,
+        group_count,
+        resumed_session,
+    };
+    tracing::info!(
+        "账号已就绪 : {}({}), 好友 {} 个, 群 {} 个",
+        event.nickname,
+        event.uin,
+        event.friend_count,
+        event.group_count,
+    );
+    event
+}
+
 async fn loop_events(handle: JoinHandle<()>, event_sender: &EventSender) -> Result<()> {
     let _ = event_sender.send_connected_and_online().await;
     let result = handle.await;
@@ -460,6 +764,19 @@ async fn loop_login(client: &Client, first: RQResult<LoginResponse>) -> Result<(
                         panic!("not slide");
                     }
                 }
+                #[cfg(feature = "slider_server")]
+                ShowSlider::LocalHttpServer(port) => {
+                    let ticket = crate::features::slider_server::ticket(
+                        port,
+                        verify_url.as_ref().with_context(|| "没有滑动验证地址")?,
+                    )
+                    .await?;
+                    tracing::info!("获取到ticket : {}", ticket);
+                    resp = rq_client
+                        .submit_ticket(&ticket)
+                        .await
+                        .with_context(|| "发送ticket失败")?;
+                }
             },
             LoginResponse::DeviceLockLogin { .. } => {
                 resp = rq_client
@@ -530,10 +847,13 @@ pub fn bytes_to_token(token: Vec<u8>) -> Token {
 /// 用于构建客户端
 pub struct ClientBuilder {
     device_source: DeviceSource,
-    version: &'static Version,
+    pub(crate) version: &'static Version,
     authentication: Option<Authentication>,
     session_store: Arc<Option<Box<dyn SessionStore + Sync + Send>>>,
-    modules_vec: Arc<Vec<Module>>,
+    #[cfg(feature = "session_encryption")]
+    session_key: Option<[u8; 32]>,
+    modules_vec: Vec<Arc<Module>>,
+    module_priorities: HashMap<String, i32>,
     result_handlers_vec: Arc<Vec<EventResultHandler>>,
     show_qr: Option<ShowQR>,
     show_slider: Option<ShowSlider>,
@@ -541,6 +861,24 @@ pub struct ClientBuilder {
     #[cfg(feature = "connect_handler")]
     connect_handler_arc: Arc<Option<Box<dyn ConnectionHandler + Sync + Send>>>,
     reconnect_duration: Duration,
+    masters: Arc<Vec<i64>>,
+    blocklist: Arc<Blocklist>,
+    quotas: Arc<Quotas>,
+    permissions: Arc<Permissions>,
+    audit_log: Arc<AuditLog>,
+    stats: Option<Arc<Stats>>,
+    membership_tracker: Option<Arc<MembershipTracker>>,
+    message_cache: Option<Arc<MessageCache>>,
+    module_toggles: Arc<ModuleToggles>,
+    i18n: Option<Arc<I18n>>,
+    raw_handlers: Vec<Arc<dyn Handler + Send + Sync>>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    error_handlers: Arc<Vec<Arc<dyn ErrorHandler>>>,
+    help_command: bool,
+    ping_command: bool,
+    pub(crate) extensions: Arc<Extensions>,
+    pub(crate) delayed_sender: Arc<DelayedSender>,
+    reconnect_hooks: ReconnectHooks,
 }
 
 impl ClientBuilder {
@@ -551,7 +889,10 @@ impl ClientBuilder {
             version: &ANDROID_PHONE,
             authentication: None,
             session_store: Arc::new(None),
-            modules_vec: Arc::new(vec![]),
+            #[cfg(feature = "session_encryption")]
+            session_key: None,
+            modules_vec: vec![],
+            module_priorities: HashMap::new(),
             result_handlers_vec: Arc::new(vec![]),
             show_qr: None,
             show_slider: None,
@@ -559,15 +900,65 @@ impl ClientBuilder {
             #[cfg(feature = "connect_handler")]
             connect_handler_arc: None.into(),
             reconnect_duration: Duration::from_millis(100),
+            masters: Arc::new(vec![]),
+            blocklist: Arc::new(Blocklist::memory()),
+            quotas: Arc::new(Quotas::memory()),
+            permissions: Arc::new(Permissions::memory()),
+            audit_log: Arc::new(AuditLog::memory()),
+            stats: None,
+            membership_tracker: None,
+            message_cache: None,
+            module_toggles: Arc::new(ModuleToggles::memory()),
+            i18n: None,
+            raw_handlers: vec![],
+            middlewares: vec![],
+            error_handlers: Arc::new(vec![]),
+            help_command: true,
+            ping_command: false,
+            extensions: Arc::new(Extensions::new()),
+            delayed_sender: Arc::new(DelayedSender::memory()),
+            reconnect_hooks: ReconnectHooks::default(),
         }
     }
 
-    /// 设置模块
-    pub fn modules<S: Into<Arc<Vec<Module>>>>(mut self, h: S) -> Self {
-        self.modules_vec = h.into();
+    /// 设置模块, 可以传入`Vec<Module>`或`Vec<Arc<Module>>`.
+    /// 使用Arc时模块不会被复制, 同一组模块实例可以在多个客户端之间共享
+    pub fn modules<I, M>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<Arc<Module>>,
+    {
+        self.modules_vec = modules.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 添加一个模块, 可以与modules一起使用, 优先级相同时按照添加的顺序处理事件
+    ///
+    /// ```ignore
+    /// ClientBuilder::new()
+    ///     .module(menu::module())
+    ///     .module_if(config.enable_imglib, imglib::module)
+    /// ```
+    pub fn module<M: Into<Arc<Module>>>(mut self, module: M) -> Self {
+        self.modules_vec.push(module.into());
+        self
+    }
+
+    /// 覆盖模块的优先级(越大越先处理事件), 用于调整不能修改的模块(例如内置模块和插件)的顺序
+    pub fn module_priority(mut self, module_id: impl Into<String>, priority: i32) -> Self {
+        self.module_priorities.insert(module_id.into(), priority);
         self
     }
 
+    /// condition为true时添加模块, 为false时不会调用f
+    pub fn module_if<M: Into<Arc<Module>>, F: FnOnce() -> M>(self, condition: bool, f: F) -> Self {
+        if condition {
+            self.module(f())
+        } else {
+            self
+        }
+    }
+
     /// 设置事件结果监听器
     pub fn result_handlers<E: Into<Arc<Vec<EventResultHandler>>>>(mut self, e: E) -> Self {
         self.result_handlers_vec = e.into();
@@ -609,38 +1000,39 @@ impl ClientBuilder {
 
     /// 构造客户端
     pub async fn build(&self) -> Result<Client, anyhow::Error> {
-        Ok(Client {
-            rq_client: Arc::new(ricq::Client::new(
-                match &self.device_source {
-                    JsonFile(file_name) => {
-                        if Path::new(file_name).exists() {
-                            parse_device_json(
-                                &tokio::fs::read_to_string(file_name)
-                                    .await
-                                    .with_context(|| format!("读取文件失败 : {}", file_name))?,
-                            )?
-                        } else {
-                            let device = Device::random();
-                            tokio::fs::write(file_name, serde_json::to_string(&device).unwrap())
+        let modules = self.prepare().await?;
+        let rq_client = Arc::new(ricq::Client::new(
+            match &self.device_source {
+                JsonFile(file_name) => {
+                    if Path::new(file_name).exists() {
+                        parse_device_json(
+                            &tokio::fs::read_to_string(file_name)
                                 .await
-                                .with_context(|| format!("写入文件失败 : {}", file_name))?;
-                            device
-                        }
+                                .with_context(|| format!("读取文件失败 : {}", file_name))?,
+                        )?
+                    } else {
+                        let device = Device::random();
+                        tokio::fs::write(file_name, serde_json::to_string(&device).unwrap())
+                            .await
+                            .with_context(|| format!("写入文件失败 : {}", file_name))?;
+                        device
                     }
-                    JsonString(json_string) => parse_device_json(json_string)?,
-                },
-                self.version.clone(),
-                ClientHandler {
-                    modules: self.modules_vec.clone(),
-                    result_handlers: self.result_handlers_vec.clone(),
-                },
-            )),
+                }
+                JsonString(json_string) => parse_device_json(json_string)?,
+            },
+            self.version.clone(),
+            self.client_handler(modules.clone()),
+        ));
+        self.register(&rq_client);
+        Ok(Client {
+            rq_client,
             authentication: self
                 .authentication
                 .clone()
                 .with_context(|| "您必须设置验证方式 (调用authentication)")?,
-            session_store: self.session_store.clone(),
-            modules: self.modules_vec.clone(),
+            session_store: self.build_session_store(),
+            module_manager: self.module_manager(modules.clone()),
+            modules,
             result_handlers: self.result_handlers_vec.clone(),
             show_qr: if self.show_qr.is_some() {
                 self.show_qr.clone().unwrap()
@@ -665,9 +1057,96 @@ impl ClientBuilder {
             #[cfg(feature = "connect_handler")]
             connection_handler: self.connect_handler_arc.clone(),
             reconnect_duration: self.reconnect_duration,
+            masters: self.masters.clone(),
+            blocklist: self.blocklist.clone(),
+            quotas: self.quotas.clone(),
+            permissions: self.permissions.clone(),
+            audit_log: self.audit_log.clone(),
+            stats: self.stats.clone(),
+            membership_tracker: self.membership_tracker.clone(),
+            message_cache: self.message_cache.clone(),
+            module_toggles: self.module_toggles.clone(),
+            extensions: self.extensions.clone(),
+            delayed_sender: self.delayed_sender.clone(),
+            reconnect_hooks: Arc::new(self.reconnect_hooks.clone()),
+            error_handlers: self.error_handlers.clone(),
         })
     }
 
+    /// 加载和注册各个组件, 返回按照优先级排序的模块
+    pub(crate) async fn prepare(&self) -> Result<Arc<Vec<Arc<Module>>>> {
+        self.blocklist.load().await?;
+        self.quotas.load().await?;
+        self.extensions.insert_arc(self.quotas.clone());
+        self.permissions.load(self.masters.clone()).await?;
+        self.extensions.insert_arc(self.permissions.clone());
+        self.audit_log.load().await?;
+        self.extensions.insert_arc(self.audit_log.clone());
+        if let Some(stats) = &self.stats {
+            stats.load().await?;
+            self.extensions.insert_arc(stats.clone());
+        }
+        if let Some(membership_tracker) = &self.membership_tracker {
+            membership_tracker.load().await?;
+            self.extensions.insert_arc(membership_tracker.clone());
+        }
+        if let Some(message_cache) = &self.message_cache {
+            self.extensions.insert_arc(message_cache.clone());
+        }
+        self.module_toggles.load(&self.extensions).await?;
+        self.extensions.insert_arc(self.module_toggles.clone());
+        if let Some(i18n) = &self.i18n {
+            i18n.load().await?;
+            self.extensions.insert_arc(i18n.clone());
+        }
+        self.delayed_sender.load().await?;
+        self.extensions.insert_arc(self.delayed_sender.clone());
+        self.extensions.insert(Masters(self.masters.clone()));
+        self.extensions.insert(crate::Health::default());
+        let mut modules = self.modules_vec.clone();
+        // 稳定排序, 优先级相同时保持添加的顺序
+        modules.sort_by_key(|m| std::cmp::Reverse(effective_priority(&self.module_priorities, m)));
+        Ok(Arc::new(modules))
+    }
+
+    fn module_manager(&self, modules: Arc<Vec<Arc<Module>>>) -> ModuleManager {
+        ModuleManager::new(
+            modules,
+            Arc::new(self.module_priorities.clone()),
+            self.module_toggles.clone(),
+        )
+    }
+
+    pub(crate) fn client_handler(&self, modules: Arc<Vec<Arc<Module>>>) -> ClientHandler {
+        let module_manager = self.module_manager(modules.clone());
+        ClientHandler {
+            modules,
+            result_handlers: self.result_handlers_vec.clone(),
+            blocklist: self.blocklist.clone(),
+            quotas: self.quotas.clone(),
+            permissions: self.permissions.clone(),
+            audit_log: self.audit_log.clone(),
+            stats: self.stats.clone(),
+            membership_tracker: self.membership_tracker.clone(),
+            message_cache: self.message_cache.clone(),
+            module_toggles: self.module_toggles.clone(),
+            module_manager,
+            raw_handlers: Arc::new(self.raw_handlers.clone()),
+            middlewares: Arc::new(self.middlewares.clone()),
+            error_handlers: self.error_handlers.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.extensions.get::<crate::features::Metrics>(),
+            dedup: EventDedup::new(4096),
+            help_command: self.help_command,
+            ping_command: self.ping_command,
+        }
+    }
+
+    pub(crate) fn register(&self, rq_client: &Arc<ricq::Client>) {
+        self.extensions.insert(ClientRef(Arc::downgrade(rq_client)));
+        register_extensions(rq_client, self.extensions.clone());
+    }
+
     /// 设置Device.json的来源
     pub fn device(mut self, device_source: DeviceSource) -> Self {
         self.device_source = device_source;
@@ -686,6 +1165,24 @@ impl ClientBuilder {
         self
     }
 
+    /// 设置加密session的密钥 (32字节), 使用AES-256-GCM加密后再保存到session_store
+    #[cfg(feature = "session_encryption")]
+    pub fn session_encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.session_key = Some(key);
+        self
+    }
+
+    fn build_session_store(&self) -> Arc<Option<Box<dyn SessionStore + Sync + Send>>> {
+        #[cfg(feature = "session_encryption")]
+        if let Some(key) = self.session_key.filter(|_| self.session_store.is_some()) {
+            return Arc::new(Some(Box::new(crate::EncryptedSessionStore::wrap(
+                self.session_store.clone(),
+                key,
+            ))));
+        }
+        self.session_store.clone()
+    }
+
     /// 设置登录方式
     pub fn authentication(mut self, authentication: Authentication) -> Self {
         self.authentication = Some(authentication);
@@ -707,6 +1204,236 @@ impl ClientBuilder {
         self.reconnect_duration = reconnect_duration;
         self
     }
+
+    /// 设置主人, 主人不受黑白名单限制, 可以使用管理指令
+    pub fn masters<S: Into<Vec<i64>>>(mut self, masters: S) -> Self {
+        self.masters = Arc::new(masters.into());
+        self
+    }
+
+    /// 设置黑白名单
+    pub fn blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.blocklist = Arc::new(blocklist);
+        self
+    }
+
+    /// 设置发送消息的限流, 超出限制的消息会等待后再发送
+    pub fn send_throttle(self, throttle: crate::SendThrottle) -> Self {
+        self.extensions.insert(throttle);
+        self
+    }
+
+    /// 设置指令配额, 使用Quotas::file可以在重启后保留计数
+    pub fn quotas(mut self, quotas: Quotas) -> Self {
+        self.quotas = Arc::new(quotas);
+        self
+    }
+
+    /// 设置角色权限, 使用Permissions::file可以保存设置的角色
+    pub fn permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = Arc::new(permissions);
+        self
+    }
+
+    /// 设置审计日志, 使用AuditLog::file可以将记录写入文件
+    pub fn audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Arc::new(audit_log);
+        self
+    }
+
+    /// 开启消息统计
+    pub fn stats(mut self, stats: Stats) -> Self {
+        self.stats = Some(Arc::new(stats));
+        self
+    }
+
+    /// 开启群成员变动记录
+    pub fn membership_tracker(mut self, membership_tracker: MembershipTracker) -> Self {
+        self.membership_tracker = Some(Arc::new(membership_tracker));
+        self
+    }
+
+    /// 开启消息缓存, 缓存最近收到的capacity条消息, 防撤回模块需要开启
+    pub fn message_cache(mut self, message_cache: MessageCache) -> Self {
+        self.message_cache = Some(Arc::new(message_cache));
+        self
+    }
+
+    /// 缓存上传过的图片, 使用`upload_image`上传相同的图片时不再重复上传
+    pub fn image_upload_cache(self, cache: crate::ImageUploadCache) -> Self {
+        self.extensions.insert(cache);
+        self
+    }
+
+    /// 设置模块开关, 使用ModuleToggles::file可以保存开关状态
+    pub fn module_toggles(mut self, module_toggles: ModuleToggles) -> Self {
+        self.module_toggles = Arc::new(module_toggles);
+        self
+    }
+
+    /// 设置多语言文本和默认语言, 使用I18n::file可以保存每个群设置的语言
+    pub fn i18n(mut self, i18n: I18n) -> Self {
+        self.i18n = Some(Arc::new(i18n));
+        self
+    }
+
+    /// 添加一个ricq的Handler, 接收所有原始事件(包括proc_qq没有封装的事件).
+    /// 先于模块调用, 不受黑白名单和拦截的影响
+    pub fn raw_handler<H: Handler + Send + Sync + 'static>(mut self, handler: H) -> Self {
+        self.raw_handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// 设置自定义权限的判断, 用于`#[require("权限名称")]`
+    pub fn permission_provider<P: crate::PermissionProvider + 'static>(self, provider: P) -> Self {
+        self.extensions
+            .insert::<Box<dyn crate::PermissionProvider>>(Box::new(provider));
+        self
+    }
+
+    /// 添加一个中间件, 在原始的Handler之后、模块之前调用, 按照添加的顺序组成调用链
+    pub fn middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// 添加一个错误处理器, 模块的处理器返回错误或panic时调用, 可以用来通知管理员或统一回复
+    pub fn error_handler<H: ErrorHandler + 'static>(mut self, handler: H) -> Self {
+        Arc::make_mut(&mut self.error_handlers).push(Arc::new(handler));
+        self
+    }
+
+    /// 是否开启内置的`/help [指令]`, 默认开启. 列出设置了bot_command的处理器, 会隐藏本群关闭的功能和角色不足的指令
+    pub fn help_command(mut self, help_command: bool) -> Self {
+        self.help_command = help_command;
+        self
+    }
+
+    /// 是否开启内置的`/ping`, 默认关闭. 回复心跳耗时、最近发送的耗时和失败率以及重连次数
+    pub fn ping_command(mut self, ping_command: bool) -> Self {
+        self.ping_command = ping_command;
+        self
+    }
+
+    /// 设置上传图片前的预处理 (缩放/压缩/去除EXIF)
+    #[cfg(feature = "image_preprocess")]
+    pub fn image_preprocess(self, image_preprocess: crate::ImagePreprocess) -> Self {
+        self.extensions.insert(image_preprocess);
+        self
+    }
+
+    /// 设置长消息的处理策略, 超过长度的纯文字消息打包成合并转发或拆分发送
+    pub fn long_message_policy(self, policy: crate::LongMessagePolicy) -> Self {
+        self.extensions.insert(policy);
+        self
+    }
+
+    /// 设置群列表和好友列表的缓存时间, 按名称查找群或好友时使用
+    pub fn contact_cache(self, contact_cache: crate::ContactCache) -> Self {
+        self.extensions.insert(contact_cache);
+        self
+    }
+
+    /// 设置向禁言中的群发送消息时的处理方式, 默认照常发送
+    pub fn muted_send_policy(self, policy: crate::MutedSendPolicy) -> Self {
+        self.extensions.insert(policy);
+        self
+    }
+
+    /// 设置唤醒词, 群中以唤醒词开头的消息可以触发`#[event(at_me)]`
+    pub fn wake_words(self, wake_words: crate::WakeWords) -> Self {
+        self.extensions.insert(wake_words);
+        self
+    }
+
+    /// 设置定时任务, 第一次登录成功后开始运行
+    #[cfg(feature = "scheduler")]
+    pub fn scheduler(self, scheduler: crate::Scheduler) -> Self {
+        self.extensions.insert(scheduler);
+        self
+    }
+
+    /// 开启Prometheus指标, 设置了地址时会提供`/metrics`接口
+    #[cfg(feature = "metrics")]
+    pub fn metrics(self, metrics: crate::features::Metrics) -> Self {
+        self.extensions.insert(metrics);
+        self
+    }
+
+    /// 记录收到的消息、通知和发送的消息, 可以在处理器中查询
+    #[cfg(feature = "event_recorder")]
+    pub fn event_recorder(self, recorder: crate::features::EventRecorder) -> Self {
+        self.extensions.insert(recorder);
+        self
+    }
+
+    /// 设置消息发送失败时的降级策略
+    pub fn send_fallback(self, send_fallback: crate::SendFallback) -> Self {
+        self.extensions.insert(send_fallback);
+        self
+    }
+
+    /// 设置断线重连策略, 未设置时使用`ReconnectPolicy::default()`
+    pub fn reconnect_policy(self, policy: ReconnectPolicy) -> Self {
+        self.extensions.insert(policy);
+        self
+    }
+
+    /// 连接断开时调用, 参数为断开的原因
+    pub fn on_disconnect<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Arc<ricq::Client>, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.reconnect_hooks.push_disconnect(hook);
+        self
+    }
+
+    /// 断线后重新连接并登录成功时调用
+    pub fn on_reconnected<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Arc<ricq::Client>, ReconnectedInfo) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.reconnect_hooks.push_reconnected(hook);
+        self
+    }
+
+    /// 设置延时发送, 使用DelayedSender::file可以在重启后恢复未发送的消息
+    pub fn delayed_sender(mut self, delayed_sender: DelayedSender) -> Self {
+        self.delayed_sender = Arc::new(delayed_sender);
+        self
+    }
+
+    /// 设置模块使用的键值存储, 模块中通过`event.module_store()`取得
+    pub fn store<S: crate::ModuleStore + 'static>(self, store: S) -> Self {
+        self.extensions
+            .insert(crate::module_store::ClientStore(Arc::new(store)));
+        self
+    }
+
+    /// 设置账号的名称, 同时运行多个账号时用于区分 (见`MultiClient`)
+    pub fn bot_name(self, name: impl Into<String>) -> Self {
+        self.extensions.insert(crate::BotName(name.into()));
+        self
+    }
+
+    /// 预先放入一个扩展, 可以在事件中通过`extensions()`取得
+    pub fn extension<T: Send + Sync + 'static>(self, value: T) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+}
+
+/// ClientBuilder::masters设置的主人列表, 构造客户端时放入扩展容器.
+/// 不包含通过`/perm`设置为Master的用户, 判断主人请使用`Permissions::is_master`
+#[derive(Debug, Clone, Default)]
+pub struct Masters(pub Arc<Vec<i64>>);
+
+impl Masters {
+    pub fn contains(&self, uin: i64) -> bool {
+        self.0.contains(&uin)
+    }
 }
 
 fn parse_device_json(json: &str) -> Result<Device, anyhow::Error> {
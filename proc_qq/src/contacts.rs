@@ -0,0 +1,209 @@
+use crate::ExtensionsTrait;
+use ricq::structs::{FriendInfo, GroupInfo};
+use ricq_core::RQResult;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 按名称查找群或好友时的匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameMatch {
+    /// 名称相同 (忽略大小写和空白)
+    Exact,
+    /// 名称包含关键词
+    Contains,
+    /// 关键词的字符按顺序出现在名称中即可, 例如 "测群" 可以找到 "测试群"
+    Fuzzy,
+    /// 在Fuzzy的基础上比较拼音, 例如 "ceshi" "cs" 可以找到 "测试群"
+    #[cfg(feature = "pinyin")]
+    Pinyin,
+}
+
+/// 群列表和好友列表的缓存, 按名称查找时使用, 超过ttl后重新获取.
+/// 默认缓存5分钟, 可以使用`ClientBuilder::contact_cache`设置
+pub struct ContactCache {
+    ttl: Duration,
+    groups: Mutex<Option<(Instant, Arc<Vec<GroupInfo>>)>>,
+    friends: Mutex<Option<(Instant, Arc<Vec<FriendInfo>>)>>,
+}
+
+impl Default for ContactCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+impl ContactCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            groups: Mutex::new(None),
+            friends: Mutex::new(None),
+        }
+    }
+
+    /// 清空缓存, 下次查找时重新获取
+    pub fn invalidate(&self) {
+        *self.groups.lock().unwrap() = None;
+        *self.friends.lock().unwrap() = None;
+    }
+
+    pub async fn groups(&self, client: &ricq::Client) -> RQResult<Arc<Vec<GroupInfo>>> {
+        if let Some((time, groups)) = self.groups.lock().unwrap().as_ref() {
+            if time.elapsed() < self.ttl {
+                return Ok(groups.clone());
+            }
+        }
+        let groups = Arc::new(client.get_group_list().await?);
+        *self.groups.lock().unwrap() = Some((Instant::now(), groups.clone()));
+        Ok(groups)
+    }
+
+    pub async fn friends(&self, client: &ricq::Client) -> RQResult<Arc<Vec<FriendInfo>>> {
+        if let Some((time, friends)) = self.friends.lock().unwrap().as_ref() {
+            if time.elapsed() < self.ttl {
+                return Ok(friends.clone());
+            }
+        }
+        let friends = Arc::new(client.get_friend_list().await?.friends);
+        *self.friends.lock().unwrap() = Some((Instant::now(), friends.clone()));
+        Ok(friends)
+    }
+}
+
+/// 按名称查找群, 结果按匹配程度从高到低排列
+pub(crate) async fn find_groups(
+    client: &ricq::Client,
+    name: &str,
+    mode: NameMatch,
+) -> RQResult<Vec<GroupInfo>> {
+    let cache = client
+        .extensions()
+        .get_or_insert_with(ContactCache::default);
+    let groups = cache.groups(client).await?;
+    Ok(rank(groups.iter(), name, mode, |group| vec![&group.name]))
+}
+
+/// 按名称查找好友, 同时匹配备注和昵称, 结果按匹配程度从高到低排列
+pub(crate) async fn find_friends(
+    client: &ricq::Client,
+    name: &str,
+    mode: NameMatch,
+) -> RQResult<Vec<FriendInfo>> {
+    let cache = client
+        .extensions()
+        .get_or_insert_with(ContactCache::default);
+    let friends = cache.friends(client).await?;
+    Ok(rank(friends.iter(), name, mode, |friend| {
+        vec![&friend.remark, &friend.nick]
+    }))
+}
+
+fn rank<'a, T: Clone + 'a, F: Fn(&T) -> Vec<&String>>(
+    items: impl Iterator<Item = &'a T>,
+    name: &str,
+    mode: NameMatch,
+    names: F,
+) -> Vec<T> {
+    let query = normalize(name);
+    if query.is_empty() {
+        return vec![];
+    }
+    let mut scored: Vec<(u32, &T)> = items
+        .filter_map(|item| {
+            names(item)
+                .into_iter()
+                .filter_map(|candidate| score(&normalize(candidate), &query, mode))
+                .max()
+                .map(|score| (score, item))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().map(|(_, item)| item.clone()).collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// 分数越高越匹配, 不匹配时返回None
+fn score(candidate: &str, query: &str, mode: NameMatch) -> Option<u32> {
+    if candidate.is_empty() {
+        return None;
+    }
+    if candidate == query {
+        return Some(1000);
+    }
+    if mode == NameMatch::Exact {
+        return None;
+    }
+    if candidate.starts_with(query) {
+        return Some(800);
+    }
+    if candidate.contains(query) {
+        return Some(600);
+    }
+    if mode == NameMatch::Contains {
+        return None;
+    }
+    if let Some(score) = subsequence_score(candidate, query) {
+        return Some(score);
+    }
+    #[cfg(feature = "pinyin")]
+    if mode == NameMatch::Pinyin {
+        return pinyin_score(candidate, query);
+    }
+    None
+}
+
+// 按顺序出现, 字符间隔越小分数越高
+fn subsequence_score(candidate: &str, query: &str) -> Option<u32> {
+    let mut chars = candidate.chars().enumerate();
+    let mut gaps = 0;
+    let mut last: Option<usize> = None;
+    for q in query.chars() {
+        let (idx, _) = chars.find(|(_, c)| *c == q)?;
+        if let Some(last) = last {
+            gaps += idx - last - 1;
+        }
+        last = Some(idx);
+    }
+    Some(400u32.saturating_sub(gaps as u32 * 10).max(100))
+}
+
+#[cfg(feature = "pinyin")]
+fn pinyin_score(candidate: &str, query: &str) -> Option<u32> {
+    use pinyin::ToPinyin;
+    let mut full = String::new();
+    let mut initials = String::new();
+    for (c, py) in candidate.chars().zip(candidate.to_pinyin()) {
+        match py {
+            Some(py) => {
+                full.push_str(py.plain());
+                initials.push_str(py.first_letter());
+            }
+            None => {
+                full.push(c);
+                initials.push(c);
+            }
+        }
+    }
+    // 关键词中的汉字也转为拼音, 可以找到同音字
+    let query: String = query
+        .chars()
+        .zip(query.to_pinyin())
+        .map(|(c, py)| py.map(|py| py.plain().to_owned()).unwrap_or(c.to_string()))
+        .collect();
+    if full == query || initials == query {
+        return Some(500);
+    }
+    if full.starts_with(&query) || initials.starts_with(&query) {
+        return Some(350);
+    }
+    if full.contains(&query) || initials.contains(&query) {
+        return Some(250);
+    }
+    subsequence_score(&initials, &query).map(|score| score / 4)
+}
@@ -0,0 +1,124 @@
+use crate::{ClientTrait, ExtensionsTrait, MessageEvent, MessageTarget, MessageTargetTrait};
+use async_trait::async_trait;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq_core::msg::MessageChain;
+use ricq_core::RQResult;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// 正在等待回复的会话, 调度器在过滤之后、模块之前把消息交给等待中的会话.
+/// 被会话接收的消息不会再传递给模块
+#[derive(Default)]
+pub struct Conversations {
+    waiters: Mutex<Vec<(MessageTarget, oneshot::Sender<MessageEvent>)>>,
+}
+
+impl Conversations {
+    fn wait(&self, target: MessageTarget) -> oneshot::Receiver<MessageEvent> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiters.lock().unwrap().push((target, sender));
+        receiver
+    }
+
+    /// 有会话在等待这个用户时把消息交给会话, 返回true
+    pub(crate) fn offer(&self, event: &MessageEvent) -> bool {
+        let target = event.target();
+        let mut waiters = self.waiters.lock().unwrap();
+        // 超时的会话
+        waiters.retain(|(_, sender)| !sender.is_closed());
+        while let Some(idx) = waiters.iter().position(|(t, _)| *t == target) {
+            let (_, sender) = waiters.remove(idx);
+            if sender.send(event.clone()).is_ok() {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 等待中的会话数量
+    pub fn waiting(&self) -> usize {
+        self.waiters.lock().unwrap().len()
+    }
+}
+
+/// 等待同一个用户在同一个群 (或私聊) 中的下一条消息, 用于确认、问答等多步对话
+///
+/// ```ignore
+/// #[event(bot_command = "/reset")]
+/// async fn reset(event: &MessageEvent) -> anyhow::Result<bool> {
+///     let answer = event
+///         .prompt("确定要重置吗? (是/否)".parse_message_chain(), Duration::from_secs(30))
+///         .await?;
+///     match answer {
+///         Some(answer) if answer.message_content().trim() == "是" => { /* ... */ }
+///         Some(_) => { /* 取消 */ }
+///         None => { /* 超时 */ }
+///     }
+///     Ok(true)
+/// }
+/// ```
+#[async_trait]
+pub trait ConversationTrait: MessageTargetTrait + ClientTrait + Sized {
+    fn conversation_client(&self) -> &ricq::Client;
+
+    /// 等待发送者的下一条消息, 超时返回None
+    async fn next_message_from_sender(&self, timeout: Duration) -> Option<MessageEvent> {
+        let receiver = self
+            .conversation_client()
+            .extensions()
+            .get_or_insert_with(Conversations::default)
+            .wait(self.target());
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(event)) => Some(event),
+            _ => None,
+        }
+    }
+
+    /// 发送消息到消息来源, 然后等待发送者的回复
+    async fn prompt<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        message: S,
+        timeout: Duration,
+    ) -> RQResult<Option<MessageEvent>> {
+        // 先开始等待, 以免在发送完成前收到回复
+        let receiver = self
+            .conversation_client()
+            .extensions()
+            .get_or_insert_with(Conversations::default)
+            .wait(self.target());
+        self.send_message_to_target(self, message).await?;
+        Ok(match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(event)) => Some(event),
+            _ => None,
+        })
+    }
+}
+
+impl ConversationTrait for GroupMessageEvent {
+    fn conversation_client(&self) -> &ricq::Client {
+        &self.client
+    }
+}
+
+impl ConversationTrait for FriendMessageEvent {
+    fn conversation_client(&self) -> &ricq::Client {
+        &self.client
+    }
+}
+
+impl ConversationTrait for GroupTempMessageEvent {
+    fn conversation_client(&self) -> &ricq::Client {
+        &self.client
+    }
+}
+
+impl ConversationTrait for MessageEvent {
+    fn conversation_client(&self) -> &ricq::Client {
+        match self {
+            MessageEvent::GroupMessage(e) => &e.client,
+            MessageEvent::FriendMessage(e) => &e.client,
+            MessageEvent::GroupTempMessage(e) => &e.client,
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use crate::persist::{load_json, save_json};
+use crate::{ClientTrait, MessageTarget};
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+use ricq_core::msg::MessageChain;
+use ricq_core::pb::msg;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// 等待发送的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingSend {
+    pub id: u64,
+    pub target: MessageTarget,
+    /// 发送时间 (unix毫秒)
+    pub send_at: i64,
+    /// 去重的key, 相同key的消息同时只会有一条在等待发送
+    #[serde(default)]
+    pub key: Option<String>,
+    /// 已经失败的次数
+    #[serde(default)]
+    pub attempts: u32,
+    /// protobuf编码后的消息元素
+    elements: Vec<Vec<u8>>,
+}
+
+impl PendingSend {
+    pub fn message_chain(&self) -> Result<MessageChain> {
+        decode_chain(&self.elements)
+    }
+}
+
+pub(crate) fn encode_chain(chain: &MessageChain) -> Vec<Vec<u8>> {
+    chain
+        .0
+        .iter()
+        .map(|elem| {
+            msg::Elem {
+                elem: Some(elem.clone()),
+            }
+            .encode_to_vec()
+        })
+        .collect()
+}
+
+pub(crate) fn decode_chain(elements: &[Vec<u8>]) -> Result<MessageChain> {
+    let mut chain = MessageChain::default();
+    for buff in elements {
+        if let Some(elem) = msg::Elem::decode(buff.as_slice())?.elem {
+            chain.0.push(elem);
+        }
+    }
+    Ok(chain)
+}
+
+/// 延时发送
+///
+/// 设置了文件时, 未发送的消息会被保存, 重启后登录成功时恢复.
+/// 消息在发送成功后才会从队列中移除, 发送失败时会重试, 因此在崩溃或重启时可能重复发送 (至少一次).
+/// 使用`send_at_with_key`可以避免同一条提醒或公告被重复加入队列.
+/// 登录成功之前加入的消息会在登录成功后才开始等待发送.
+pub struct DelayedSender {
+    path: Option<String>,
+    max_attempts: u32,
+    retry_interval: Duration,
+    next_id: AtomicU64,
+    restored: AtomicBool,
+    pending: Mutex<HashMap<u64, PendingSend>>,
+    /// 已经有任务在等待发送的消息id
+    running: std::sync::Mutex<HashSet<u64>>,
+}
+
+impl DelayedSender {
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            max_attempts: 5,
+            retry_interval: Duration::from_secs(30),
+            next_id: AtomicU64::new(1),
+            restored: AtomicBool::new(false),
+            pending: Mutex::new(HashMap::new()),
+            running: std::sync::Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    /// 发送失败时最多尝试的次数 (默认5次), 第n次失败后等待n*retry_interval再重试
+    pub fn retry(mut self, max_attempts: u32, retry_interval: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    pub(crate) async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let list: Vec<PendingSend> = load_json(path).await?;
+            let mut pending = self.pending.lock().await;
+            for item in list {
+                self.next_id.fetch_max(item.id + 1, Ordering::SeqCst);
+                pending.insert(item.id, item);
+            }
+        }
+        Ok(())
+    }
+
+    async fn save(&self, pending: &HashMap<u64, PendingSend>) -> Result<()> {
+        if let Some(path) = &self.path {
+            let list: Vec<&PendingSend> = pending.values().collect();
+            save_json(path, &list).await?;
+        }
+        Ok(())
+    }
+
+    /// 登录成功后恢复未发送的消息 (文件中的和登录前加入的), 只会执行一次
+    pub(crate) async fn restore(self: &Arc<Self>, client: Arc<ricq::Client>) {
+        if self.restored.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let list: Vec<PendingSend> = self.pending.lock().await.values().cloned().collect();
+        for item in list {
+            self.spawn(client.clone(), item);
+        }
+    }
+
+    /// 延时发送, 返回用于取消的id
+    pub async fn send_later<S: Into<MessageChain>>(
+        self: &Arc<Self>,
+        client: Arc<ricq::Client>,
+        target: MessageTarget,
+        message: S,
+        delay: Duration,
+    ) -> Result<u64> {
+        self.send_at(
+            client,
+            target,
+            message,
+            Utc::now() + chrono::Duration::from_std(delay)?,
+        )
+        .await
+    }
+
+    /// 定时发送, 返回用于取消的id
+    pub async fn send_at<S: Into<MessageChain>, Tz: TimeZone>(
+        self: &Arc<Self>,
+        client: Arc<ricq::Client>,
+        target: MessageTarget,
+        message: S,
+        time: DateTime<Tz>,
+    ) -> Result<u64> {
+        self.enqueue(
+            client,
+            None,
+            target,
+            message.into(),
+            time.timestamp_millis(),
+        )
+        .await
+    }
+
+    /// 带有去重key的定时发送, 已经有相同key的消息在等待发送时不会再次加入, 返回已有消息的id
+    pub async fn send_at_with_key<S: Into<MessageChain>, Tz: TimeZone>(
+        self: &Arc<Self>,
+        client: Arc<ricq::Client>,
+        key: impl Into<String>,
+        target: MessageTarget,
+        message: S,
+        time: DateTime<Tz>,
+    ) -> Result<u64> {
+        self.enqueue(
+            client,
+            Some(key.into()),
+            target,
+            message.into(),
+            time.timestamp_millis(),
+        )
+        .await
+    }
+
+    async fn enqueue(
+        self: &Arc<Self>,
+        client: Arc<ricq::Client>,
+        key: Option<String>,
+        target: MessageTarget,
+        message: MessageChain,
+        send_at: i64,
+    ) -> Result<u64> {
+        let item = {
+            let mut pending = self.pending.lock().await;
+            if let Some(key) = &key {
+                if let Some(exists) = pending.values().find(|item| item.key.as_ref() == Some(key)) {
+                    return Ok(exists.id);
+                }
+            }
+            let item = PendingSend {
+                id: self.next_id.fetch_add(1, Ordering::SeqCst),
+                target,
+                send_at,
+                key,
+                attempts: 0,
+                elements: encode_chain(&message),
+            };
+            pending.insert(item.id, item.clone());
+            self.save(&pending).await?;
+            item
+        };
+        let id = item.id;
+        // 登录前加入的消息由restore开始发送
+        if self.restored.load(Ordering::SeqCst) {
+            self.spawn(client, item);
+        }
+        Ok(id)
+    }
+
+    /// 取消还未发送的消息
+    pub async fn cancel(&self, id: u64) -> Result<bool> {
+        let mut pending = self.pending.lock().await;
+        let removed = pending.remove(&id).is_some();
+        if removed {
+            self.save(&pending).await?;
+        }
+        Ok(removed)
+    }
+
+    /// 取消key对应的还未发送的消息
+    pub async fn cancel_key(&self, key: &str) -> Result<bool> {
+        let mut pending = self.pending.lock().await;
+        let id = pending
+            .values()
+            .find(|item| item.key.as_deref() == Some(key))
+            .map(|item| item.id);
+        match id {
+            Some(id) => {
+                pending.remove(&id);
+                self.save(&pending).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// 所有等待发送的消息
+    pub async fn pending(&self) -> Vec<PendingSend> {
+        self.pending.lock().await.values().cloned().collect()
+    }
+
+    /// 每条消息同时只会有一个任务在等待发送
+    fn spawn(self: &Arc<Self>, client: Arc<ricq::Client>, item: PendingSend) {
+        let id = item.id;
+        if !self.running.lock().unwrap().insert(id) {
+            return;
+        }
+        let sender = self.clone();
+        tokio::spawn(async move {
+            sender.run(client, item).await;
+            sender.running.lock().unwrap().remove(&id);
+        });
+    }
+
+    async fn run(&self, client: Arc<ricq::Client>, mut item: PendingSend) {
+        loop {
+            let wait = item.send_at - Utc::now().timestamp_millis();
+            if wait > 0 {
+                tokio::time::sleep(Duration::from_millis(wait as u64)).await;
+            }
+            // 已经被取消
+            if !self.pending.lock().await.contains_key(&item.id) {
+                return;
+            }
+            let chain = match item.message_chain() {
+                Ok(chain) => chain,
+                Err(err) => {
+                    tracing::warn!("延时消息解析失败 ({}) : {:?}", item.id, err);
+                    break;
+                }
+            };
+            match client.send_message_to_target(&item.target, chain).await {
+                Ok(_) => break,
+                Err(err) => {
+                    item.attempts += 1;
+                    if item.attempts >= self.max_attempts {
+                        tracing::error!(
+                            "延时消息发送失败 ({}), 已重试{}次, 放弃发送 : {:?}",
+                            item.id,
+                            item.attempts,
+                            err
+                        );
+                        break;
+                    }
+                    tracing::warn!(
+                        "延时消息发送失败 ({}), 第{}次 : {:?}",
+                        item.id,
+                        item.attempts,
+                        err
+                    );
+                    item.send_at = Utc::now().timestamp_millis()
+                        + (self.retry_interval * item.attempts).as_millis() as i64;
+                    // 记录失败次数, 重启后继续重试
+                    let mut pending = self.pending.lock().await;
+                    if !pending.contains_key(&item.id) {
+                        return;
+                    }
+                    pending.insert(item.id, item.clone());
+                    if let Err(err) = self.save(&pending).await {
+                        tracing::warn!("延时消息保存失败 : {:?}", err);
+                    }
+                }
+            }
+        }
+        let mut pending = self.pending.lock().await;
+        pending.remove(&item.id);
+        if let Err(err) = self.save(&pending).await {
+            tracing::warn!("延时消息保存失败 : {:?}", err);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::testing::MockClient;
+    use crate::{ClientBuilder, MessageChainParseTrait};
+
+    #[tokio::test]
+    async fn enqueued_before_restore_is_sent_once() -> Result<()> {
+        let client = MockClient::build(&ClientBuilder::new()).await?;
+        let sender = Arc::new(DelayedSender::memory());
+        sender
+            .send_later(
+                client.rq_client.clone(),
+                MessageTarget::Private(12345),
+                "提醒".parse_message_chain(),
+                Duration::from_millis(10),
+            )
+            .await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(client.take_sent_messages().is_empty());
+
+        sender.restore(client.rq_client.clone()).await;
+        sender.restore(client.rq_client.clone()).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let sent = client.take_sent_messages();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].text(), "提醒");
+        assert!(sender.pending().await.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::ImageElement;
+use anyhow::{anyhow, Result};
+use bytes::{Bytes, BytesMut};
+use std::path::Path;
+use std::time::Duration;
+
+/// 下载的选项
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// 失败后的重试次数
+    pub retries: u32,
+    /// 最大字节数, 超过时放弃下载
+    pub max_bytes: usize,
+    /// 每次请求的超时时间
+    pub timeout: Duration,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            max_bytes: 30 * 1024 * 1024,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl DownloadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+enum DownloadError {
+    // 可以重试的错误
+    Retry(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+/// 下载文件, 网络错误和服务器错误会重试, 超过大小限制时直接返回错误
+pub async fn download_bytes(url: &str, options: &DownloadOptions) -> Result<Bytes> {
+    let client = reqwest::Client::builder()
+        .timeout(options.timeout)
+        .build()?;
+    let mut attempt = 0;
+    loop {
+        match download_once(&client, url, options.max_bytes).await {
+            Ok(data) => return Ok(data),
+            Err(DownloadError::Retry(err)) if attempt < options.retries => {
+                attempt += 1;
+                tracing::debug!("下载失败, 第{}次重试 : {} : {:?}", attempt, url, err);
+                tokio::time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+            }
+            Err(DownloadError::Retry(err)) | Err(DownloadError::Fatal(err)) => return Err(err),
+        }
+    }
+}
+
+async fn download_once(
+    client: &reqwest::Client,
+    url: &str,
+    max_bytes: usize,
+) -> std::result::Result<Bytes, DownloadError> {
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| DownloadError::Retry(err.into()))?;
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(DownloadError::Retry(anyhow!("下载失败 : {}", status)));
+    }
+    if !status.is_success() {
+        return Err(DownloadError::Fatal(anyhow!("下载失败 : {}", status)));
+    }
+    let too_large = || DownloadError::Fatal(anyhow!("文件超过大小限制 : {} 字节", max_bytes));
+    if response.content_length().unwrap_or(0) as usize > max_bytes {
+        return Err(too_large());
+    }
+    let mut buff = BytesMut::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|err| DownloadError::Retry(err.into()))?
+    {
+        if buff.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        buff.extend_from_slice(&chunk);
+    }
+    Ok(buff.freeze())
+}
+
+impl ImageElement {
+    /// 下载图片原图
+    pub async fn download(&self) -> Result<Bytes> {
+        self.download_with(&DownloadOptions::default()).await
+    }
+
+    pub async fn download_with(&self, options: &DownloadOptions) -> Result<Bytes> {
+        download_bytes(&self.url(), options).await
+    }
+
+    /// 下载图片并保存到文件
+    pub async fn save_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        let data = self.download().await?;
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+}
@@ -7,7 +7,10 @@ use std::sync::Arc;
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
+use ricq::structs::{FriendAudio, GroupAudio};
 use ricq_core::msg::elem::{FlashImage, FriendImage, GroupImage};
+use ricq_core::pb::msg::Ptt;
+use ricq_core::RQResult;
 
 use crate::DeviceSource::JsonFile;
 
@@ -83,6 +86,10 @@ pub enum ShowSlider {
 
     #[cfg(all(any(target_os = "windows"), feature = "pop_window_slider"))]
     PopWindow,
+
+    /// 在端口上启动网页, 在其他设备的浏览器中完成滑动后提交ticket, 适合没有桌面的服务器
+    #[cfg(feature = "slider_server")]
+    LocalHttpServer(u16),
 }
 
 #[derive(Clone)]
@@ -158,6 +165,7 @@ macro_rules! image_element_get {
 image_element_get!(width, u32);
 image_element_get!(height, u32);
 image_element_get!(size, u32);
+image_element_get!(image_type, i32);
 
 impl ImageElement {
     pub fn url(&self) -> String {
@@ -182,6 +190,11 @@ impl ImageElement {
         }
     }
 
+    /// 服务器标记的图片类型是否为GIF, 不需要下载. 是否为动图需要下载后使用`ImageData::is_animated`判断
+    pub fn is_gif(&self) -> bool {
+        self.image_type() == 2000
+    }
+
     pub fn is_flash(&self) -> bool {
         match self {
             ImageElement::FlashImage(_) => true,
@@ -269,3 +282,100 @@ impl ImageElement {
         }
     }
 }
+
+/// 收到的语音
+#[derive(Debug, Clone)]
+pub enum VoiceElement {
+    /// (群号, 语音)
+    GroupAudio(i64, GroupAudio),
+    /// (发送者, 语音)
+    FriendAudio(i64, FriendAudio),
+}
+
+impl VoiceElement {
+    pub fn ptt(&self) -> &Ptt {
+        match self {
+            VoiceElement::GroupAudio(_, audio) => &audio.0,
+            VoiceElement::FriendAudio(_, audio) => &audio.0,
+        }
+    }
+
+    pub fn md5(&self) -> Vec<u8> {
+        self.ptt().file_md5.clone().unwrap_or_default()
+    }
+
+    pub fn file_name(&self) -> String {
+        self.ptt().file_name.clone().unwrap_or_default()
+    }
+
+    pub fn size(&self) -> i32 {
+        self.ptt().file_size.unwrap_or_default()
+    }
+
+    /// 语音的编码, 0为amr, 1为silk
+    pub fn format(&self) -> i32 {
+        self.ptt().format.unwrap_or_default()
+    }
+
+    pub fn is_group(&self) -> bool {
+        matches!(self, VoiceElement::GroupAudio(..))
+    }
+
+    pub fn is_friend(&self) -> bool {
+        matches!(self, VoiceElement::FriendAudio(..))
+    }
+
+    /// 取得下载地址
+    pub async fn url(&self, client: &ricq::Client) -> RQResult<String> {
+        match self {
+            VoiceElement::GroupAudio(group_code, audio) => {
+                client.get_group_audio_url(*group_code, audio.clone()).await
+            }
+            VoiceElement::FriendAudio(sender, audio) => {
+                client.get_friend_audio_url(*sender, audio.clone()).await
+            }
+        }
+    }
+
+    /// 下载语音, 数据为silk或amr格式(见`format`)
+    pub async fn download(&self, client: &ricq::Client) -> Result<Bytes> {
+        let url = self.url(client).await?;
+        crate::download_bytes(&url, &crate::DownloadOptions::default()).await
+    }
+}
+
+/// 下载的图片数据
+///
+/// 再次上传时请直接使用`data`, 不要解码后重新编码, 否则GIF只会保留第一帧
+#[derive(Debug, Clone)]
+pub struct ImageData {
+    pub data: Bytes,
+}
+
+impl ImageData {
+    pub fn is_gif(&self) -> bool {
+        crate::gif::is_gif(&self.data)
+    }
+
+    /// 是否为多帧的GIF动图
+    pub fn is_animated(&self) -> bool {
+        crate::gif::is_animated_gif(&self.data)
+    }
+
+    /// GIF的帧数, 其他格式返回None
+    pub fn frame_count(&self) -> Option<usize> {
+        crate::gif::gif_frame_count(&self.data)
+    }
+}
+
+impl From<Bytes> for ImageData {
+    fn from(data: Bytes) -> Self {
+        Self { data }
+    }
+}
+
+impl AsRef<[u8]> for ImageData {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
@@ -0,0 +1,142 @@
+use crate::MessageEvent;
+use ricq::client::event::EventWithClient;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock, Weak};
+
+/// 以类型为键的并发容器
+///
+/// 每个客户端拥有一个, 可以在任意带有客户端的事件中取得, 用于模块之间共享状态.
+#[derive(Default)]
+pub struct Extensions {
+    map: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 放入一个值, 返回被替换的旧值
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<Arc<T>> {
+        self.insert_arc(Arc::new(value))
+    }
+
+    /// 放入一个Arc, 返回被替换的旧值
+    pub fn insert_arc<T: Send + Sync + 'static>(&self, value: Arc<T>) -> Option<Arc<T>> {
+        self.map
+            .write()
+            .unwrap()
+            .insert(TypeId::of::<T>(), value)
+            .and_then(|old| old.downcast::<T>().ok())
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.map
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|value| value.downcast::<T>().ok())
+    }
+
+    /// 取得值, 不存在时使用f创建并放入
+    pub fn get_or_insert_with<T: Send + Sync + 'static, F: FnOnce() -> T>(&self, f: F) -> Arc<T> {
+        if let Some(value) = self.get::<T>() {
+            return value;
+        }
+        let mut map = self.map.write().unwrap();
+        map.entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(f()))
+            .clone()
+            .downcast::<T>()
+            .unwrap()
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.map
+            .write()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|old| old.downcast::<T>().ok())
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.read().unwrap().contains_key(&TypeId::of::<T>())
+    }
+}
+
+/// 构造客户端时放入扩展, 用于在只有引用时取得客户端的Arc (延时发送需要)
+pub(crate) struct ClientRef(pub(crate) Weak<ricq::Client>);
+
+// ricq的客户端无法携带自定义数据, 这里以客户端的地址找到对应的容器.
+// 同时保存客户端的弱引用, 客户端释放后条目失效, 地址被新的客户端复用时不会取到旧的容器.
+
+struct Registered {
+    client: Weak<ricq::Client>,
+    extensions: Arc<Extensions>,
+}
+
+fn registry() -> &'static RwLock<HashMap<usize, Registered>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<usize, Registered>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn client_key(client: &ricq::Client) -> usize {
+    client as *const ricq::Client as usize
+}
+
+pub(crate) fn register_extensions(client: &Arc<ricq::Client>, extensions: Arc<Extensions>) {
+    let mut registry = registry().write().unwrap();
+    // 顺便清理已经释放的客户端
+    registry.retain(|_, registered| registered.client.strong_count() > 0);
+    registry.insert(
+        client_key(client),
+        Registered {
+            client: Arc::downgrade(client),
+            extensions,
+        },
+    );
+}
+
+pub trait ExtensionsTrait {
+    fn extensions(&self) -> Arc<Extensions>;
+}
+
+impl ExtensionsTrait for ricq::Client {
+    /// 不是由ClientBuilder创建的客户端没有容器, 返回一个不会被保存的空容器
+    fn extensions(&self) -> Arc<Extensions> {
+        registry()
+            .read()
+            .unwrap()
+            .get(&client_key(self))
+            .filter(|registered| registered.client.strong_count() > 0)
+            .map(|registered| registered.extensions.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ExtensionsTrait for crate::Client {
+    fn extensions(&self) -> Arc<Extensions> {
+        self.extensions.clone()
+    }
+}
+
+/// 构建前与客户端共用同一个容器, 例如在构建前取得`module_store_for`
+impl ExtensionsTrait for crate::ClientBuilder {
+    fn extensions(&self) -> Arc<Extensions> {
+        self.extensions.clone()
+    }
+}
+
+impl<T> ExtensionsTrait for EventWithClient<T> {
+    fn extensions(&self) -> Arc<Extensions> {
+        self.client.extensions()
+    }
+}
+
+impl ExtensionsTrait for MessageEvent {
+    fn extensions(&self) -> Arc<Extensions> {
+        self.client().extensions()
+    }
+}
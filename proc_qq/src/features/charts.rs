@@ -0,0 +1,166 @@
+use crate::{Stats, StatsRange};
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use plotters::prelude::*;
+use std::io::Cursor;
+
+/// 图表的样式
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+    pub width: u32,
+    pub height: u32,
+    /// 字体, 需要显示中文时请设置为系统中存在的中文字体
+    pub font: String,
+    pub color: RGBColor,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 480,
+            font: "sans-serif".to_owned(),
+            color: RGBColor(0x4c, 0x8b, 0xf5),
+        }
+    }
+}
+
+fn encode_png(buffer: Vec<u8>, options: &ChartOptions) -> Result<Vec<u8>> {
+    let image = image::RgbImage::from_raw(options.width, options.height, buffer)
+        .ok_or_else(|| anyhow::anyhow!("图表缓冲区大小错误"))?;
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(image)
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
+/// 柱状图, 返回PNG, 可以直接用于`upload_image_to_source`
+pub fn render_bar_chart(
+    title: &str,
+    items: &[(String, u64)],
+    options: &ChartOptions,
+) -> Result<Vec<u8>> {
+    let mut buffer = vec![0u8; (options.width * options.height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (options.width, options.height))
+            .into_drawing_area();
+        root.fill(&WHITE)?;
+        let max = items.iter().map(|(_, v)| *v).max().unwrap_or(0) + 1;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (options.font.as_str(), 24))
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d((0..items.len()).into_segmented(), 0..max)?;
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .x_labels(items.len().max(1))
+            .x_label_formatter(&|v| match v {
+                SegmentValue::CenterOf(i) => items
+                    .get(*i)
+                    .map(|(label, _)| label.clone())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .label_style((options.font.as_str(), 14))
+            .draw()?;
+        chart.draw_series(
+            Histogram::vertical(&chart)
+                .style(options.color.filled())
+                .margin(6)
+                .data(items.iter().enumerate().map(|(i, (_, v))| (i, *v))),
+        )?;
+        root.present()?;
+    }
+    encode_png(buffer, options)
+}
+
+/// 折线图, 横轴为时间, 返回PNG
+pub fn render_line_chart(
+    title: &str,
+    points: &[(DateTime<Utc>, u64)],
+    options: &ChartOptions,
+) -> Result<Vec<u8>> {
+    let mut buffer = vec![0u8; (options.width * options.height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (options.width, options.height))
+            .into_drawing_area();
+        root.fill(&WHITE)?;
+        let now = Utc::now().timestamp();
+        let start = points.first().map(|(t, _)| t.timestamp()).unwrap_or(now);
+        let end = points
+            .last()
+            .map(|(t, _)| t.timestamp())
+            .unwrap_or(now)
+            .max(start + 3600);
+        let max = points.iter().map(|(_, v)| *v).max().unwrap_or(0) + 1;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (options.font.as_str(), 24))
+            .margin(12)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d(start..end, 0..max)?;
+        chart
+            .configure_mesh()
+            .x_label_formatter(&|v| {
+                Local
+                    .timestamp_opt(*v, 0)
+                    .single()
+                    .map(|t| t.format("%m-%d %H:00").to_string())
+                    .unwrap_or_default()
+            })
+            .label_style((options.font.as_str(), 14))
+            .draw()?;
+        chart.draw_series(LineSeries::new(
+            points.iter().map(|(t, v)| (t.timestamp(), *v)),
+            options.color.stroke_width(2),
+        ))?;
+        root.present()?;
+    }
+    encode_png(buffer, options)
+}
+
+impl Stats {
+    /// 群活跃排行的柱状图
+    pub fn top_users_chart(
+        &self,
+        group_code: i64,
+        range: StatsRange,
+        limit: usize,
+        options: &ChartOptions,
+    ) -> Result<Vec<u8>> {
+        let items: Vec<(String, u64)> = self
+            .top_users(group_code, range)
+            .into_iter()
+            .take(limit)
+            .map(|(uin, count)| (uin.to_string(), count))
+            .collect();
+        render_bar_chart("活跃排行", &items, options)
+    }
+
+    /// 每小时消息数量的折线图
+    pub fn messages_per_hour_chart(
+        &self,
+        group_code: Option<i64>,
+        range: StatsRange,
+        options: &ChartOptions,
+    ) -> Result<Vec<u8>> {
+        render_line_chart(
+            "消息数量",
+            &self.messages_per_hour(group_code, range),
+            options,
+        )
+    }
+
+    /// 指令使用次数的柱状图
+    pub fn command_usage_chart(
+        &self,
+        range: StatsRange,
+        limit: usize,
+        options: &ChartOptions,
+    ) -> Result<Vec<u8>> {
+        let items: Vec<(String, u64)> = self.command_usage(range).into_iter().take(limit).collect();
+        render_bar_chart("指令使用", &items, options)
+    }
+}
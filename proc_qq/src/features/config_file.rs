@@ -0,0 +1,177 @@
+use crate::{Authentication, ClientBuilder, DeviceSource, FileSessionStore, ShowQR};
+use anyhow::{anyhow, Context, Result};
+use ricq_core::protocol::version::{Version, ANDROID_PHONE, ANDROID_WATCH, IPAD, MACOS};
+use serde_derive::Deserialize;
+use std::path::Path;
+
+/// 配置文件的内容, 支持toml和yaml (按照扩展名判断)
+///
+/// ```toml
+/// # qr | password | password_md5, 不填写时有密码使用密码登录, 否则扫码
+/// authentication = "password"
+/// uin = 123456
+/// password = "******"
+/// device = "device.json"
+/// session = "session.token"
+/// # phone | watch | ipad | macos
+/// protocol = "ipad"
+/// # console | file | system
+/// show_qr = "console"
+/// ```
+///
+/// 每一项都可以使用环境变量覆盖, 例如 PROC_QQ_UIN PROC_QQ_PASSWORD PROC_QQ_PASSWORD_MD5
+/// PROC_QQ_AUTHENTICATION PROC_QQ_DEVICE PROC_QQ_SESSION PROC_QQ_PROTOCOL PROC_QQ_SHOW_QR
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    pub authentication: Option<String>,
+    pub uin: Option<i64>,
+    pub password: Option<String>,
+    /// 密码的md5, 32位十六进制
+    pub password_md5: Option<String>,
+    /// device.json的路径
+    pub device: Option<String>,
+    /// 保存session的文件
+    pub session: Option<String>,
+    pub protocol: Option<String>,
+    pub show_qr: Option<String>,
+}
+
+const ENV_PREFIX: &str = "PROC_QQ_";
+
+impl BotConfig {
+    /// 读取配置文件, 并使用环境变量覆盖
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("读取配置文件失败 : {}", path.display()))?;
+        let mut config: BotConfig = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text)
+                .with_context(|| format!("解析配置文件失败 : {}", path.display()))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text)
+                .with_context(|| format!("解析配置文件失败 : {}", path.display()))?,
+            _ => return Err(anyhow!("配置文件只支持toml和yaml : {}", path.display())),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// 使用环境变量覆盖配置
+    pub fn apply_env(&mut self) -> Result<()> {
+        let env = |name: &str| std::env::var(format!("{}{}", ENV_PREFIX, name)).ok();
+        if let Some(uin) = env("UIN") {
+            self.uin = Some(
+                uin.parse()
+                    .with_context(|| format!("{}UIN 不是QQ号 : {}", ENV_PREFIX, uin))?,
+            );
+        }
+        let strings = [
+            ("AUTHENTICATION", &mut self.authentication),
+            ("PASSWORD", &mut self.password),
+            ("PASSWORD_MD5", &mut self.password_md5),
+            ("DEVICE", &mut self.device),
+            ("SESSION", &mut self.session),
+            ("PROTOCOL", &mut self.protocol),
+            ("SHOW_QR", &mut self.show_qr),
+        ];
+        for (name, value) in strings {
+            if let Some(env_value) = env(name) {
+                *value = Some(env_value);
+            }
+        }
+        Ok(())
+    }
+
+    fn authentication(&self) -> Result<Authentication> {
+        let uin = || self.uin.with_context(|| "配置文件中没有设置uin");
+        let method = match self.authentication.as_deref() {
+            Some(method) => method,
+            None if self.password_md5.is_some() => "password_md5",
+            None if self.password.is_some() => "password",
+            None => "qr",
+        };
+        match method {
+            "qr" => Ok(Authentication::QRCode),
+            "password" => Ok(Authentication::UinPassword(
+                uin()?,
+                self.password
+                    .clone()
+                    .with_context(|| "配置文件中没有设置password")?,
+            )),
+            "password_md5" => Ok(Authentication::UinPasswordMd5(
+                uin()?,
+                parse_md5(
+                    self.password_md5
+                        .as_deref()
+                        .with_context(|| "配置文件中没有设置password_md5")?,
+                )?,
+            )),
+            _ => Err(anyhow!(
+                "登录方式只能为 qr password password_md5 : {}",
+                method
+            )),
+        }
+    }
+
+    /// 将配置设置到ClientBuilder中
+    pub fn apply(&self, builder: ClientBuilder) -> Result<ClientBuilder> {
+        let mut builder = builder.authentication(self.authentication()?);
+        if let Some(device) = &self.device {
+            builder = builder.device(DeviceSource::JsonFile(device.clone()));
+        }
+        if let Some(session) = &self.session {
+            builder = builder.session_store(FileSessionStore::boxed(session.clone()));
+        }
+        if let Some(protocol) = &self.protocol {
+            builder = builder.version(protocol_version(protocol)?);
+        }
+        if let Some(show_qr) = &self.show_qr {
+            builder = builder.show_rq(Some(match show_qr.as_str() {
+                #[cfg(feature = "console_qr")]
+                "console" => ShowQR::PrintToConsole,
+                "file" => ShowQR::SaveToFile,
+                "system" => ShowQR::OpenBySystem,
+                _ => return Err(anyhow!("不支持的二维码显示方式 : {}", show_qr)),
+            }));
+        }
+        Ok(builder)
+    }
+}
+
+/// 协议的名称: phone watch ipad macos
+pub fn protocol_version(name: &str) -> Result<&'static Version> {
+    match name {
+        "phone" => Ok(&ANDROID_PHONE),
+        "watch" => Ok(&ANDROID_WATCH),
+        "ipad" => Ok(&IPAD),
+        "macos" => Ok(&MACOS),
+        _ => Err(anyhow!("未知的协议 : {}", name)),
+    }
+}
+
+fn parse_md5(hex: &str) -> Result<[u8; 16]> {
+    let hex = hex.trim();
+    if hex.len() != 32 || !hex.is_ascii() {
+        return Err(anyhow!("password_md5 必须是32位十六进制"));
+    }
+    let mut md5 = [0u8; 16];
+    for (i, byte) in md5.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .with_context(|| "password_md5 必须是32位十六进制")?;
+    }
+    Ok(md5)
+}
+
+impl ClientBuilder {
+    /// 从配置文件(toml或yaml)读取登录方式、设备、session、协议和二维码的显示方式, 见`BotConfig`
+    ///
+    /// ```ignore
+    /// let client = ClientBuilder::from_config_file("bot.toml")?
+    ///     .modules(modules)
+    ///     .build()
+    ///     .await?;
+    /// ```
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self> {
+        BotConfig::load(path)?.apply(ClientBuilder::new())
+    }
+}
@@ -0,0 +1,542 @@
+use crate::{ExtensionsTrait, MessageChainModel, MessageContentTrait, MessageTarget};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use ricq::handler::QEvent;
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use serde_derive::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// 收到的事件或机器人发送的消息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl RecordDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordDirection::Incoming => "incoming",
+            RecordDirection::Outgoing => "outgoing",
+        }
+    }
+}
+
+/// 一条事件记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// unix毫秒
+    pub time: i64,
+    pub direction: RecordDirection,
+    /// 事件类型, 如`group_message` `friend_message` `group_recall` `group_mute`
+    pub kind: String,
+    pub group_code: Option<i64>,
+    /// 消息的发送者, 通知中为被操作的成员
+    pub uin: Option<i64>,
+    /// 机器人发送的私聊消息和戳一戳的接收者
+    pub target_uin: Option<i64>,
+    /// 通知的操作者
+    pub operator_uin: Option<i64>,
+    pub seqs: Vec<i32>,
+    pub message: Option<MessageChainModel>,
+    /// 消息的文字内容, 或者通知的说明 (如申请的验证消息)
+    pub content: String,
+}
+
+impl EventRecord {
+    fn new(direction: RecordDirection, kind: &str) -> Self {
+        Self {
+            time: Utc::now().timestamp_millis(),
+            direction,
+            kind: kind.to_owned(),
+            group_code: None,
+            uin: None,
+            target_uin: None,
+            operator_uin: None,
+            seqs: vec![],
+            message: None,
+            content: String::new(),
+        }
+    }
+
+    fn with_message(mut self, seqs: &[i32], chain: &MessageChain) -> Self {
+        self.seqs = seqs.to_vec();
+        self.content = chain.message_content();
+        self.message = Some(MessageChainModel::from(chain));
+        self
+    }
+
+    /// 收到的消息和通知, 登录和连接状态等事件返回None
+    pub fn from_event(event: &QEvent) -> Option<Self> {
+        let incoming = RecordDirection::Incoming;
+        Some(match event {
+            QEvent::GroupMessage(event) => {
+                let inner = &event.inner;
+                let mut record =
+                    Self::new(incoming, "group_message").with_message(&inner.seqs, &inner.elements);
+                record.group_code = Some(inner.group_code);
+                record.uin = Some(inner.from_uin);
+                record
+            }
+            QEvent::FriendMessage(event) => {
+                let inner = &event.inner;
+                let mut record = Self::new(incoming, "friend_message")
+                    .with_message(&inner.seqs, &inner.elements);
+                record.uin = Some(inner.from_uin);
+                record
+            }
+            QEvent::GroupTempMessage(event) => {
+                let inner = &event.inner;
+                let mut record = Self::new(incoming, "group_temp_message")
+                    .with_message(&inner.seqs, &inner.elements);
+                record.group_code = Some(inner.group_code);
+                record.uin = Some(inner.from_uin);
+                record
+            }
+            QEvent::GroupMessageRecall(event) => {
+                let inner = &event.inner;
+                let mut record = Self::new(incoming, "group_recall");
+                record.group_code = Some(inner.group_code);
+                record.uin = Some(inner.author_uin);
+                record.operator_uin = Some(inner.operator_uin);
+                record.seqs = vec![inner.msg_seq];
+                record
+            }
+            QEvent::FriendMessageRecall(event) => {
+                let inner = &event.inner;
+                let mut record = Self::new(incoming, "friend_recall");
+                record.uin = Some(inner.friend_uin);
+                record.seqs = vec![inner.msg_seq];
+                record
+            }
+            QEvent::NewMember(event) => {
+                let mut record = Self::new(incoming, "group_increase");
+                record.group_code = Some(event.inner.group_code);
+                record.uin = Some(event.inner.member_uin);
+                record
+            }
+            QEvent::GroupLeave(event) => {
+                let inner = &event.inner;
+                let mut record = Self::new(incoming, "group_decrease");
+                record.group_code = Some(inner.group_code);
+                record.uin = Some(inner.member_uin);
+                record.operator_uin = inner.operator_uin;
+                record
+            }
+            QEvent::GroupMute(event) => {
+                let inner = &event.inner;
+                let mut record = Self::new(incoming, "group_mute");
+                record.group_code = Some(inner.group_code);
+                record.uin = Some(inner.target_uin);
+                record.operator_uin = Some(inner.operator_uin);
+                record.content = if inner.duration.is_zero() {
+                    "解除禁言".to_owned()
+                } else {
+                    format!("禁言{}秒", inner.duration.as_secs())
+                };
+                record
+            }
+            QEvent::NewFriend(event) => {
+                let mut record = Self::new(incoming, "friend_add");
+                record.uin = Some(event.inner.uin);
+                record
+            }
+            QEvent::GroupPoke(event) => {
+                let mut record = Self::new(incoming, "group_poke");
+                record.group_code = Some(event.inner.group_code);
+                record.uin = Some(event.inner.sender);
+                record.target_uin = Some(event.inner.receiver);
+                record
+            }
+            QEvent::FriendPoke(event) => {
+                let mut record = Self::new(incoming, "friend_poke");
+                record.uin = Some(event.inner.sender);
+                record.target_uin = Some(event.inner.receiver);
+                record
+            }
+            QEvent::NewFriendRequest(event) => {
+                let mut record = Self::new(incoming, "friend_request");
+                record.uin = Some(event.inner.req_uin);
+                record.content = event.inner.message.clone();
+                record
+            }
+            QEvent::GroupRequest(event) => {
+                let mut record = Self::new(incoming, "group_request");
+                record.group_code = Some(event.inner.group_code);
+                record.uin = Some(event.inner.req_uin);
+                record.content = event.inner.message.clone();
+                record
+            }
+            _ => return None,
+        })
+    }
+
+    /// 机器人发送的消息
+    pub fn outgoing(
+        bot_uin: i64,
+        target: MessageTarget,
+        receipt: &MessageReceipt,
+        chain: &MessageChain,
+    ) -> Self {
+        let mut record =
+            Self::new(RecordDirection::Outgoing, "").with_message(&receipt.seqs, chain);
+        record.uin = Some(bot_uin);
+        match target {
+            MessageTarget::Group(group_code, _) => {
+                record.kind = "group_message".to_owned();
+                record.group_code = Some(group_code);
+            }
+            MessageTarget::Private(uin) => {
+                record.kind = "friend_message".to_owned();
+                record.target_uin = Some(uin);
+            }
+            MessageTarget::GroupTemp(group_code, uin) => {
+                record.kind = "group_temp_message".to_owned();
+                record.group_code = Some(group_code);
+                record.target_uin = Some(uin);
+            }
+        }
+        record
+    }
+}
+
+/// 查询条件, 结果按时间从新到旧排列
+///
+/// ```ignore
+/// // 昨天以来某人在群中说过的话
+/// let records = recorder
+///     .query(
+///         RecordQuery::new()
+///             .group_code(group_code)
+///             .uin(uin)
+///             .kind("group_message")
+///             .since(Utc::now() - chrono::Duration::days(1)),
+///     )
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordQuery {
+    pub group_code: Option<i64>,
+    pub uin: Option<i64>,
+    pub kind: Option<String>,
+    pub direction: Option<RecordDirection>,
+    /// unix毫秒, 包含
+    pub since: Option<i64>,
+    /// unix毫秒, 不包含
+    pub until: Option<i64>,
+    /// 内容中包含的文字
+    pub keyword: Option<String>,
+    pub limit: usize,
+}
+
+impl Default for RecordQuery {
+    fn default() -> Self {
+        Self {
+            group_code: None,
+            uin: None,
+            kind: None,
+            direction: None,
+            since: None,
+            until: None,
+            keyword: None,
+            limit: 100,
+        }
+    }
+}
+
+impl RecordQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn group_code(mut self, group_code: i64) -> Self {
+        self.group_code = Some(group_code);
+        self
+    }
+
+    pub fn uin(mut self, uin: i64) -> Self {
+        self.uin = Some(uin);
+        self
+    }
+
+    pub fn kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+
+    pub fn direction(mut self, direction: RecordDirection) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    pub fn since<Tz: TimeZone>(mut self, time: DateTime<Tz>) -> Self {
+        self.since = Some(time.timestamp_millis());
+        self
+    }
+
+    pub fn until<Tz: TimeZone>(mut self, time: DateTime<Tz>) -> Self {
+        self.until = Some(time.timestamp_millis());
+        self
+    }
+
+    pub fn keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.keyword = Some(keyword.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        (self.group_code.is_none() || record.group_code == self.group_code)
+            && (self.uin.is_none() || record.uin == self.uin)
+            && self.kind.as_ref().map_or(true, |kind| &record.kind == kind)
+            && self
+                .direction
+                .map_or(true, |direction| record.direction == direction)
+            && self.since.map_or(true, |since| record.time >= since)
+            && self.until.map_or(true, |until| record.time < until)
+            && self
+                .keyword
+                .as_ref()
+                .map_or(true, |keyword| record.content.contains(keyword.as_str()))
+    }
+}
+
+/// 事件记录的存储
+#[async_trait]
+pub trait EventRecordStore: Send + Sync {
+    async fn append(&self, record: &EventRecord) -> Result<()>;
+    async fn query(&self, query: &RecordQuery) -> Result<Vec<EventRecord>>;
+}
+
+/// 以JSON Lines追加写入文件, 查询时读取整个文件, 适合数据量较小的场景
+pub struct JsonLinesRecordStore {
+    path: String,
+    lock: Mutex<()>,
+}
+
+impl JsonLinesRecordStore {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl EventRecordStore for JsonLinesRecordStore {
+    async fn append(&self, record: &EventRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let _lock = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn query(&self, query: &RecordQuery) -> Result<Vec<EventRecord>> {
+        let content = {
+            let _lock = self.lock.lock().await;
+            if !tokio::fs::try_exists(&self.path).await? {
+                return Ok(vec![]);
+            }
+            tokio::fs::read_to_string(&self.path).await?
+        };
+        let mut records = vec![];
+        for line in content.lines().rev().filter(|line| !line.trim().is_empty()) {
+            if records.len() >= query.limit {
+                break;
+            }
+            match serde_json::from_str::<EventRecord>(line) {
+                Ok(record) if query.matches(&record) => records.push(record),
+                Ok(_) => (),
+                Err(err) => tracing::warn!("事件记录解析失败 : {:?}", err),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// 使用sqlite保存的事件记录
+#[cfg(feature = "event_recorder_sqlite")]
+pub struct SqliteRecordStore {
+    connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+}
+
+#[cfg(feature = "event_recorder_sqlite")]
+impl SqliteRecordStore {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS event_records (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                time INTEGER NOT NULL,
+                direction TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                group_code INTEGER,
+                uin INTEGER,
+                content TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS event_records_group ON event_records (group_code, time);
+            CREATE INDEX IF NOT EXISTS event_records_uin ON event_records (uin, time);",
+        )?;
+        Ok(Self {
+            connection: std::sync::Arc::new(std::sync::Mutex::new(connection)),
+        })
+    }
+
+    /// sqlite的调用是阻塞的, 放到阻塞线程中执行
+    async fn with_connection<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&rusqlite::Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || f(&connection.lock().unwrap())).await?
+    }
+}
+
+#[cfg(feature = "event_recorder_sqlite")]
+#[async_trait]
+impl EventRecordStore for SqliteRecordStore {
+    async fn append(&self, record: &EventRecord) -> Result<()> {
+        let data = serde_json::to_string(record)?;
+        let record = record.clone();
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT INTO event_records (time, direction, kind, group_code, uin, content, data)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    record.time,
+                    record.direction.as_str(),
+                    record.kind,
+                    record.group_code,
+                    record.uin,
+                    record.content,
+                    data,
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn query(&self, query: &RecordQuery) -> Result<Vec<EventRecord>> {
+        use rusqlite::types::Value;
+        let mut conditions = vec![];
+        let mut values: Vec<Value> = vec![];
+        if let Some(group_code) = query.group_code {
+            conditions.push("group_code = ?");
+            values.push(Value::Integer(group_code));
+        }
+        if let Some(uin) = query.uin {
+            conditions.push("uin = ?");
+            values.push(Value::Integer(uin));
+        }
+        if let Some(kind) = &query.kind {
+            conditions.push("kind = ?");
+            values.push(Value::Text(kind.clone()));
+        }
+        if let Some(direction) = query.direction {
+            conditions.push("direction = ?");
+            values.push(Value::Text(direction.as_str().to_owned()));
+        }
+        if let Some(since) = query.since {
+            conditions.push("time >= ?");
+            values.push(Value::Integer(since));
+        }
+        if let Some(until) = query.until {
+            conditions.push("time < ?");
+            values.push(Value::Integer(until));
+        }
+        if let Some(keyword) = &query.keyword {
+            conditions.push("instr(content, ?) > 0");
+            values.push(Value::Text(keyword.clone()));
+        }
+        let mut sql = "SELECT data FROM event_records".to_owned();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY time DESC, id DESC LIMIT ?");
+        values.push(Value::Integer(query.limit as i64));
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(&sql)?;
+            let rows = statement.query_map(rusqlite::params_from_iter(values), |row| {
+                row.get::<_, String>(0)
+            })?;
+            let mut records = vec![];
+            for data in rows {
+                records.push(serde_json::from_str(&data?)?);
+            }
+            Ok(records)
+        })
+        .await
+    }
+}
+
+/// 事件记录, 使用`ClientBuilder::event_recorder`开启
+///
+/// 保存收到的消息和通知(撤回、禁言、进退群、戳一戳、好友和加群申请)以及机器人发送的消息,
+/// 消息以`MessageChainModel`序列化保存. 处理器中可以通过`event.extensions().get::<EventRecorder>()`查询.
+pub struct EventRecorder {
+    store: Box<dyn EventRecordStore>,
+}
+
+impl EventRecorder {
+    pub fn new<S: EventRecordStore + 'static>(store: S) -> Self {
+        Self {
+            store: Box::new(store),
+        }
+    }
+
+    /// 以JSON Lines保存到文件
+    pub fn file(path: impl Into<String>) -> Self {
+        Self::new(JsonLinesRecordStore::new(path))
+    }
+
+    /// 保存到sqlite, 数据量较大时使用
+    #[cfg(feature = "event_recorder_sqlite")]
+    pub fn sqlite(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Ok(Self::new(SqliteRecordStore::open(path)?))
+    }
+
+    /// 写入一条记录, 失败时只打印日志
+    pub async fn record(&self, record: EventRecord) {
+        if let Err(err) = self.store.append(&record).await {
+            tracing::warn!("事件记录写入失败 : {:?}", err);
+        }
+    }
+
+    pub async fn query(&self, query: RecordQuery) -> Result<Vec<EventRecord>> {
+        self.store.query(&query).await
+    }
+}
+
+/// 记录收到的事件, 没有开启事件记录时什么都不做
+pub(crate) async fn record_incoming_event(event: &QEvent) {
+    let client = match super::qevent_client(event) {
+        Some(client) => client,
+        None => return,
+    };
+    let recorder = match client.extensions().get::<EventRecorder>() {
+        Some(recorder) => recorder,
+        None => return,
+    };
+    if let Some(record) = EventRecord::from_event(event) {
+        recorder.record(record).await;
+    }
+}
@@ -0,0 +1,92 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageError, ImageFormat};
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+
+/// 图片格式转换失败
+#[derive(Debug)]
+pub enum ImageConvertError {
+    /// 无法识别的格式
+    UnknownFormat,
+    /// 没有开启对应解码器的格式 (例如没有开启`image_convert_avif`时的AVIF)
+    Unsupported(ImageFormat),
+    Decode(ImageError),
+    Encode(ImageError),
+}
+
+impl Display for ImageConvertError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageConvertError::UnknownFormat => write!(f, "无法识别的图片格式"),
+            ImageConvertError::Unsupported(format) => write!(f, "不支持的图片格式 : {:?}", format),
+            ImageConvertError::Decode(err) => write!(f, "图片解码失败 : {}", err),
+            ImageConvertError::Encode(err) => write!(f, "图片编码失败 : {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ImageConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageConvertError::Decode(err) | ImageConvertError::Encode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// 是否为可以直接上传的格式
+pub fn is_upload_supported(format: ImageFormat) -> bool {
+    matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif
+    )
+}
+
+/// 将WebP、AVIF、BMP等格式转为PNG或JPEG, 已经是JPEG、PNG、GIF时返回None
+///
+/// 带透明通道或者无损的BMP转为PNG, 其他转为JPEG. 动态的WebP只保留第一帧.
+pub fn convert_image(data: &[u8]) -> Result<Option<Vec<u8>>, ImageConvertError> {
+    let format = image::guess_format(data).map_err(|_| ImageConvertError::UnknownFormat)?;
+    if is_upload_supported(format) {
+        return Ok(None);
+    }
+    let img = image::load_from_memory_with_format(data, format).map_err(|err| match err {
+        ImageError::Unsupported(_) => ImageConvertError::Unsupported(format),
+        err => ImageConvertError::Decode(err),
+    })?;
+    let mut buff = Vec::new();
+    if img.color().has_alpha() || format == ImageFormat::Bmp {
+        img.write_to(&mut Cursor::new(&mut buff), ImageFormat::Png)
+            .map_err(ImageConvertError::Encode)?;
+    } else {
+        JpegEncoder::new_with_quality(&mut buff, 90)
+            .encode_image(&DynamicImage::ImageRgb8(img.to_rgb8()))
+            .map_err(ImageConvertError::Encode)?;
+    }
+    Ok(Some(buff))
+}
+
+/// 上传前转换格式, 可以直接上传的图片不会复制
+pub(crate) async fn convert_upload_image(
+    data: Cow<'_, [u8]>,
+) -> Result<Cow<'_, [u8]>, ImageConvertError> {
+    match image::guess_format(&data) {
+        Ok(format) if !is_upload_supported(format) => {}
+        // 无法识别的数据交给服务器处理
+        _ => return Ok(data),
+    }
+    let owned = data.to_vec();
+    let result = tokio::task::spawn_blocking(move || convert_image(&owned))
+        .await
+        .map_err(|err| {
+            ImageConvertError::Decode(ImageError::IoError(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                err,
+            )))
+        })?;
+    Ok(match result? {
+        Some(converted) => Cow::Owned(converted),
+        None => data,
+    })
+}
@@ -0,0 +1,153 @@
+use crate::ExtensionsTrait;
+use anyhow::Result;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use ricq_core::{RQError, RQResult};
+use std::borrow::Cow;
+use std::io::Cursor;
+
+/// 上传图片前的预处理
+///
+/// 通过`ClientBuilder::image_preprocess`设置后, `upload_image_to_source`会先进行处理:
+/// 超过最大尺寸时等比缩小, 去除EXIF等元数据, 超过最大字节数时转为JPEG并降低质量.
+/// 不需要处理的图片和GIF动图会原样上传.
+#[derive(Debug, Clone)]
+pub struct ImagePreprocess {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub jpeg_quality: u8,
+    pub max_bytes: usize,
+    pub strip_metadata: bool,
+}
+
+impl Default for ImagePreprocess {
+    fn default() -> Self {
+        Self {
+            max_width: 4096,
+            max_height: 4096,
+            jpeg_quality: 85,
+            max_bytes: 10 * 1024 * 1024,
+            strip_metadata: true,
+        }
+    }
+}
+
+impl ImagePreprocess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_dimensions(mut self, max_width: u32, max_height: u32) -> Self {
+        self.max_width = max_width;
+        self.max_height = max_height;
+        self
+    }
+
+    pub fn jpeg_quality(mut self, jpeg_quality: u8) -> Self {
+        self.jpeg_quality = jpeg_quality.clamp(1, 100);
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn strip_metadata(mut self, strip_metadata: bool) -> Self {
+        self.strip_metadata = strip_metadata;
+        self
+    }
+
+    /// 处理图片, 不需要处理时返回None
+    pub fn process(&self, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        // 解码后只剩第一帧, 动图保持原样
+        if crate::is_animated_gif(data) {
+            return Ok(None);
+        }
+        let format = image::guess_format(data)?;
+        let oversize = data.len() > self.max_bytes;
+        let metadata = self.strip_metadata && has_metadata(data, format);
+        let (width, height) =
+            image::io::Reader::with_format(Cursor::new(data), format).into_dimensions()?;
+        let too_large = width > self.max_width || height > self.max_height;
+        if !oversize && !metadata && !too_large {
+            return Ok(None);
+        }
+        let mut img = image::load_from_memory_with_format(data, format)?;
+        if too_large {
+            img = img.resize(self.max_width, self.max_height, FilterType::Lanczos3);
+        }
+        // PNG保持无损, 仍然过大时才转为JPEG
+        if format == ImageFormat::Png {
+            let png = encode_png(&img)?;
+            if png.len() <= self.max_bytes {
+                return Ok(Some(png));
+            }
+        }
+        let mut quality = self.jpeg_quality;
+        loop {
+            let jpeg = encode_jpeg(&img, quality)?;
+            if jpeg.len() <= self.max_bytes {
+                return Ok(Some(jpeg));
+            }
+            if quality > 40 {
+                quality -= 15;
+            } else {
+                let (width, height) = img.dimensions();
+                if width < 64 || height < 64 {
+                    return Ok(Some(jpeg));
+                }
+                img = img.resize(width * 4 / 5, height * 4 / 5, FilterType::Triangle);
+            }
+        }
+    }
+}
+
+fn encode_png(img: &DynamicImage) -> Result<Vec<u8>> {
+    let mut buff = Vec::new();
+    img.write_to(&mut Cursor::new(&mut buff), ImageFormat::Png)?;
+    Ok(buff)
+}
+
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut buff = Vec::new();
+    JpegEncoder::new_with_quality(&mut buff, quality).encode_image(&img.to_rgb8())?;
+    Ok(buff)
+}
+
+// 只检查常见的EXIF位置, 重新编码时所有元数据都会被去除
+fn has_metadata(data: &[u8], format: ImageFormat) -> bool {
+    let needle: &[u8] = match format {
+        ImageFormat::Jpeg => b"Exif\0\0",
+        ImageFormat::Png => b"eXIf",
+        ImageFormat::WebP => b"EXIF",
+        _ => return false,
+    };
+    let head = &data[..data.len().min(128 * 1024)];
+    head.windows(needle.len()).any(|w| w == needle)
+}
+
+/// 使用客户端中设置的预处理, 没有设置时原样返回
+pub(crate) async fn apply_image_preprocess<'a>(
+    client: &ricq::Client,
+    data: Cow<'a, [u8]>,
+) -> RQResult<Cow<'a, [u8]>> {
+    let preprocess = match client.extensions().get::<ImagePreprocess>() {
+        Some(preprocess) => preprocess,
+        None => return Ok(data),
+    };
+    let owned = data.to_vec();
+    let result = tokio::task::spawn_blocking(move || preprocess.process(&owned))
+        .await
+        .map_err(|err| RQError::Other(format!("图片预处理失败 : {}", err)))?;
+    match result {
+        Ok(Some(processed)) => Ok(Cow::Owned(processed)),
+        Ok(None) => Ok(data),
+        // 无法识别的图片交给服务器处理
+        Err(err) => {
+            tracing::debug!("图片预处理跳过 : {:?}", err);
+            Ok(data)
+        }
+    }
+}
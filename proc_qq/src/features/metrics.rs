@@ -0,0 +1,182 @@
+use crate::ExtensionsTrait;
+use anyhow::Result;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use ricq::handler::QEvent;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Prometheus指标, 使用`ClientBuilder::metrics`开启
+///
+/// 收集收到的事件数(按类型)、每个模块处理事件的耗时、发送消息的成功失败次数和重连次数.
+/// 设置了`listen`时, `run_client`登录后会在该地址提供`/metrics`接口.
+///
+/// ```ignore
+/// ClientBuilder::new().metrics(Metrics::new().listen(([127, 0, 0, 1], 9100)))
+/// ```
+pub struct Metrics {
+    addr: Option<SocketAddr>,
+    registry: Registry,
+    events: IntCounterVec,
+    handler_seconds: HistogramVec,
+    sends: IntCounterVec,
+    reconnects: IntCounter,
+    started: AtomicBool,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry = Registry::new();
+        let events = IntCounterVec::new(
+            Opts::new("proc_qq_events_received_total", "收到的事件数"),
+            &["type"],
+        )
+        .unwrap();
+        let handler_seconds = HistogramVec::new(
+            HistogramOpts::new("proc_qq_handler_duration_seconds", "模块处理事件的耗时"),
+            &["module", "handle"],
+        )
+        .unwrap();
+        let sends = IntCounterVec::new(
+            Opts::new("proc_qq_messages_sent_total", "发送消息的次数"),
+            &["result"],
+        )
+        .unwrap();
+        let reconnects = IntCounter::new("proc_qq_reconnects_total", "重连成功的次数").unwrap();
+        registry.register(Box::new(events.clone())).unwrap();
+        registry
+            .register(Box::new(handler_seconds.clone()))
+            .unwrap();
+        registry.register(Box::new(sends.clone())).unwrap();
+        registry.register(Box::new(reconnects.clone())).unwrap();
+        Self {
+            addr: None,
+            registry,
+            events,
+            handler_seconds,
+            sends,
+            reconnects,
+            started: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 提供`/metrics`接口的地址, 不设置时只收集不提供接口
+    pub fn listen(mut self, addr: impl Into<SocketAddr>) -> Self {
+        self.addr = Some(addr.into());
+        self
+    }
+
+    /// 指标所在的Registry, 可以注册自己的指标一起输出
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// 输出文本格式的指标
+    pub fn gather(&self) -> Result<String> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    pub(crate) fn record_event(&self, event: &QEvent) {
+        self.events.with_label_values(&[event_type(event)]).inc();
+    }
+
+    pub(crate) fn observe_handler(&self, module_id: &str, handle_name: &str, elapsed: Duration) {
+        self.handler_seconds
+            .with_label_values(&[module_id, handle_name])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub(crate) fn record_send(&self, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.sends.with_label_values(&[result]).inc();
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.inc();
+    }
+
+    fn start(self: Arc<Self>) {
+        let addr = match self.addr {
+            Some(addr) => addr,
+            None => return,
+        };
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let app = Router::new()
+            .route("/metrics", get(render_metrics))
+            .with_state(self);
+        let server = match axum::Server::try_bind(&addr) {
+            Ok(builder) => builder.serve(app.into_make_service()),
+            Err(err) => {
+                tracing::error!("指标接口启动失败 : {:?}", err);
+                return;
+            }
+        };
+        tracing::info!("指标接口 : http://{}/metrics", addr);
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                tracing::error!("指标接口出现错误 : {:?}", err);
+            }
+        });
+    }
+}
+
+async fn render_metrics(State(metrics): State<Arc<Metrics>>) -> Response {
+    match metrics.gather() {
+        Ok(text) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], text).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// 登录后启动指标接口
+pub(crate) fn start_metrics_server(client: &Arc<ricq::Client>) {
+    if let Some(metrics) = client.extensions().get::<Metrics>() {
+        metrics.start();
+    }
+}
+
+fn event_type(event: &QEvent) -> &'static str {
+    match event {
+        QEvent::Login(_) => "login",
+        QEvent::GroupMessage(_) => "group_message",
+        QEvent::FriendMessage(_) => "friend_message",
+        QEvent::GroupTempMessage(_) => "group_temp_message",
+        QEvent::GroupRequest(_) => "group_request",
+        QEvent::NewFriendRequest(_) => "new_friend_request",
+        QEvent::NewFriend(_) => "new_friend",
+        QEvent::FriendPoke(_) => "friend_poke",
+        QEvent::DeleteFriend(_) => "delete_friend",
+        QEvent::GroupMute(_) => "group_mute",
+        QEvent::GroupLeave(_) => "group_leave",
+        QEvent::GroupNameUpdate(_) => "group_name_update",
+        QEvent::GroupMessageRecall(_) => "group_message_recall",
+        QEvent::FriendMessageRecall(_) => "friend_message_recall",
+        QEvent::MSFOffline(_) => "msf_offline",
+        QEvent::KickedOffline(_) => "kicked_offline",
+        QEvent::GroupDisband(_) => "group_disband",
+        QEvent::MemberPermissionChange(_) => "member_permission_change",
+        QEvent::SelfInvited(_) => "self_invited",
+        QEvent::GroupAudioMessage(_) => "group_audio_message",
+        QEvent::FriendAudioMessage(_) => "friend_audio_message",
+        QEvent::NewMember(_) => "new_member",
+        QEvent::ClientDisconnect(_) => "client_disconnect",
+        QEvent::GroupPoke(_) => "group_poke",
+    }
+}
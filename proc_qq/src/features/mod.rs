@@ -4,6 +4,44 @@ pub mod captcha_window;
 #[allow(unused_imports)]
 pub use captcha_window::*;
 
+#[cfg(feature = "slider_server")]
+pub(crate) mod slider_server;
+
+#[cfg(feature = "charts")]
+pub mod charts;
+#[cfg(feature = "charts")]
+pub use charts::*;
+
+#[cfg(feature = "image_preprocess")]
+pub mod image_preprocess;
+#[cfg(feature = "image_preprocess")]
+pub use image_preprocess::*;
+
+#[cfg(feature = "image_convert")]
+pub mod image_convert;
+#[cfg(feature = "image_convert")]
+pub use image_convert::*;
+
+/// 上传图片前的处理: 转换不支持的格式, 然后进行客户端中设置的预处理
+#[allow(unused_mut)]
+pub(crate) async fn preprocess_upload_image<'a>(
+    _client: &ricq::Client,
+    data: &'a [u8],
+) -> ricq_core::RQResult<std::borrow::Cow<'a, [u8]>> {
+    let mut data = std::borrow::Cow::Borrowed(data);
+    #[cfg(feature = "image_convert")]
+    {
+        data = image_convert::convert_upload_image(data)
+            .await
+            .map_err(|err| ricq_core::RQError::Other(err.to_string()))?;
+    }
+    #[cfg(feature = "image_preprocess")]
+    {
+        data = image_preprocess::apply_image_preprocess(_client, data).await?;
+    }
+    Ok(data)
+}
+
 #[cfg(feature = "connect_handler")]
 pub mod connect_handler;
 #[cfg(feature = "connect_handler")]
@@ -13,3 +51,110 @@ pub use connect_handler::*;
 pub mod proxy;
 #[cfg(feature = "proxy")]
 pub use proxy::*;
+
+#[cfg(feature = "silk")]
+pub mod silk;
+#[cfg(feature = "silk")]
+pub use silk::*;
+
+#[cfg(feature = "tts")]
+pub mod tts;
+#[cfg(feature = "tts")]
+pub use tts::*;
+
+#[cfg(feature = "serde_models")]
+pub mod serde_models;
+#[cfg(feature = "serde_models")]
+pub use serde_models::*;
+
+#[cfg(feature = "web_admin")]
+pub mod web_admin;
+#[cfg(feature = "web_admin")]
+pub use web_admin::*;
+
+#[cfg(feature = "plugin_host")]
+pub mod plugin_host;
+#[cfg(feature = "plugin_host")]
+pub use plugin_host::*;
+
+#[cfg(feature = "wasm_plugin")]
+pub mod wasm_plugin;
+#[cfg(feature = "wasm_plugin")]
+pub use wasm_plugin::*;
+
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "script")]
+pub use script::*;
+
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+#[cfg(feature = "scheduler")]
+pub use scheduler::*;
+
+#[cfg(feature = "onebot")]
+pub mod onebot;
+#[cfg(feature = "onebot")]
+pub use onebot::*;
+
+#[cfg(feature = "sled_store")]
+pub mod sled_store;
+#[cfg(feature = "sled_store")]
+pub use sled_store::*;
+
+#[cfg(feature = "sqlite_store")]
+pub mod sqlite_store;
+#[cfg(feature = "sqlite_store")]
+pub use sqlite_store::*;
+
+#[cfg(feature = "redis_session")]
+pub mod redis_session;
+#[cfg(feature = "redis_session")]
+pub use redis_session::*;
+
+#[cfg(feature = "sqlite_session")]
+pub mod sqlite_session;
+#[cfg(feature = "sqlite_session")]
+pub use sqlite_session::*;
+
+#[cfg(feature = "session_encryption")]
+pub mod session_encryption;
+#[cfg(feature = "session_encryption")]
+pub use session_encryption::*;
+
+#[cfg(feature = "config_file")]
+pub mod config_file;
+#[cfg(feature = "config_file")]
+pub use config_file::*;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+
+#[cfg(feature = "event_recorder")]
+pub mod event_recorder;
+#[cfg(feature = "event_recorder")]
+pub use event_recorder::*;
+
+/// 取得事件中的客户端, 只包括OneBot推送和事件记录会处理的事件
+#[cfg(any(feature = "onebot", feature = "event_recorder"))]
+pub(crate) fn qevent_client(event: &ricq::handler::QEvent) -> Option<&ricq::Client> {
+    use ricq::handler::QEvent;
+    Some(match event {
+        QEvent::GroupMessage(event) => &event.client,
+        QEvent::FriendMessage(event) => &event.client,
+        QEvent::GroupTempMessage(event) => &event.client,
+        QEvent::GroupMessageRecall(event) => &event.client,
+        QEvent::FriendMessageRecall(event) => &event.client,
+        QEvent::NewMember(event) => &event.client,
+        QEvent::GroupLeave(event) => &event.client,
+        QEvent::GroupMute(event) => &event.client,
+        QEvent::NewFriend(event) => &event.client,
+        QEvent::GroupPoke(event) => &event.client,
+        QEvent::FriendPoke(event) => &event.client,
+        QEvent::NewFriendRequest(event) => &event.client,
+        QEvent::GroupRequest(event) => &event.client,
+        _ => return None,
+    })
+}
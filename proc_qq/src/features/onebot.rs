@@ -0,0 +1,1046 @@
+use crate::{
+    download_bytes, Client, ClientTrait, DownloadOptions, ElementModel, ExtensionsTrait,
+    MessageChainModel, MessageTarget,
+};
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use ricq::client::event::{JoinGroupRequestEvent, NewFriendRequestEvent};
+use ricq::handler::QEvent;
+use ricq_core::msg::elem::{At, Face, Text};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    ErrorResponse, Request as WsRequest, Response as WsResponse,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+// 保留最近消息的message_id的条数
+const RECENT_MESSAGES: usize = 2000;
+// 保留还没有处理的好友和加群请求的条数
+const RECENT_REQUESTS: usize = 200;
+
+/// OneBot 11 协议适配
+///
+/// 将客户端以OneBot 11的形式提供给其他框架使用:
+/// - HTTP API : `POST /send_group_msg` 等, 参数可以是JSON或查询字符串
+/// - 正向WebSocket : 推送事件, 同时可以通过同一个连接调用API (需要带`echo`)
+/// - HTTP POST : 将事件POST到设置的地址 (不支持快速操作)
+///
+/// 设置`access_token`后, 请求需要 `Authorization: Bearer token` 或 `?access_token=token`.
+/// 发送图片时默认只接受网址, 需要发送本地文件时使用`allow_local_files`指定允许读取的目录.
+///
+/// ```ignore
+/// let client = Arc::new(ClientBuilder::new()....build().await?);
+/// OneBot::new()
+///     .http(([127, 0, 0, 1], 5700))
+///     .websocket(([127, 0, 0, 1], 6700))
+///     .access_token("my-token")
+///     .start(client.clone())?;
+/// run_client(client).await?;
+/// ```
+#[derive(Default)]
+pub struct OneBot {
+    http: Option<SocketAddr>,
+    websocket: Option<SocketAddr>,
+    http_post: Option<String>,
+    access_token: Option<String>,
+    local_files: Option<PathBuf>,
+}
+
+impl OneBot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// HTTP API监听的地址
+    pub fn http(mut self, addr: impl Into<SocketAddr>) -> Self {
+        self.http = Some(addr.into());
+        self
+    }
+
+    /// 正向WebSocket监听的地址
+    pub fn websocket(mut self, addr: impl Into<SocketAddr>) -> Self {
+        self.websocket = Some(addr.into());
+        self
+    }
+
+    /// 事件POST的地址
+    pub fn http_post(mut self, url: impl Into<String>) -> Self {
+        self.http_post = Some(url.into());
+        self
+    }
+
+    pub fn access_token(mut self, token: impl Into<String>) -> Self {
+        self.access_token = Some(token.into());
+        self
+    }
+
+    /// 允许图片消息段使用这个目录中的本地文件 (`file=/dir/a.png`或相对于目录的路径), 默认不允许
+    pub fn allow_local_files(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.local_files = Some(dir.into());
+        self
+    }
+
+    /// 在后台启动设置的服务, 之后收到的事件会推送给连接的应用
+    pub fn start(self, client: Arc<Client>) -> Result<JoinHandle<()>> {
+        let local_files = match &self.local_files {
+            Some(dir) => Some(
+                std::fs::canonicalize(dir)
+                    .with_context(|| format!("找不到目录 : {}", dir.display()))?,
+            ),
+            None => None,
+        };
+        let (events, _) = broadcast::channel(256);
+        let shared = Arc::new(OneBotShared {
+            events,
+            http_post: self.http_post,
+            access_token: self.access_token.clone(),
+            message_ids: MessageIds::default(),
+            requests: Mutex::new(VecDeque::new()),
+        });
+        client.rq_client.extensions().insert_arc(shared.clone());
+        let state = Arc::new(State {
+            access_token: self.access_token,
+            local_files,
+            client,
+            shared,
+        });
+        let mut tasks: Vec<BoxFuture<'static, ()>> = vec![];
+        if let Some(addr) = self.http {
+            let state = state.clone();
+            let make_service = make_service_fn(move |_| {
+                let state = state.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| {
+                        let state = state.clone();
+                        async move { Ok::<_, Infallible>(state.handle_http(request).await) }
+                    }))
+                }
+            });
+            let server = Server::try_bind(&addr)?.serve(make_service);
+            tracing::info!("OneBot HTTP : http://{}", addr);
+            tasks.push(Box::pin(async move {
+                if let Err(err) = server.await {
+                    tracing::error!("OneBot HTTP出现错误 : {:?}", err);
+                }
+            }));
+        }
+        if let Some(addr) = self.websocket {
+            let listener = std::net::TcpListener::bind(addr)?;
+            listener.set_nonblocking(true)?;
+            let listener = TcpListener::from_std(listener)?;
+            tracing::info!("OneBot WebSocket : ws://{}", addr);
+            let state = state.clone();
+            tasks.push(Box::pin(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let state = state.clone();
+                            tokio::spawn(async move {
+                                if let Err(err) = state.serve_websocket(stream).await {
+                                    tracing::debug!("OneBot WebSocket连接断开 : {:?}", err);
+                                }
+                            });
+                        }
+                        Err(err) => {
+                            tracing::error!("OneBot WebSocket出现错误 : {:?}", err);
+                            return;
+                        }
+                    }
+                }
+            }));
+        }
+        Ok(tokio::spawn(async move {
+            futures::future::join_all(tasks).await;
+        }))
+    }
+}
+
+enum PendingRequest {
+    Friend(NewFriendRequestEvent),
+    Group(JoinGroupRequestEvent),
+}
+
+/// 事件推送和API共用的数据, 放在ricq客户端的扩展中以便在收到事件时找到
+pub(crate) struct OneBotShared {
+    events: broadcast::Sender<String>,
+    http_post: Option<String>,
+    access_token: Option<String>,
+    message_ids: MessageIds,
+    // (flag, 请求), 超过RECENT_REQUESTS条时丢弃最早的
+    requests: Mutex<VecDeque<(String, PendingRequest)>>,
+}
+
+struct MessageEntry {
+    id: i32,
+    target: MessageTarget,
+    time: i64,
+    seqs: Vec<i32>,
+    rands: Vec<i32>,
+}
+
+/// OneBot的message_id为int32, 为最近的消息分配自增的id以便撤回和引用
+#[derive(Default)]
+struct MessageIds {
+    next: AtomicI32,
+    entries: Mutex<VecDeque<MessageEntry>>,
+}
+
+impl MessageIds {
+    fn record(&self, target: MessageTarget, time: i64, seqs: Vec<i32>, rands: Vec<i32>) -> i32 {
+        let id = self.next.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= RECENT_MESSAGES {
+            entries.pop_front();
+        }
+        entries.push_back(MessageEntry {
+            id,
+            target,
+            time,
+            seqs,
+            rands,
+        });
+        id
+    }
+
+    fn find(&self, id: i32) -> Option<(MessageTarget, i64, Vec<i32>, Vec<i32>)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| {
+                (
+                    entry.target,
+                    entry.time,
+                    entry.seqs.clone(),
+                    entry.rands.clone(),
+                )
+            })
+    }
+
+    /// 按群号(私聊为None)和seq查找, 找不到时返回seq
+    fn find_by_seq(&self, group_code: Option<i64>, seq: i32) -> i32 {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|entry| {
+                let entry_group = match entry.target {
+                    MessageTarget::Group(group_code, _) => Some(group_code),
+                    _ => None,
+                };
+                entry_group == group_code && entry.seqs.contains(&seq)
+            })
+            .map(|entry| entry.id)
+            .unwrap_or(seq)
+    }
+}
+
+impl OneBotShared {
+    fn segments(&self, group_code: Option<i64>, chain: &MessageChain) -> Vec<Value> {
+        MessageChainModel::from(chain)
+            .0
+            .into_iter()
+            .filter_map(|elem| match elem {
+                ElementModel::Text { content } => {
+                    Some(json!({"type": "text", "data": {"text": content}}))
+                }
+                ElementModel::At { target: 0, .. } => {
+                    Some(json!({"type": "at", "data": {"qq": "all"}}))
+                }
+                ElementModel::At { target, .. } => {
+                    Some(json!({"type": "at", "data": {"qq": target.to_string()}}))
+                }
+                ElementModel::Face { index, .. } => {
+                    Some(json!({"type": "face", "data": {"id": index.to_string()}}))
+                }
+                // file同样使用网址, 应用可以直接用收到的消息段回复
+                ElementModel::Image { url, .. } => {
+                    Some(json!({"type": "image", "data": {"file": url, "url": url}}))
+                }
+                ElementModel::Reply { seq, .. } => Some(json!({"type": "reply", "data": {
+                    "id": self.message_ids.find_by_seq(group_code, seq).to_string()
+                }})),
+                ElementModel::Other { .. } => None,
+            })
+            .collect()
+    }
+
+    fn message_event(
+        &self,
+        self_id: i64,
+        target: MessageTarget,
+        receipt: MessageReceipt,
+        chain: &MessageChain,
+        sender: Value,
+    ) -> Value {
+        let (message_type, sub_type, group_code, user_id) = match target {
+            MessageTarget::Group(group_code, uin) => ("group", "normal", Some(group_code), uin),
+            MessageTarget::Private(uin) => ("private", "friend", None, uin),
+            MessageTarget::GroupTemp(_, uin) => ("private", "group", None, uin),
+        };
+        let time = receipt.time;
+        let message_id = self
+            .message_ids
+            .record(target, time, receipt.seqs, receipt.rands);
+        let message = self.segments(group_code, chain);
+        let mut event = json!({
+            "time": time,
+            "self_id": self_id,
+            "post_type": "message",
+            "message_type": message_type,
+            "sub_type": sub_type,
+            "message_id": message_id,
+            "user_id": user_id,
+            "message": message,
+            "raw_message": to_cq_string(&message),
+            "font": 0,
+            "sender": sender,
+        });
+        if let Some(group_code) = group_code {
+            event["group_id"] = json!(group_code);
+            event["anonymous"] = Value::Null;
+        }
+        event
+    }
+
+    fn notice(self_id: i64, notice_type: &str, mut fields: Value) -> Value {
+        fields["time"] = json!(chrono::Local::now().timestamp());
+        fields["self_id"] = json!(self_id);
+        fields["post_type"] = json!("notice");
+        fields["notice_type"] = json!(notice_type);
+        fields
+    }
+
+    fn request(&self, self_id: i64, request: PendingRequest, mut fields: Value) -> Value {
+        let flag = format!(
+            "{}",
+            self.message_ids.next.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        fields["time"] = json!(chrono::Local::now().timestamp());
+        fields["self_id"] = json!(self_id);
+        fields["post_type"] = json!("request");
+        fields["flag"] = json!(flag);
+        let mut requests = self.requests.lock().unwrap();
+        if requests.len() >= RECENT_REQUESTS {
+            requests.pop_front();
+        }
+        requests.push_back((flag, request));
+        fields
+    }
+
+    fn event_json(&self, self_id: i64, event: &QEvent) -> Option<Value> {
+        Some(match event {
+            QEvent::GroupMessage(event) => {
+                let inner = &event.inner;
+                self.message_event(
+                    self_id,
+                    MessageTarget::Group(inner.group_code, inner.from_uin),
+                    MessageReceipt {
+                        seqs: inner.seqs.clone(),
+                        rands: inner.rands.clone(),
+                        time: inner.time as i64,
+                    },
+                    &inner.elements,
+                    json!({"user_id": inner.from_uin, "nickname": inner.group_card, "card": inner.group_card}),
+                )
+            }
+            QEvent::FriendMessage(event) => {
+                let inner = &event.inner;
+                self.message_event(
+                    self_id,
+                    MessageTarget::Private(inner.from_uin),
+                    MessageReceipt {
+                        seqs: inner.seqs.clone(),
+                        rands: inner.rands.clone(),
+                        time: inner.time as i64,
+                    },
+                    &inner.elements,
+                    json!({"user_id": inner.from_uin, "nickname": inner.from_nick}),
+                )
+            }
+            QEvent::GroupTempMessage(event) => {
+                let inner = &event.inner;
+                self.message_event(
+                    self_id,
+                    MessageTarget::GroupTemp(inner.group_code, inner.from_uin),
+                    MessageReceipt {
+                        seqs: inner.seqs.clone(),
+                        rands: inner.rands.clone(),
+                        time: inner.time as i64,
+                    },
+                    &inner.elements,
+                    json!({"user_id": inner.from_uin, "nickname": inner.from_nick}),
+                )
+            }
+            QEvent::GroupMessageRecall(event) => {
+                let inner = &event.inner;
+                Self::notice(
+                    self_id,
+                    "group_recall",
+                    json!({
+                        "group_id": inner.group_code,
+                        "user_id": inner.author_uin,
+                        "operator_id": inner.operator_uin,
+                        "message_id": self.message_ids.find_by_seq(Some(inner.group_code), inner.msg_seq),
+                    }),
+                )
+            }
+            QEvent::FriendMessageRecall(event) => {
+                let inner = &event.inner;
+                Self::notice(
+                    self_id,
+                    "friend_recall",
+                    json!({
+                        "user_id": inner.friend_uin,
+                        "message_id": self.message_ids.find_by_seq(None, inner.msg_seq),
+                    }),
+                )
+            }
+            QEvent::NewMember(event) => Self::notice(
+                self_id,
+                "group_increase",
+                json!({
+                    "sub_type": "approve",
+                    "group_id": event.inner.group_code,
+                    "operator_id": 0,
+                    "user_id": event.inner.member_uin,
+                }),
+            ),
+            QEvent::GroupLeave(event) => {
+                let inner = &event.inner;
+                let sub_type = match inner.operator_uin {
+                    Some(operator) if operator != inner.member_uin => {
+                        if inner.member_uin == self_id {
+                            "kick_me"
+                        } else {
+                            "kick"
+                        }
+                    }
+                    _ => "leave",
+                };
+                Self::notice(
+                    self_id,
+                    "group_decrease",
+                    json!({
+                        "sub_type": sub_type,
+                        "group_id": inner.group_code,
+                        "operator_id": inner.operator_uin.unwrap_or(inner.member_uin),
+                        "user_id": inner.member_uin,
+                    }),
+                )
+            }
+            QEvent::GroupMute(event) => {
+                let inner = &event.inner;
+                Self::notice(
+                    self_id,
+                    "group_ban",
+                    json!({
+                        "sub_type": if inner.duration.is_zero() { "lift_ban" } else { "ban" },
+                        "group_id": inner.group_code,
+                        "operator_id": inner.operator_uin,
+                        "user_id": inner.target_uin,
+                        "duration": inner.duration.as_secs(),
+                    }),
+                )
+            }
+            QEvent::NewFriend(event) => {
+                Self::notice(self_id, "friend_add", json!({"user_id": event.inner.uin}))
+            }
+            QEvent::GroupPoke(event) => Self::notice(
+                self_id,
+                "notify",
+                json!({
+                    "sub_type": "poke",
+                    "group_id": event.inner.group_code,
+                    "user_id": event.inner.sender,
+                    "target_id": event.inner.receiver,
+                }),
+            ),
+            QEvent::FriendPoke(event) => Self::notice(
+                self_id,
+                "notify",
+                json!({
+                    "sub_type": "poke",
+                    "sender_id": event.inner.sender,
+                    "user_id": event.inner.sender,
+                    "target_id": event.inner.receiver,
+                }),
+            ),
+            QEvent::NewFriendRequest(event) => self.request(
+                self_id,
+                PendingRequest::Friend(event.clone()),
+                json!({
+                    "request_type": "friend",
+                    "user_id": event.inner.req_uin,
+                    "comment": event.inner.message,
+                }),
+            ),
+            QEvent::GroupRequest(event) => self.request(
+                self_id,
+                PendingRequest::Group(event.clone()),
+                json!({
+                    "request_type": "group",
+                    "sub_type": "add",
+                    "group_id": event.inner.group_code,
+                    "user_id": event.inner.req_uin,
+                    "comment": event.inner.message,
+                }),
+            ),
+            _ => return None,
+        })
+    }
+
+    fn push(&self, self_id: i64, event: Value) {
+        let body = event.to_string();
+        // 没有WebSocket连接时发送失败, 忽略即可
+        let _ = self.events.send(body.clone());
+        if let Some(url) = &self.http_post {
+            let url = url.clone();
+            let access_token = self.access_token.clone();
+            tokio::spawn(async move {
+                let mut request = reqwest::Client::new()
+                    .post(url)
+                    .header("content-type", "application/json")
+                    .header("X-Self-ID", self_id.to_string())
+                    .body(body);
+                if let Some(token) = access_token {
+                    request = request.bearer_auth(token);
+                }
+                if let Err(err) = request.send().await {
+                    tracing::warn!("OneBot 推送事件失败 : {:?}", err);
+                }
+            });
+        }
+    }
+}
+
+/// 收到事件时推送给OneBot的连接, 没有启动OneBot时什么都不做
+pub(crate) async fn push_onebot_event(event: &QEvent) {
+    let client = match super::qevent_client(event) {
+        Some(client) => client,
+        None => return,
+    };
+    let shared = match client.extensions().get::<OneBotShared>() {
+        Some(shared) => shared,
+        None => return,
+    };
+    let self_id = client.uin().await;
+    if let Some(event) = shared.event_json(self_id, event) {
+        shared.push(self_id, event);
+    }
+}
+
+struct State {
+    access_token: Option<String>,
+    /// 已经规范化的允许读取的目录
+    local_files: Option<PathBuf>,
+    client: Arc<Client>,
+    shared: Arc<OneBotShared>,
+}
+
+fn ok(data: Value) -> Value {
+    json!({"status": "ok", "retcode": 0, "data": data})
+}
+
+fn failed(retcode: i32, message: String) -> Value {
+    json!({"status": "failed", "retcode": retcode, "data": Value::Null, "msg": message})
+}
+
+fn param_i64(params: &Value, name: &str) -> Result<i64> {
+    match &params[name] {
+        Value::Number(number) => number.as_i64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("缺少参数 : {}", name))
+}
+
+fn param_bool(params: &Value, name: &str, default: bool) -> bool {
+    match &params[name] {
+        Value::Bool(value) => *value,
+        Value::String(text) => text == "true" || text == "1",
+        Value::Number(number) => number.as_i64() != Some(0),
+        _ => default,
+    }
+}
+
+fn cq_escape(text: &str, comma: bool) -> String {
+    let text = text
+        .replace('&', "&amp;")
+        .replace('[', "&#91;")
+        .replace(']', "&#93;");
+    if comma {
+        text.replace(',', "&#44;")
+    } else {
+        text
+    }
+}
+
+fn cq_unescape(text: &str) -> String {
+    text.replace("&#91;", "[")
+        .replace("&#93;", "]")
+        .replace("&#44;", ",")
+        .replace("&amp;", "&")
+}
+
+/// 消息段转换为CQ码字符串
+fn to_cq_string(segments: &[Value]) -> String {
+    segments
+        .iter()
+        .map(|segment| {
+            let data = segment["data"].as_object();
+            match segment["type"].as_str() {
+                Some("text") => {
+                    cq_escape(segment["data"]["text"].as_str().unwrap_or_default(), false)
+                }
+                Some(kind) => {
+                    let mut code = format!("[CQ:{}", kind);
+                    for (key, value) in data.into_iter().flatten() {
+                        let value = match value {
+                            Value::String(value) => value.clone(),
+                            other => other.to_string(),
+                        };
+                        code.push_str(&format!(",{}={}", key, cq_escape(&value, true)));
+                    }
+                    code.push(']');
+                    code
+                }
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// CQ码字符串转换为消息段
+fn parse_cq_string(text: &str) -> Vec<Value> {
+    let mut segments = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("[CQ:") {
+        if start > 0 {
+            segments.push(json!({"type": "text", "data": {"text": cq_unescape(&rest[..start])}}));
+        }
+        let end = match rest[start..].find(']') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let mut parts = rest[start + 4..end].split(',');
+        let kind = parts.next().unwrap_or_default();
+        let data: serde_json::Map<String, Value> = parts
+            .filter_map(|part| part.split_once('='))
+            .map(|(key, value)| (key.to_owned(), Value::String(cq_unescape(value))))
+            .collect();
+        segments.push(json!({"type": kind, "data": data}));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(json!({"type": "text", "data": {"text": cq_unescape(rest)}}));
+    }
+    segments
+}
+
+/// 解析查询字符串, 进行百分号解码
+fn query_pairs(query: Option<&str>) -> impl Iterator<Item = (String, String)> + '_ {
+    url::form_urlencoded::parse(query.unwrap_or_default().as_bytes()).into_owned()
+}
+
+impl State {
+    fn authorized(&self, authorization: Option<&str>, query: Option<&str>) -> bool {
+        let token = match &self.access_token {
+            Some(token) => token,
+            None => return true,
+        };
+        if let Some(value) = authorization {
+            let value = value
+                .strip_prefix("Bearer ")
+                .or_else(|| value.strip_prefix("Token "))
+                .unwrap_or(value);
+            if value == token {
+                return true;
+            }
+        }
+        query_pairs(query).any(|(key, value)| key == "access_token" && value == *token)
+    }
+
+    async fn handle_http(&self, request: Request<Body>) -> Response<Body> {
+        let authorization = request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        if !self.authorized(authorization, request.uri().query()) {
+            return Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())
+                .unwrap();
+        }
+        let action = request.uri().path().trim_matches('/').to_owned();
+        let mut params: serde_json::Map<String, Value> = query_pairs(request.uri().query())
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect();
+        if request.method() == Method::POST {
+            let body = match hyper::body::to_bytes(request.into_body()).await {
+                Ok(body) => body,
+                Err(err) => {
+                    return http_json(StatusCode::BAD_REQUEST, &failed(100, err.to_string()))
+                }
+            };
+            if !body.is_empty() {
+                match serde_json::from_slice::<Value>(&body) {
+                    Ok(Value::Object(body)) => params.extend(body),
+                    _ => {
+                        return http_json(
+                            StatusCode::BAD_REQUEST,
+                            &failed(100, "参数不是JSON对象".to_owned()),
+                        )
+                    }
+                }
+            }
+        }
+        let response = self.call(&action, Value::Object(params)).await;
+        let status = if response["retcode"] == 1404 {
+            StatusCode::NOT_FOUND
+        } else {
+            StatusCode::OK
+        };
+        http_json(status, &response)
+    }
+
+    async fn serve_websocket(self: Arc<Self>, stream: TcpStream) -> Result<()> {
+        let state = self.clone();
+        let websocket = tokio_tungstenite::accept_hdr_async(
+            stream,
+            move |request: &WsRequest, response: WsResponse| {
+                let authorization = request
+                    .headers()
+                    .get("authorization")
+                    .and_then(|value| value.to_str().ok());
+                if state.authorized(authorization, request.uri().query()) {
+                    Ok(response)
+                } else {
+                    let mut response = ErrorResponse::new(None);
+                    *response.status_mut() =
+                        tokio_tungstenite::tungstenite::http::StatusCode::UNAUTHORIZED;
+                    Err(response)
+                }
+            },
+        )
+        .await?;
+        let (mut sink, mut source) = websocket.split();
+        let mut events = self.shared.events.subscribe();
+        let self_id = self.client.rq_client.uin().await;
+        let lifecycle = json!({
+            "time": chrono::Local::now().timestamp(),
+            "self_id": self_id,
+            "post_type": "meta_event",
+            "meta_event_type": "lifecycle",
+            "sub_type": "connect",
+        });
+        sink.send(Message::Text(lifecycle.to_string())).await?;
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => sink.send(Message::Text(event)).await?,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("OneBot WebSocket推送过慢, 跳过了{}个事件", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                message = source.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        let response = self.handle_websocket_action(&text).await;
+                        sink.send(Message::Text(response.to_string())).await?;
+                    }
+                    Some(Ok(Message::Ping(data))) => sink.send(Message::Pong(data)).await?,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                },
+            }
+        }
+    }
+
+    async fn handle_websocket_action(&self, text: &str) -> Value {
+        let request: Value = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(err) => return failed(1400, err.to_string()),
+        };
+        let action = request["action"].as_str().unwrap_or_default();
+        let mut response = self.call(action, request["params"].clone()).await;
+        if !request["echo"].is_null() {
+            response["echo"] = request["echo"].clone();
+        }
+        response
+    }
+
+    async fn call(&self, action: &str, params: Value) -> Value {
+        let result = match action {
+            "send_private_msg" => self.send_msg(Some("private"), &params).await,
+            "send_group_msg" => self.send_msg(Some("group"), &params).await,
+            "send_msg" => {
+                self.send_msg(params["message_type"].as_str(), &params)
+                    .await
+            }
+            "delete_msg" => self.delete_msg(&params).await,
+            "get_login_info" => self.get_login_info().await,
+            "get_friend_list" => self.get_friend_list().await,
+            "get_group_list" => self.get_group_list().await,
+            "set_group_kick" => self.set_group_kick(&params).await,
+            "set_group_ban" => self.set_group_ban(&params).await,
+            "set_group_whole_ban" => self.set_group_whole_ban(&params).await,
+            "set_friend_add_request" | "set_group_add_request" => {
+                self.set_add_request(&params).await
+            }
+            "get_status" => Ok(json!({
+                // NetworkStatus::Running
+                "online": self.client.rq_client.get_status() == 1,
+                "good": true,
+            })),
+            "get_version_info" => Ok(json!({
+                "app_name": "proc_qq",
+                "app_version": env!("CARGO_PKG_VERSION"),
+                "protocol_version": "v11",
+            })),
+            "can_send_image" => Ok(json!({"yes": true})),
+            "can_send_record" => Ok(json!({"yes": false})),
+            _ => return failed(1404, format!("不支持的API : {}", action)),
+        };
+        match result {
+            Ok(data) => ok(data),
+            Err(err) => failed(100, format!("{:?}", err)),
+        }
+    }
+
+    async fn send_msg(&self, message_type: Option<&str>, params: &Value) -> Result<Value> {
+        let target = match message_type {
+            Some("group") => MessageTarget::Group(param_i64(params, "group_id")?, 0),
+            Some("private") => MessageTarget::Private(param_i64(params, "user_id")?),
+            _ => match param_i64(params, "group_id") {
+                Ok(group_code) => MessageTarget::Group(group_code, 0),
+                Err(_) => MessageTarget::Private(param_i64(params, "user_id")?),
+            },
+        };
+        let segments = match &params["message"] {
+            Value::String(text) if param_bool(params, "auto_escape", false) => {
+                vec![json!({"type": "text", "data": {"text": text}})]
+            }
+            Value::String(text) => parse_cq_string(text),
+            Value::Array(segments) => segments.clone(),
+            segment @ Value::Object(_) => vec![segment.clone()],
+            _ => return Err(anyhow!("缺少参数 : message")),
+        };
+        let chain = self.message_chain(&target, &segments).await?;
+        let receipt: MessageReceipt = self
+            .client
+            .rq_client
+            .send_message_to_target(&target, chain)
+            .await?;
+        let message_id =
+            self.shared
+                .message_ids
+                .record(target, receipt.time, receipt.seqs, receipt.rands);
+        Ok(json!({ "message_id": message_id }))
+    }
+
+    /// 支持文字、@、表情和图片(网址, 或者`allow_local_files`目录中的文件), 其他消息段会被忽略
+    async fn message_chain(
+        &self,
+        target: &MessageTarget,
+        segments: &[Value],
+    ) -> Result<MessageChain> {
+        let client = &self.client.rq_client;
+        let mut chain = MessageChain::default();
+        for segment in segments {
+            let data = &segment["data"];
+            match segment["type"].as_str().unwrap_or_default() {
+                "text" => chain.push(Text::new(
+                    data["text"].as_str().unwrap_or_default().to_owned(),
+                )),
+                "at" => match data["qq"].as_str() {
+                    Some("all") => {
+                        let mut at = At::new(0);
+                        at.display = "@全体成员".to_owned();
+                        chain.push(at)
+                    }
+                    _ => chain.push(At::new(param_i64(data, "qq")?)),
+                },
+                "face" => chain.push(Face::new(param_i64(data, "id")? as i32)),
+                "image" => {
+                    let file = data["url"]
+                        .as_str()
+                        .or_else(|| data["file"].as_str())
+                        .ok_or_else(|| anyhow!("图片缺少file"))?;
+                    let bytes = if file.starts_with("http://") || file.starts_with("https://") {
+                        download_bytes(file, &DownloadOptions::default()).await?
+                    } else {
+                        self.read_local_file(file.strip_prefix("file://").unwrap_or(file))
+                            .await?
+                            .into()
+                    };
+                    let bytes = crate::features::preprocess_upload_image(client, &bytes).await?;
+                    match target {
+                        MessageTarget::Group(group_code, _) => {
+                            chain.push(client.upload_group_image(*group_code, &bytes).await?)
+                        }
+                        MessageTarget::Private(uin) | MessageTarget::GroupTemp(_, uin) => {
+                            chain.push(client.upload_friend_image(*uin, &bytes).await?)
+                        }
+                    }
+                }
+                other => tracing::debug!("OneBot 忽略不支持的消息段 : {}", other),
+            }
+        }
+        Ok(chain)
+    }
+
+    /// 只能读取`allow_local_files`目录中的文件, 防止通过API读取机器人的其他文件
+    async fn read_local_file(&self, path: &str) -> Result<Vec<u8>> {
+        let dir = self
+            .local_files
+            .as_ref()
+            .ok_or_else(|| anyhow!("不允许发送本地文件, 请使用网址或设置allow_local_files"))?;
+        let path = tokio::fs::canonicalize(dir.join(path))
+            .await
+            .with_context(|| format!("读取图片失败 : {}", path))?;
+        if !path.starts_with(dir) {
+            return Err(anyhow!("不允许读取目录之外的文件 : {}", path.display()));
+        }
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("读取图片失败 : {}", path.display()))
+    }
+
+    async fn delete_msg(&self, params: &Value) -> Result<Value> {
+        let message_id = param_i64(params, "message_id")? as i32;
+        let (target, time, seqs, rands) = self
+            .shared
+            .message_ids
+            .find(message_id)
+            .ok_or_else(|| anyhow!("找不到消息 : {}", message_id))?;
+        let client = &self.client.rq_client;
+        match target {
+            MessageTarget::Group(group_code, _) => {
+                client.recall_group_message(group_code, seqs, rands).await?
+            }
+            MessageTarget::Private(uin) => {
+                client.recall_friend_message(uin, time, seqs, rands).await?
+            }
+            MessageTarget::GroupTemp(..) => return Err(anyhow!("不支持撤回临时会话消息")),
+        }
+        Ok(Value::Null)
+    }
+
+    async fn get_login_info(&self) -> Result<Value> {
+        let client = &self.client.rq_client;
+        Ok(json!({
+            "user_id": client.uin().await,
+            "nickname": client.account_info.read().await.nickname,
+        }))
+    }
+
+    async fn get_friend_list(&self) -> Result<Value> {
+        let friends = self.client.rq_client.get_friend_list().await?.friends;
+        Ok(friends
+            .into_iter()
+            .map(|friend| json!({"user_id": friend.uin, "nickname": friend.nick, "remark": friend.remark}))
+            .collect())
+    }
+
+    async fn get_group_list(&self) -> Result<Value> {
+        let groups = self.client.rq_client.get_group_list().await?;
+        Ok(groups
+            .into_iter()
+            .map(|group| {
+                json!({
+                    "group_id": group.code,
+                    "group_name": group.name,
+                    "member_count": group.member_count,
+                    "max_member_count": group.max_member_count,
+                })
+            })
+            .collect())
+    }
+
+    async fn set_group_kick(&self, params: &Value) -> Result<Value> {
+        self.client
+            .rq_client
+            .group_kick(
+                param_i64(params, "group_id")?,
+                vec![param_i64(params, "user_id")?],
+                "",
+                param_bool(params, "reject_add_request", false),
+            )
+            .await?;
+        Ok(Value::Null)
+    }
+
+    async fn set_group_ban(&self, params: &Value) -> Result<Value> {
+        let duration = param_i64(params, "duration").unwrap_or(30 * 60);
+        self.client
+            .rq_client
+            .group_mute(
+                param_i64(params, "group_id")?,
+                param_i64(params, "user_id")?,
+                Duration::from_secs(duration.max(0) as u64),
+            )
+            .await?;
+        Ok(Value::Null)
+    }
+
+    async fn set_group_whole_ban(&self, params: &Value) -> Result<Value> {
+        self.client
+            .rq_client
+            .group_mute_all(
+                param_i64(params, "group_id")?,
+                param_bool(params, "enable", true),
+            )
+            .await?;
+        Ok(Value::Null)
+    }
+
+    async fn set_add_request(&self, params: &Value) -> Result<Value> {
+        let flag = params["flag"]
+            .as_str()
+            .ok_or_else(|| anyhow!("缺少参数 : flag"))?;
+        let request = {
+            let mut requests = self.shared.requests.lock().unwrap();
+            let index = requests.iter().position(|(key, _)| key == flag);
+            index.and_then(|index| requests.remove(index))
+        };
+        let (_, request) = request.ok_or_else(|| anyhow!("找不到请求 : {}", flag))?;
+        let approve = param_bool(params, "approve", true);
+        match request {
+            PendingRequest::Friend(event) if approve => event.accept().await?,
+            PendingRequest::Friend(event) => event.reject().await?,
+            PendingRequest::Group(event) if approve => event.accept().await?,
+            PendingRequest::Group(event) => {
+                let reason = params["reason"].as_str().unwrap_or_default().to_owned();
+                event.reject(reason, false).await?
+            }
+        }
+        Ok(Value::Null)
+    }
+}
+
+fn http_json(status: StatusCode, value: &Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json; charset=utf-8")
+        .body(Body::from(value.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,124 @@
+use crate::{Module, PLUGIN_API_VERSION, PROC_QQ_VERSION};
+use anyhow::{anyhow, Context, Result};
+use libloading::{Library, Symbol};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+
+/// 已加载的插件
+#[derive(Debug, Clone)]
+pub struct LoadedPlugin {
+    pub path: PathBuf,
+    pub module_id: String,
+    pub module_name: String,
+}
+
+/// 从动态库加载模块, 插件使用`export_plugin!`导出入口
+///
+/// ```ignore
+/// let mut host = PluginHost::new();
+/// let mut modules = vec![hello::module()];
+/// modules.extend(unsafe { host.load_dir("plugins") }?);
+/// ClientBuilder::new().modules(modules)...
+/// ```
+///
+/// 插件加载后不会被卸载, 更新插件需要重启程序.
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<LoadedPlugin>,
+}
+
+impl PluginHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加载一个插件
+    ///
+    /// # Safety
+    ///
+    /// 动态库中的代码会在加载时执行.
+    /// 虽然会检查插件接口和proc_qq的版本, 但无法检查rustc的版本, 插件与宿主需要使用相同的rustc编译.
+    pub unsafe fn load(&mut self, path: impl AsRef<Path>) -> Result<Module> {
+        let path = path.as_ref();
+        let library =
+            Library::new(path).with_context(|| format!("加载插件失败 : {}", path.display()))?;
+        let api_version: Symbol<extern "C" fn() -> u32> = library
+            .get(b"proc_qq_plugin_api_version")
+            .with_context(|| format!("不是proc_qq插件 : {}", path.display()))?;
+        let api_version = api_version();
+        if api_version != PLUGIN_API_VERSION {
+            return Err(anyhow!(
+                "插件接口版本不匹配 : {} (插件 {}, 宿主 {})",
+                path.display(),
+                api_version,
+                PLUGIN_API_VERSION
+            ));
+        }
+        let version: Symbol<extern "C" fn() -> *const c_char> = library
+            .get(b"proc_qq_plugin_version")
+            .with_context(|| format!("不是proc_qq插件 : {}", path.display()))?;
+        let version = CStr::from_ptr(version()).to_string_lossy().into_owned();
+        let host_version = &PROC_QQ_VERSION[..PROC_QQ_VERSION.len() - 1];
+        if version != host_version {
+            return Err(anyhow!(
+                "插件使用的proc_qq版本不匹配 : {} (插件 {}, 宿主 {})",
+                path.display(),
+                version,
+                host_version
+            ));
+        }
+        let entry: Symbol<fn() -> Module> = library
+            .get(b"proc_qq_plugin")
+            .with_context(|| format!("不是proc_qq插件 : {}", path.display()))?;
+        let module = entry();
+        // 模块中的处理器指向动态库中的代码, 动态库需要一直保持加载
+        std::mem::forget(library);
+        tracing::info!(
+            "已加载插件 : {} ({} / {})",
+            path.display(),
+            module.id,
+            module.name
+        );
+        self.plugins.push(LoadedPlugin {
+            path: path.to_path_buf(),
+            module_id: module.id.clone(),
+            module_name: module.name.clone(),
+        });
+        Ok(module)
+    }
+
+    /// 加载文件夹中所有的动态库, 加载失败的插件会被跳过并记录日志
+    ///
+    /// # Safety
+    ///
+    /// 同`load`
+    pub unsafe fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<Vec<Module>> {
+        let dir = dir.as_ref();
+        let mut paths = vec![];
+        for entry in
+            std::fs::read_dir(dir).with_context(|| format!("读取文件夹失败 : {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str())
+                == Some(std::env::consts::DLL_EXTENSION)
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let mut modules = vec![];
+        for path in paths {
+            match self.load(&path) {
+                Ok(module) => modules.push(module),
+                Err(err) => tracing::warn!("{:?}", err),
+            }
+        }
+        Ok(modules)
+    }
+
+    /// 已加载的插件
+    pub fn plugins(&self) -> &[LoadedPlugin] {
+        &self.plugins
+    }
+}
@@ -0,0 +1,47 @@
+use crate::SessionStore;
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+
+/// 保存在redis中的session, 适合在容器中运行
+///
+/// ```ignore
+/// ClientBuilder::new().session_store(RedisSessionStore::boxed("redis://127.0.0.1/", "proc_qq:session:123456")?)
+/// ```
+pub struct RedisSessionStore {
+    client: redis::Client,
+    key: String,
+}
+
+impl RedisSessionStore {
+    pub fn new(url: &str, key: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            key: key.into(),
+        })
+    }
+
+    pub fn boxed(url: &str, key: impl Into<String>) -> Result<Box<dyn SessionStore + Send + Sync>> {
+        Ok(Box::new(Self::new(url, key)?))
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn save_session(&self, data: Vec<u8>) -> Result<()> {
+        let mut connection = self.client.get_async_connection().await?;
+        connection.set(&self.key, data).await?;
+        Ok(())
+    }
+
+    async fn load_session(&self) -> Result<Option<Vec<u8>>> {
+        let mut connection = self.client.get_async_connection().await?;
+        Ok(connection.get(&self.key).await?)
+    }
+
+    async fn remove_session(&self) -> Result<()> {
+        let mut connection = self.client.get_async_connection().await?;
+        connection.del(&self.key).await?;
+        Ok(())
+    }
+}
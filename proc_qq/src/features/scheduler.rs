@@ -0,0 +1,135 @@
+use crate::ExtensionsTrait;
+use anyhow::{Context, Result};
+use chrono::Local;
+use cron::Schedule;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+type JobFn = Box<dyn Fn(Arc<ricq::Client>) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+enum Trigger {
+    Cron(Schedule),
+    Every(Duration),
+}
+
+struct Job {
+    name: String,
+    trigger: Trigger,
+    run: JobFn,
+}
+
+/// 定时任务
+///
+/// 使用`ClientBuilder::scheduler`设置, 第一次登录成功后开始运行, 断线重连时不会中断.
+/// 同一个任务不会并行执行, 执行时间超过间隔时等到之后的下一次.
+///
+/// ```ignore
+/// let scheduler = Scheduler::new()
+///     // 秒 分 时 日 月 星期
+///     .cron("早安", "0 0 8 * * *", |client| async move {
+///         client
+///             .send_group_message(group_code, "早上好".parse_message_chain())
+///             .await?;
+///         Ok(())
+///     })?
+///     .every("刷新数据", Duration::from_secs(600), |_client| async move { Ok(()) });
+/// ClientBuilder::new().scheduler(scheduler)
+/// ```
+pub struct Scheduler {
+    jobs: Vec<Arc<Job>>,
+    started: AtomicBool,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            jobs: vec![],
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// 按cron表达式执行 (本地时间, 包含秒: `秒 分 时 日 月 星期 [年]`)
+    pub fn cron<F, Fut>(mut self, name: impl Into<String>, expression: &str, job: F) -> Result<Self>
+    where
+        F: Fn(Arc<ricq::Client>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let schedule = Schedule::from_str(expression)
+            .with_context(|| format!("cron表达式不正确 : {}", expression))?;
+        self.push(name.into(), Trigger::Cron(schedule), job);
+        Ok(self)
+    }
+
+    /// 每隔一段时间执行, 第一次在开始运行后经过interval执行
+    pub fn every<F, Fut>(mut self, name: impl Into<String>, interval: Duration, job: F) -> Self
+    where
+        F: Fn(Arc<ricq::Client>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.push(name.into(), Trigger::Every(interval), job);
+        self
+    }
+
+    fn push<F, Fut>(&mut self, name: String, trigger: Trigger, job: F)
+    where
+        F: Fn(Arc<ricq::Client>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.jobs.push(Arc::new(Job {
+            name,
+            trigger,
+            run: Box::new(move |client| Box::pin(job(client))),
+        }));
+    }
+
+    /// 任务的名称
+    pub fn job_names(&self) -> Vec<String> {
+        self.jobs.iter().map(|job| job.name.clone()).collect()
+    }
+
+    fn start(&self, client: Arc<ricq::Client>) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        for job in &self.jobs {
+            tokio::spawn(run_job(job.clone(), client.clone()));
+        }
+    }
+}
+
+async fn run_job(job: Arc<Job>, client: Arc<ricq::Client>) {
+    loop {
+        let delay = match &job.trigger {
+            Trigger::Every(interval) => *interval,
+            Trigger::Cron(schedule) => match schedule.upcoming(Local).next() {
+                Some(time) => (time - Local::now()).to_std().unwrap_or_default(),
+                None => {
+                    tracing::info!("定时任务 {} 已没有下一次执行时间", job.name);
+                    return;
+                }
+            },
+        };
+        tokio::time::sleep(delay).await;
+        tracing::debug!("执行定时任务 : {}", job.name);
+        if let Err(err) = (job.run)(client.clone()).await {
+            tracing::error!("定时任务 {} 出现错误 : {:?}", job.name, err);
+        }
+    }
+}
+
+/// 登录成功后启动客户端中设置的定时任务, 只会启动一次
+pub(crate) fn start_scheduler(client: &Arc<ricq::Client>) {
+    if let Some(scheduler) = client.extensions().get::<Scheduler>() {
+        scheduler.start(client.clone());
+    }
+}
@@ -0,0 +1,389 @@
+use crate::audit::record_config_change;
+use crate::persist::{load_json, save_json};
+use crate::{
+    sender_is_master, CommandInfo, MessageContentTrait, MessageEvent, MessageEventProcess,
+    MessageSendToSourceTrait, Module, ModuleEventHandler, ModuleEventProcess, Role,
+    TextEleParseTrait,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, Scope, AST};
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+/// 脚本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Script {
+    pub name: String,
+    /// 匹配消息的正则表达式
+    pub pattern: String,
+    /// 只在这个群生效, None为所有消息
+    #[serde(default)]
+    pub group_code: Option<i64>,
+    /// Rhai代码
+    pub source: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ScriptsData {
+    scripts: Vec<Script>,
+    /// 脚本名称 -> KV
+    #[serde(default)]
+    kv: HashMap<String, HashMap<String, String>>,
+}
+
+struct CompiledScript {
+    script: Script,
+    regex: Regex,
+    ast: AST,
+}
+
+/// 每次执行时可以调用的函数和结果
+#[derive(Default)]
+struct ScriptContext {
+    replies: Vec<String>,
+    kv: HashMap<String, String>,
+    kv_changed: bool,
+}
+
+/// 运行时可以修改的Rhai脚本
+///
+/// 通过`script_module`加入到模块中生效, 主人可以使用`/script`指令进行管理.
+/// 脚本中可以使用以下变量和函数:
+///
+/// - `text` 消息内容, `sender` 发送者QQ号, `group` 群号(私聊为0), `captures` 正则的捕获组
+/// - `reply(text)` 回复消息, `kv_get(key)` `kv_set(key, value)` `kv_delete(key)` 读写脚本自己的KV
+///
+/// ```text
+/// /script add greet ^早(上好)?$ reply(`早, ${sender}`);
+/// ```
+pub struct ScriptEngine {
+    path: Option<String>,
+    scripts: RwLock<Vec<CompiledScript>>,
+    kv: Mutex<HashMap<String, HashMap<String, String>>>,
+}
+
+// 禁止加载文件和eval, 限制执行的指令数和数据大小
+fn sandbox_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(100_000);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(10_000);
+    engine.set_max_array_size(1_000);
+    engine.set_max_map_size(1_000);
+    engine
+}
+
+impl CompiledScript {
+    fn compile(script: Script) -> Result<Self> {
+        let regex = Regex::new(&script.pattern)?;
+        let ast = sandbox_engine()
+            .compile(&script.source)
+            .map_err(|err| anyhow!("脚本语法错误 : {}", err))?;
+        Ok(Self { script, regex, ast })
+    }
+}
+
+impl ScriptEngine {
+    /// 仅保存在内存中的脚本
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            scripts: RwLock::new(vec![]),
+            kv: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 保存在文件中的脚本和KV, 需要调用`load`加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    pub async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let data: ScriptsData = load_json(path).await?;
+            let mut scripts = vec![];
+            for script in data.scripts {
+                scripts.push(CompiledScript::compile(script)?);
+            }
+            *self.scripts.write().await = scripts;
+            *self.kv.lock().unwrap() = data.kv;
+        }
+        Ok(())
+    }
+
+    async fn save(&self, scripts: &[CompiledScript]) -> Result<()> {
+        if let Some(path) = &self.path {
+            let data = ScriptsData {
+                scripts: scripts.iter().map(|s| s.script.clone()).collect(),
+                kv: self.kv.lock().unwrap().clone(),
+            };
+            save_json(path, &data).await?;
+        }
+        Ok(())
+    }
+
+    /// 添加脚本, 存在同名的脚本时替换
+    pub async fn add_script(&self, script: Script) -> Result<()> {
+        let compiled = CompiledScript::compile(script)?;
+        let mut scripts = self.scripts.write().await;
+        match scripts
+            .iter_mut()
+            .find(|s| s.script.name == compiled.script.name)
+        {
+            Some(old) => *old = compiled,
+            None => scripts.push(compiled),
+        }
+        self.save(&scripts).await
+    }
+
+    /// 删除脚本和它的KV, 返回是否存在
+    pub async fn remove_script(&self, name: &str) -> Result<bool> {
+        let mut scripts = self.scripts.write().await;
+        let len = scripts.len();
+        scripts.retain(|s| s.script.name != name);
+        let removed = scripts.len() != len;
+        if removed {
+            self.kv.lock().unwrap().remove(name);
+            self.save(&scripts).await?;
+        }
+        Ok(removed)
+    }
+
+    pub async fn scripts(&self) -> Vec<Script> {
+        self.scripts
+            .read()
+            .await
+            .iter()
+            .map(|s| s.script.clone())
+            .collect()
+    }
+
+    fn run(
+        &self,
+        compiled: &CompiledScript,
+        event: &MessageEvent,
+        content: &str,
+        group_code: Option<i64>,
+    ) -> Result<ScriptContext> {
+        let name = &compiled.script.name;
+        let context = Arc::new(Mutex::new(ScriptContext {
+            kv: self
+                .kv
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .unwrap_or_default(),
+            ..Default::default()
+        }));
+        let mut engine = sandbox_engine();
+        let ctx = context.clone();
+        engine.register_fn("reply", move |text: Dynamic| {
+            ctx.lock().unwrap().replies.push(text.to_string());
+        });
+        let ctx = context.clone();
+        engine.register_fn("kv_get", move |key: &str| -> Dynamic {
+            match ctx.lock().unwrap().kv.get(key) {
+                Some(value) => value.clone().into(),
+                None => Dynamic::UNIT,
+            }
+        });
+        let ctx = context.clone();
+        engine.register_fn("kv_set", move |key: &str, value: Dynamic| {
+            let mut ctx = ctx.lock().unwrap();
+            ctx.kv.insert(key.to_owned(), value.to_string());
+            ctx.kv_changed = true;
+        });
+        let ctx = context.clone();
+        engine.register_fn("kv_delete", move |key: &str| {
+            let mut ctx = ctx.lock().unwrap();
+            if ctx.kv.remove(key).is_some() {
+                ctx.kv_changed = true;
+            }
+        });
+        let captures: Array = compiled
+            .regex
+            .captures(content)
+            .map(|captures| {
+                captures
+                    .iter()
+                    .map(|m| m.map(|m| m.as_str().to_owned()).unwrap_or_default().into())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut scope = Scope::new();
+        scope.push_constant("text", content.to_owned());
+        scope.push_constant("sender", event.from_uin());
+        scope.push_constant("group", group_code.unwrap_or(0));
+        scope.push_constant("captures", captures);
+        engine
+            .run_ast_with_scope(&mut scope, &compiled.ast)
+            .map_err(|err| anyhow!("脚本执行失败 {} : {}", name, err))?;
+        drop(engine);
+        let context = Arc::try_unwrap(context)
+            .map_err(|_| anyhow!("脚本执行失败 {}", name))?
+            .into_inner()
+            .unwrap();
+        Ok(context)
+    }
+
+    /// 执行第一个匹配的脚本, 返回脚本是否回复了消息
+    pub async fn handle(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let group_code = match event {
+            MessageEvent::GroupMessage(e) => Some(e.inner.group_code),
+            _ => None,
+        };
+        let (name, context) = {
+            let scripts = self.scripts.read().await;
+            let compiled = scripts.iter().find(|s| {
+                (s.script.group_code.is_none() || s.script.group_code == group_code)
+                    && s.regex.is_match(&content)
+            });
+            match compiled {
+                Some(compiled) => (
+                    compiled.script.name.clone(),
+                    self.run(compiled, event, &content, group_code)?,
+                ),
+                None => return Ok(false),
+            }
+        };
+        if context.kv_changed {
+            self.kv.lock().unwrap().insert(name, context.kv);
+            let scripts = self.scripts.read().await;
+            self.save(&scripts).await?;
+        }
+        for reply in &context.replies {
+            event
+                .send_message_to_source(MessageChain::new(reply.as_str().parse_text()))
+                .await?;
+        }
+        Ok(!context.replies.is_empty())
+    }
+
+    /// 处理主人发出的管理指令, 返回是否为管理指令
+    pub async fn handle_command(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let mut sp = content.trim().splitn(5, char::is_whitespace);
+        if sp.next() != Some("/script") {
+            return Ok(false);
+        }
+        let args: Vec<&str> = sp.collect();
+        let reply = match args.as_slice() {
+            [] | ["list"] => {
+                let scripts = self.scripts().await;
+                if scripts.is_empty() {
+                    "没有脚本".to_owned()
+                } else {
+                    scripts
+                        .iter()
+                        .map(|s| match s.group_code {
+                            Some(group_code) => {
+                                format!("{} : {} (群 {})", s.name, s.pattern, group_code)
+                            }
+                            None => format!("{} : {}", s.name, s.pattern),
+                        })
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                }
+            }
+            ["show", name] => match self.scripts().await.into_iter().find(|s| s.name == *name) {
+                Some(script) => format!("{} : {}\n{}", script.name, script.pattern, script.source),
+                None => format!("脚本不存在 : {}", name),
+            },
+            ["del", name] => {
+                if self.remove_script(name).await? {
+                    "OK".to_owned()
+                } else {
+                    format!("脚本不存在 : {}", name)
+                }
+            }
+            ["add", name, pattern, source] => {
+                let script = Script {
+                    name: name.to_string(),
+                    pattern: pattern.to_string(),
+                    // 在群中添加的脚本只在该群生效
+                    group_code: match event {
+                        MessageEvent::GroupMessage(e) => Some(e.inner.group_code),
+                        _ => None,
+                    },
+                    source: source.trim().to_owned(),
+                };
+                match self.add_script(script).await {
+                    Ok(_) => format!("已保存脚本 : {}", name),
+                    Err(err) => format!("保存脚本失败 : {}", err),
+                }
+            }
+            _ => SCRIPT_USAGE.to_owned(),
+        };
+        if reply == "OK" || reply.starts_with("已") {
+            record_config_change(event).await;
+        }
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+}
+
+static SCRIPT_USAGE: &str = "/script list\n\
+/script show 名称\n\
+/script add 名称 正则 代码 (同名时替换)\n\
+/script del 名称";
+
+struct ScriptHandler {
+    engine: Arc<ScriptEngine>,
+}
+
+#[async_trait]
+impl MessageEventProcess for ScriptHandler {
+    async fn handle(&self, event: &MessageEvent) -> Result<bool> {
+        if sender_is_master(event).await && self.engine.handle_command(event).await? {
+            return Ok(true);
+        }
+        match self.engine.handle(event).await {
+            Ok(replied) => Ok(replied),
+            Err(err) => {
+                tracing::warn!("{:?}", err);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// 内置的脚本模块
+///
+/// ```ignore
+/// let engine = Arc::new(ScriptEngine::file("scripts.json"));
+/// engine.load().await?;
+/// let modules = vec![script_module(engine.clone()), ...];
+/// ```
+pub fn script_module(engine: Arc<ScriptEngine>) -> Module {
+    Module {
+        id: "proc_qq_scripts".to_owned(),
+        name: "脚本".to_owned(),
+        handles: vec![ModuleEventHandler {
+            name: "scripts".to_owned(),
+            process: ModuleEventProcess::Message(Box::new(ScriptHandler { engine })),
+            command: Some(CommandInfo {
+                usage: "/script".to_owned(),
+                description: SCRIPT_USAGE.to_owned(),
+                require: Some(Role::Master),
+            }),
+        }],
+        data: Arc::new(Default::default()),
+        priority: 0,
+    }
+}
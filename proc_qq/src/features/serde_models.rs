@@ -0,0 +1,220 @@
+use crate::{ImageElement, MessageEvent};
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq_core::msg::elem::{At, Face, RQElem, Text};
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+
+/// 可以序列化的消息元素
+///
+/// 用于导出、存档和回放等需要稳定格式的场景, 字段只增不改
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ElementModel {
+    Text {
+        content: String,
+    },
+    At {
+        target: i64,
+        display: String,
+    },
+    Face {
+        index: i32,
+        name: String,
+    },
+    Image {
+        url: String,
+        /// 小写hex
+        md5: String,
+        width: u32,
+        height: u32,
+        size: u32,
+        image_type: i32,
+        flash: bool,
+    },
+    Reply {
+        seq: i32,
+        sender: i64,
+    },
+    /// 其他元素, 只保留类型名
+    Other {
+        kind: String,
+    },
+}
+
+impl ElementModel {
+    fn image(image: ImageElement) -> Self {
+        ElementModel::Image {
+            url: image.url(),
+            md5: image.md5().iter().map(|b| format!("{:02x}", b)).collect(),
+            width: image.width(),
+            height: image.height(),
+            size: image.size(),
+            image_type: image.image_type(),
+            flash: image.is_flash(),
+        }
+    }
+}
+
+impl From<RQElem> for ElementModel {
+    fn from(elem: RQElem) -> Self {
+        match elem {
+            RQElem::Text(text) => ElementModel::Text {
+                content: text.content,
+            },
+            RQElem::At(at) => ElementModel::At {
+                target: at.target,
+                display: at.display,
+            },
+            RQElem::Face(face) => ElementModel::Face {
+                index: face.index,
+                name: face.name,
+            },
+            RQElem::GroupImage(image) => Self::image(ImageElement::GroupImage(image)),
+            RQElem::FriendImage(image) => Self::image(ImageElement::FriendImage(image)),
+            RQElem::FlashImage(image) => Self::image(ImageElement::FlashImage(image)),
+            RQElem::Reply(reply) => ElementModel::Reply {
+                seq: reply.reply_seq,
+                sender: reply.sender,
+            },
+            other => ElementModel::Other {
+                kind: format!("{:?}", other)
+                    .split(|c: char| !c.is_alphanumeric())
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+            },
+        }
+    }
+}
+
+/// 可以序列化的消息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MessageChainModel(pub Vec<ElementModel>);
+
+impl From<&MessageChain> for MessageChainModel {
+    fn from(chain: &MessageChain) -> Self {
+        Self(chain.clone().into_iter().map(ElementModel::from).collect())
+    }
+}
+
+impl MessageChainModel {
+    /// 还原为可以发送的消息, 只有文字、@和表情可以还原, 图片等元素会被忽略
+    pub fn to_message_chain(&self) -> MessageChain {
+        let mut chain = MessageChain::default();
+        for elem in &self.0 {
+            match elem {
+                ElementModel::Text { content } => chain.push(Text::new(content.clone())),
+                ElementModel::At { target, display } => {
+                    let mut at = At::new(*target);
+                    at.display = display.clone();
+                    chain.push(at)
+                }
+                ElementModel::Face { index, .. } => chain.push(Face::new(*index)),
+                _ => {}
+            }
+        }
+        chain
+    }
+}
+
+/// 可以序列化的消息事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageEventModel {
+    GroupMessage {
+        group_code: i64,
+        group_name: String,
+        from_uin: i64,
+        group_card: String,
+        /// unix秒
+        time: i32,
+        seqs: Vec<i32>,
+        elements: MessageChainModel,
+    },
+    FriendMessage {
+        from_uin: i64,
+        from_nick: String,
+        time: i32,
+        seqs: Vec<i32>,
+        elements: MessageChainModel,
+    },
+    GroupTempMessage {
+        group_code: i64,
+        from_uin: i64,
+        from_nick: String,
+        time: i32,
+        seqs: Vec<i32>,
+        elements: MessageChainModel,
+    },
+}
+
+impl From<&GroupMessageEvent> for MessageEventModel {
+    fn from(event: &GroupMessageEvent) -> Self {
+        let inner = &event.inner;
+        MessageEventModel::GroupMessage {
+            group_code: inner.group_code,
+            group_name: inner.group_name.clone(),
+            from_uin: inner.from_uin,
+            group_card: inner.group_card.clone(),
+            time: inner.time,
+            seqs: inner.seqs.clone(),
+            elements: (&inner.elements).into(),
+        }
+    }
+}
+
+impl From<&FriendMessageEvent> for MessageEventModel {
+    fn from(event: &FriendMessageEvent) -> Self {
+        let inner = &event.inner;
+        MessageEventModel::FriendMessage {
+            from_uin: inner.from_uin,
+            from_nick: inner.from_nick.clone(),
+            time: inner.time,
+            seqs: inner.seqs.clone(),
+            elements: (&inner.elements).into(),
+        }
+    }
+}
+
+impl From<&GroupTempMessageEvent> for MessageEventModel {
+    fn from(event: &GroupTempMessageEvent) -> Self {
+        let inner = &event.inner;
+        MessageEventModel::GroupTempMessage {
+            group_code: inner.group_code,
+            from_uin: inner.from_uin,
+            from_nick: inner.from_nick.clone(),
+            time: inner.time,
+            seqs: inner.seqs.clone(),
+            elements: (&inner.elements).into(),
+        }
+    }
+}
+
+impl From<&MessageEvent> for MessageEventModel {
+    fn from(event: &MessageEvent) -> Self {
+        match event {
+            MessageEvent::GroupMessage(event) => event.into(),
+            MessageEvent::FriendMessage(event) => event.into(),
+            MessageEvent::GroupTempMessage(event) => event.into(),
+        }
+    }
+}
+
+impl MessageEventModel {
+    pub fn from_uin(&self) -> i64 {
+        match self {
+            MessageEventModel::GroupMessage { from_uin, .. }
+            | MessageEventModel::FriendMessage { from_uin, .. }
+            | MessageEventModel::GroupTempMessage { from_uin, .. } => *from_uin,
+        }
+    }
+
+    pub fn elements(&self) -> &MessageChainModel {
+        match self {
+            MessageEventModel::GroupMessage { elements, .. }
+            | MessageEventModel::FriendMessage { elements, .. }
+            | MessageEventModel::GroupTempMessage { elements, .. } => elements,
+        }
+    }
+}
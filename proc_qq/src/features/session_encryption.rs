@@ -0,0 +1,90 @@
+use crate::SessionStore;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// 加密后的数据: MAGIC + nonce(12) + 密文
+const MAGIC: &[u8] = b"PQSE1";
+const NONCE_LEN: usize = 12;
+
+/// 加密保存session, 包装其他的SessionStore
+///
+/// 使用AES-256-GCM, 密钥为32字节, 请通过环境变量或密钥管理服务提供, 不要写在代码中.
+/// 读取到未加密的旧数据时照常使用, 下次保存时会加密.
+///
+/// ```ignore
+/// ClientBuilder::new()
+///     .session_store(FileSessionStore::boxed("session.token"))
+///     .session_encryption_key(key)
+/// ```
+pub struct EncryptedSessionStore {
+    inner: Arc<Option<Box<dyn SessionStore + Send + Sync>>>,
+    cipher: Aes256Gcm,
+}
+
+impl EncryptedSessionStore {
+    pub fn boxed(
+        inner: Box<dyn SessionStore + Send + Sync>,
+        key: [u8; 32],
+    ) -> Box<dyn SessionStore + Send + Sync> {
+        Box::new(Self::wrap(Arc::new(Some(inner)), key))
+    }
+
+    pub(crate) fn wrap(
+        inner: Arc<Option<Box<dyn SessionStore + Send + Sync>>>,
+        key: [u8; 32],
+    ) -> Self {
+        Self {
+            inner,
+            cipher: Aes256Gcm::new(&key.into()),
+        }
+    }
+
+    fn inner(&self) -> Result<&(dyn SessionStore + Send + Sync)> {
+        self.inner
+            .as_deref()
+            .ok_or_else(|| anyhow!("没有设置session_store"))
+    }
+}
+
+#[async_trait]
+impl SessionStore for EncryptedSessionStore {
+    async fn save_session(&self, data: Vec<u8>) -> Result<()> {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let encrypted = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), data.as_slice())
+            .map_err(|_| anyhow!("加密session失败"))?;
+        let mut output = Vec::with_capacity(MAGIC.len() + NONCE_LEN + encrypted.len());
+        output.extend_from_slice(MAGIC);
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&encrypted);
+        self.inner()?.save_session(output).await
+    }
+
+    async fn load_session(&self) -> Result<Option<Vec<u8>>> {
+        let data = match self.inner()?.load_session().await? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let body = match data.strip_prefix(MAGIC) {
+            Some(body) if body.len() > NONCE_LEN => body,
+            _ => {
+                tracing::warn!("session未加密, 下次保存时将加密");
+                return Ok(Some(data));
+            }
+        };
+        let (nonce, encrypted) = body.split_at(NONCE_LEN);
+        let decrypted = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), encrypted)
+            .map_err(|_| anyhow!("解密session失败, 请检查密钥"))?;
+        Ok(Some(decrypted))
+    }
+
+    async fn remove_session(&self) -> Result<()> {
+        self.inner()?.remove_session().await
+    }
+}
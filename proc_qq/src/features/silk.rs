@@ -0,0 +1,14 @@
+use anyhow::Result;
+use std::time::Duration;
+
+/// 将16位小端单声道PCM编码为silk, 返回silk数据和时长
+pub fn encode_pcm_to_silk(pcm: Vec<u8>, sample_rate: u32) -> Result<(Vec<u8>, Duration)> {
+    let duration = Duration::from_millis(pcm.len() as u64 / 2 * 1000 / sample_rate.max(1) as u64);
+    let silk = silk_rs::encode_silk(pcm, sample_rate as i32, 24000, true)?;
+    Ok((silk, duration))
+}
+
+/// 将收到的silk语音解码为16位小端单声道PCM
+pub fn decode_silk_to_pcm(silk: Vec<u8>, sample_rate: u32) -> Result<Vec<u8>> {
+    Ok(silk_rs::decode_silk(silk, sample_rate as i32)?)
+}
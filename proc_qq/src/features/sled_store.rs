@@ -0,0 +1,49 @@
+use crate::ModuleStore;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// 使用sled保存的存储, 每个命名空间为一个Tree
+pub struct SledStore {
+    db: sled::Db,
+}
+
+impl SledStore {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Ok(Self {
+            db: sled::open(path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl ModuleStore for SledStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        match self.db.open_tree(namespace)?.get(key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()> {
+        let tree = self.db.open_tree(namespace)?;
+        tree.insert(key, serde_json::to_vec(&value)?)?;
+        tree.flush_async().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool> {
+        let tree = self.db.open_tree(namespace)?;
+        let removed = tree.remove(key)?.is_some();
+        tree.flush_async().await?;
+        Ok(removed)
+    }
+
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        let mut keys = vec![];
+        for key in self.db.open_tree(namespace)?.iter().keys() {
+            keys.push(String::from_utf8_lossy(&key?).into_owned());
+        }
+        Ok(keys)
+    }
+}
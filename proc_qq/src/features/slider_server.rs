@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+static SLIDER_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>滑动验证</title>
+</head>
+<body>
+<h3>滑动验证</h3>
+<ol>
+<li>在浏览器中按F12打开开发者工具, 切换到网络(Network)面板</li>
+<li>打开 <a href="{verify_url}" target="_blank">验证链接</a> 完成滑动</li>
+<li>在网络面板中找到 cap_union_new_verify 请求, 复制响应中ticket的值</li>
+</ol>
+<textarea id="ticket" rows="6" cols="60" placeholder="ticket"></textarea>
+<br>
+<button onclick="submitTicket()">提交</button>
+<p id="result"></p>
+<script>
+function submitTicket() {
+    var ticket = document.getElementById('ticket').value.trim();
+    if (!ticket) {
+        return;
+    }
+    fetch('ticket', { method: 'POST', body: ticket })
+        .then(function (rsp) { return rsp.text(); })
+        .then(function (text) { document.getElementById('result').innerText = text; });
+}
+</script>
+</body>
+</html>
+"#;
+
+struct State {
+    page: String,
+    sender: Mutex<Option<oneshot::Sender<String>>>,
+}
+
+impl State {
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/") => Response::builder()
+                .header("Content-Type", "text/html; charset=utf-8")
+                .body(Body::from(self.page.clone()))
+                .unwrap(),
+            (&Method::POST, "/ticket") => {
+                let body = match hyper::body::to_bytes(request.into_body()).await {
+                    Ok(body) => body,
+                    Err(_) => return text_response(StatusCode::BAD_REQUEST, "读取ticket失败"),
+                };
+                let ticket = String::from_utf8_lossy(&body).trim().to_owned();
+                if ticket.is_empty() {
+                    return text_response(StatusCode::BAD_REQUEST, "ticket不能为空");
+                }
+                match self.sender.lock().unwrap().take() {
+                    Some(sender) => {
+                        let _ = sender.send(ticket);
+                        text_response(StatusCode::OK, "已提交, 可以关闭页面")
+                    }
+                    None => text_response(StatusCode::CONFLICT, "已经提交过ticket"),
+                }
+            }
+            _ => text_response(StatusCode::NOT_FOUND, "not found"),
+        }
+    }
+}
+
+fn text_response(status: StatusCode, text: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .body(Body::from(text))
+        .unwrap()
+}
+
+/// 在port端口启动网页, 在其他设备的浏览器中完成滑动后提交ticket, 收到后关闭
+pub(crate) async fn ticket(port: u16, verify_url: &str) -> Result<String> {
+    let (sender, receiver) = oneshot::channel();
+    let state = Arc::new(State {
+        page: SLIDER_HTML.replace("{verify_url}", &html_escape(verify_url)),
+        sender: Mutex::new(Some(sender)),
+    });
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(state.handle(request).await) }
+            }))
+        }
+    });
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("滑动验证页面启动失败 : {}", addr))?
+        .serve(make_service)
+        .with_graceful_shutdown(async {
+            let _ = shutdown_signal.await;
+        });
+    let server = tokio::spawn(server);
+    tracing::info!(
+        "需要滑动验证, 请在浏览器中打开 http://服务器地址:{}/ 按照页面提示完成滑动并提交ticket",
+        port
+    );
+    let ticket = receiver.await.with_context(|| "没有收到ticket")?;
+    let _ = shutdown.send(());
+    let _ = server.await;
+    Ok(ticket)
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
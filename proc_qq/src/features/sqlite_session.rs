@@ -0,0 +1,68 @@
+use crate::SessionStore;
+use anyhow::Result;
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::Mutex;
+
+/// 保存在sqlite中的session, 多个账号可以使用同一个数据库 (按name区分)
+///
+/// ```ignore
+/// ClientBuilder::new().session_store(SqliteSessionStore::boxed("data.db", "123456")?)
+/// ```
+pub struct SqliteSessionStore {
+    connection: Mutex<Connection>,
+    name: String,
+}
+
+impl SqliteSessionStore {
+    pub fn open(path: impl AsRef<std::path::Path>, name: impl Into<String>) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS session (name TEXT PRIMARY KEY, data BLOB NOT NULL)",
+            [],
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            name: name.into(),
+        })
+    }
+
+    pub fn boxed(
+        path: impl AsRef<std::path::Path>,
+        name: impl Into<String>,
+    ) -> Result<Box<dyn SessionStore + Send + Sync>> {
+        Ok(Box::new(Self::open(path, name)?))
+    }
+}
+
+#[async_trait]
+impl SessionStore for SqliteSessionStore {
+    async fn save_session(&self, data: Vec<u8>) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO session (name, data) VALUES (?1, ?2)",
+            params![self.name, data],
+        )?;
+        Ok(())
+    }
+
+    async fn load_session(&self) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM session WHERE name = ?1",
+                params![self.name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    async fn remove_session(&self) -> Result<()> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM session WHERE name = ?1", params![self.name])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use crate::ModuleStore;
+use anyhow::Result;
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+/// 使用sqlite保存的存储, 所有命名空间保存在同一张表中
+pub struct SqliteStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS module_store (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (namespace, key)
+            )",
+            [],
+        )?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// sqlite的调用是阻塞的, 放到阻塞线程中执行
+    async fn with_connection<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || f(&connection.lock().unwrap())).await?
+    }
+}
+
+#[async_trait]
+impl ModuleStore for SqliteStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        let (namespace, key) = (namespace.to_owned(), key.to_owned());
+        let value: Option<String> = self
+            .with_connection(move |connection| {
+                Ok(connection
+                    .query_row(
+                        "SELECT value FROM module_store WHERE namespace = ?1 AND key = ?2",
+                        params![namespace, key],
+                        |row| row.get(0),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        match value {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()> {
+        let (namespace, key) = (namespace.to_owned(), key.to_owned());
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO module_store (namespace, key, value) VALUES (?1, ?2, ?3)",
+                params![namespace, key, value.to_string()],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool> {
+        let (namespace, key) = (namespace.to_owned(), key.to_owned());
+        self.with_connection(move |connection| {
+            let removed = connection.execute(
+                "DELETE FROM module_store WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        let namespace = namespace.to_owned();
+        self.with_connection(move |connection| {
+            let mut statement =
+                connection.prepare("SELECT key FROM module_store WHERE namespace = ?1")?;
+            let keys = statement
+                .query_map(params![namespace], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(keys)
+        })
+        .await
+    }
+}
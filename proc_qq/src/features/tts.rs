@@ -0,0 +1,43 @@
+use crate::features::silk::encode_pcm_to_silk;
+use crate::{MessageSendToSourceTrait, AUDIO_CODEC_SILK};
+use anyhow::Result;
+use async_trait::async_trait;
+use ricq_core::structs::MessageReceipt;
+use std::time::Duration;
+
+/// 合成的语音
+pub enum TtsAudio {
+    /// 16位小端单声道PCM, 会被编码为silk
+    Pcm { data: Vec<u8>, sample_rate: u32 },
+    /// 已经编码好的silk
+    Silk { data: Vec<u8>, duration: Duration },
+}
+
+/// 文字转语音, 只需要实现合成部分.
+/// 合成结果为mp3等格式时, 请先转换为PCM (例如使用ffmpeg).
+#[async_trait]
+pub trait TtsProvider: Send + Sync {
+    async fn synthesize(&self, text: &str) -> Result<TtsAudio>;
+}
+
+#[async_trait]
+pub trait TtsReplyTrait: MessageSendToSourceTrait {
+    /// 合成语音并发送到消息来源, 临时会话不支持发送语音
+    async fn reply_voice_tts<P: TtsProvider + ?Sized>(
+        &self,
+        text: &str,
+        provider: &P,
+    ) -> Result<MessageReceipt> {
+        let (data, duration) = match provider.synthesize(text).await? {
+            TtsAudio::Pcm { data, sample_rate } => {
+                tokio::task::spawn_blocking(move || encode_pcm_to_silk(data, sample_rate)).await??
+            }
+            TtsAudio::Silk { data, duration } => (data, duration),
+        };
+        Ok(self
+            .send_audio_to_source(data, AUDIO_CODEC_SILK, duration)
+            .await?)
+    }
+}
+
+impl<T: MessageSendToSourceTrait> TtsReplyTrait for T {}
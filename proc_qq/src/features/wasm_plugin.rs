@@ -0,0 +1,320 @@
+use crate::persist::save_json;
+use crate::{
+    MessageChainTextTrait, MessageEvent, MessageEventProcess, MessageSendToSourceTrait, Module,
+    ModuleEventHandler, ModuleEventProcess, TextEleParseTrait,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ricq_core::msg::MessageChain;
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Memory, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+/// WASM插件的资源限制
+#[derive(Debug, Clone)]
+pub struct WasmLimits {
+    /// 每个事件可以消耗的fuel (约等于执行的指令数)
+    pub fuel: u64,
+    /// 线性内存的上限 (字节)
+    pub memory: usize,
+    /// 每个事件最多回复的消息数
+    pub replies: usize,
+    /// 每条回复的最大长度 (字节)
+    pub reply_len: usize,
+    /// KV中最多的键数
+    pub kv_keys: usize,
+    /// KV中每个值的最大长度 (字节)
+    pub kv_value_len: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory: 16 * 1024 * 1024,
+            replies: 5,
+            reply_len: 4500,
+            kv_keys: 1000,
+            kv_value_len: 64 * 1024,
+        }
+    }
+}
+
+/// 传给插件的事件, 插件通过`event_len`和`event_read`读取json
+#[derive(Serialize)]
+struct WasmEvent {
+    group_code: Option<i64>,
+    from_uin: i64,
+    text: String,
+}
+
+struct HostState {
+    event: Vec<u8>,
+    replies: Vec<String>,
+    kv: Arc<Mutex<HashMap<String, String>>>,
+    kv_dirty: bool,
+    limits: WasmLimits,
+    store_limits: StoreLimits,
+}
+
+/// 在沙箱中运行的WASM插件
+///
+/// 插件只能通过宿主提供的接口(模块名`proc_qq`)读取当前消息、回复消息和访问自己的KV,
+/// 不能访问文件、网络和客户端的其他功能, 适合在公用的机器人上运行用户提交的指令.
+///
+/// 插件需要导出`memory`和`on_message() -> i32`(返回1时拦截事件), 可以导入:
+///
+/// - `event_len() -> i32`, `event_read(ptr: i32)` 读取事件的json `{"group_code":..,"from_uin":..,"text":..}`
+/// - `reply(ptr: i32, len: i32)` 回复一条文字消息, 在`on_message`返回后发送
+/// - `kv_get(key_ptr, key_len, buf_ptr, buf_len) -> i32` 返回值的长度, 不存在时返回-1, 缓冲区不足时只写入前buf_len个字节
+/// - `kv_set(key_ptr, key_len, value_ptr, value_len)` `kv_delete(key_ptr, key_len)`
+///
+/// ```ignore
+/// let plugin = WasmPlugin::load("plugins/dice.wasm")?.kv_file("plugins/dice.kv.json")?;
+/// let modules = vec![plugin.module("dice", "骰子")];
+/// ```
+pub struct WasmPlugin {
+    engine: Engine,
+    module: wasmtime::Module,
+    limits: WasmLimits,
+    kv: Arc<Mutex<HashMap<String, String>>>,
+    kv_file: Option<String>,
+}
+
+impl WasmPlugin {
+    /// 加载并编译插件
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = wasmtime::Module::from_file(&engine, path)
+            .with_context(|| format!("加载WASM插件失败 : {}", path.display()))?;
+        Ok(Self {
+            engine,
+            module,
+            limits: WasmLimits::default(),
+            kv: Arc::new(Mutex::new(HashMap::new())),
+            kv_file: None,
+        })
+    }
+
+    pub fn limits(mut self, limits: WasmLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 将插件的KV保存在文件中, 文件存在时立即读取
+    pub fn kv_file(mut self, path: impl Into<String>) -> Result<Self> {
+        let path = path.into();
+        if Path::new(&path).exists() {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("读取文件失败 : {}", path))?;
+            *self.kv.lock().unwrap() =
+                serde_json::from_str(&text).with_context(|| format!("解析文件失败 : {}", path))?;
+        }
+        self.kv_file = Some(path);
+        Ok(self)
+    }
+
+    /// 包装成处理消息事件的模块
+    pub fn module(self, id: impl Into<String>, name: impl Into<String>) -> Module {
+        Module {
+            id: id.into(),
+            name: name.into(),
+            handles: vec![ModuleEventHandler {
+                name: "wasm_on_message".to_owned(),
+                process: ModuleEventProcess::Message(Box::new(WasmHandler(Arc::new(self)))),
+                command: None,
+            }],
+            data: Arc::new(Default::default()),
+            priority: 0,
+        }
+    }
+
+    // 每个事件使用新的实例, 插件之间以及事件之间只能通过KV共享数据
+    fn run(&self, event: Vec<u8>) -> Result<(bool, Vec<String>, bool)> {
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                event,
+                replies: vec![],
+                kv: self.kv.clone(),
+                kv_dirty: false,
+                limits: self.limits.clone(),
+                store_limits: StoreLimitsBuilder::new()
+                    .memory_size(self.limits.memory)
+                    .instances(1)
+                    .build(),
+            },
+        );
+        store.limiter(|state| &mut state.store_limits);
+        store.add_fuel(self.limits.fuel)?;
+        let instance = host_linker(&self.engine)?.instantiate(&mut store, &self.module)?;
+        let on_message = instance.get_typed_func::<(), i32>(&mut store, "on_message")?;
+        let intercept = on_message.call(&mut store, ())? == 1;
+        let state = store.into_data();
+        Ok((intercept, state.replies, state.kv_dirty))
+    }
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> Result<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => Err(anyhow!("插件没有导出memory")),
+    }
+}
+
+fn read_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    if ptr < 0 || len < 0 {
+        return Err(anyhow!("无效的指针"));
+    }
+    let memory = memory(caller)?;
+    let mut buf = vec![0; len as usize];
+    memory.read(&*caller, ptr as usize, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String> {
+    Ok(String::from_utf8(read_bytes(caller, ptr, len)?)?)
+}
+
+fn write_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, data: &[u8]) -> Result<()> {
+    if ptr < 0 {
+        return Err(anyhow!("无效的指针"));
+    }
+    let memory = memory(caller)?;
+    memory.write(&mut *caller, ptr as usize, data)?;
+    Ok(())
+}
+
+fn host_linker(engine: &Engine) -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        "proc_qq",
+        "event_len",
+        |caller: Caller<'_, HostState>| -> i32 { caller.data().event.len() as i32 },
+    )?;
+    linker.func_wrap(
+        "proc_qq",
+        "event_read",
+        |mut caller: Caller<'_, HostState>, ptr: i32| -> Result<()> {
+            let event = caller.data().event.clone();
+            write_bytes(&mut caller, ptr, &event)
+        },
+    )?;
+    linker.func_wrap(
+        "proc_qq",
+        "reply",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<()> {
+            let limits = caller.data().limits.clone();
+            if caller.data().replies.len() >= limits.replies {
+                return Err(anyhow!("回复的消息过多"));
+            }
+            if len as usize > limits.reply_len {
+                return Err(anyhow!("回复的消息过长"));
+            }
+            let text = read_string(&mut caller, ptr, len)?;
+            caller.data_mut().replies.push(text);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "proc_qq",
+        "kv_get",
+        |mut caller: Caller<'_, HostState>,
+         key_ptr: i32,
+         key_len: i32,
+         buf_ptr: i32,
+         buf_len: i32|
+         -> Result<i32> {
+            let key = read_string(&mut caller, key_ptr, key_len)?;
+            let value = caller.data().kv.lock().unwrap().get(&key).cloned();
+            match value {
+                Some(value) => {
+                    let bytes = value.as_bytes();
+                    let len = bytes.len().min(buf_len.max(0) as usize);
+                    write_bytes(&mut caller, buf_ptr, &bytes[..len])?;
+                    Ok(bytes.len() as i32)
+                }
+                None => Ok(-1),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "proc_qq",
+        "kv_set",
+        |mut caller: Caller<'_, HostState>,
+         key_ptr: i32,
+         key_len: i32,
+         value_ptr: i32,
+         value_len: i32|
+         -> Result<()> {
+            let limits = caller.data().limits.clone();
+            if value_len as usize > limits.kv_value_len {
+                return Err(anyhow!("KV的值过长"));
+            }
+            let key = read_string(&mut caller, key_ptr, key_len)?;
+            let value = read_string(&mut caller, value_ptr, value_len)?;
+            {
+                let mut kv = caller.data().kv.lock().unwrap();
+                if !kv.contains_key(&key) && kv.len() >= limits.kv_keys {
+                    return Err(anyhow!("KV的键过多"));
+                }
+                kv.insert(key, value);
+            }
+            caller.data_mut().kv_dirty = true;
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "proc_qq",
+        "kv_delete",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32| -> Result<()> {
+            let key = read_string(&mut caller, key_ptr, key_len)?;
+            if caller.data().kv.lock().unwrap().remove(&key).is_some() {
+                caller.data_mut().kv_dirty = true;
+            }
+            Ok(())
+        },
+    )?;
+    Ok(linker)
+}
+
+struct WasmHandler(Arc<WasmPlugin>);
+
+#[async_trait]
+impl MessageEventProcess for WasmHandler {
+    async fn handle(&self, event: &MessageEvent) -> Result<bool> {
+        let group_code = match event {
+            MessageEvent::GroupMessage(message) => Some(message.inner.group_code),
+            MessageEvent::GroupTempMessage(message) => Some(message.inner.group_code),
+            MessageEvent::FriendMessage(_) => None,
+        };
+        let data = serde_json::to_vec(&WasmEvent {
+            group_code,
+            from_uin: event.from_uin(),
+            text: event.plain_text(),
+        })?;
+        let plugin = self.0.clone();
+        // wasm的执行是同步的, 通过fuel限制执行时间
+        let (intercept, replies, kv_dirty) =
+            tokio::task::spawn_blocking(move || plugin.run(data)).await??;
+        if kv_dirty {
+            if let Some(path) = &self.0.kv_file {
+                let kv = self.0.kv.lock().unwrap().clone();
+                save_json(path, &kv).await?;
+            }
+        }
+        for reply in replies {
+            event
+                .send_message_to_source(MessageChain::new(reply.parse_text()))
+                .await?;
+        }
+        Ok(intercept)
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{
+    record_audit, AuditAction, AuditEntry, Client, ClientTrait, ExtensionsTrait, MessageCache,
+    MessageTarget, Role, TextEleParseTrait,
+};
+use anyhow::Result;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::task::JoinHandle;
+
+static INDEX_HTML: &str = include_str!("web_admin.html");
+
+/// 内置的管理界面
+///
+/// 提供连接状态、最近的消息(需要开启`message_cache`)、模块开关、角色管理和发送消息的功能.
+/// 所有接口都需要token, 请不要暴露在公网上.
+/// 通过管理界面修改的配置会以`web_admin`记录到审计日志.
+///
+/// ```ignore
+/// let client = Arc::new(ClientBuilder::new()....build().await?);
+/// WebAdmin::new(([127, 0, 0, 1], 8080), "my-token").start(client.clone())?;
+/// run_client(client).await?;
+/// ```
+pub struct WebAdmin {
+    addr: SocketAddr,
+    token: String,
+}
+
+impl WebAdmin {
+    pub fn new(addr: impl Into<SocketAddr>, token: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            token: token.into(),
+        }
+    }
+
+    /// 在后台启动HTTP服务
+    pub fn start(self, client: Arc<Client>) -> Result<JoinHandle<()>> {
+        let state = Arc::new(State {
+            token: self.token,
+            client,
+        });
+        let make_service = make_service_fn(move |_| {
+            let state = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(state.handle(request).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&self.addr)?.serve(make_service);
+        tracing::info!("管理界面 : http://{}", self.addr);
+        Ok(tokio::spawn(async move {
+            if let Err(err) = server.await {
+                tracing::error!("管理界面出现错误 : {:?}", err);
+            }
+        }))
+    }
+}
+
+struct State {
+    token: String,
+    client: Arc<Client>,
+}
+
+#[derive(Serialize)]
+struct StatusResponse {
+    uin: i64,
+    online: bool,
+    status: u8,
+    modules: Vec<ModuleStatus>,
+}
+
+#[derive(Serialize)]
+struct ModuleStatus {
+    id: String,
+    name: String,
+    enabled: bool,
+}
+
+#[derive(Serialize)]
+struct EventItem {
+    time: i32,
+    group_code: Option<i64>,
+    from_uin: i64,
+    sender_name: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ToggleRequest {
+    id: String,
+    enabled: bool,
+}
+
+#[derive(Serialize)]
+struct RoleItem {
+    uin: i64,
+    /// None为全局角色
+    group_code: Option<i64>,
+    role: Role,
+}
+
+#[derive(Deserialize)]
+struct SetRoleRequest {
+    uin: i64,
+    #[serde(default)]
+    group_code: Option<i64>,
+    role: Role,
+}
+
+#[derive(Deserialize)]
+struct RemoveRoleRequest {
+    uin: i64,
+    #[serde(default)]
+    group_code: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct SendRequest {
+    group_code: Option<i64>,
+    uin: Option<i64>,
+    text: String,
+}
+
+fn json<T: serde::Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(body) => Response::builder()
+            .header("content-type", "application/json; charset=utf-8")
+            .body(Body::from(body))
+            .unwrap(),
+        Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "text/plain; charset=utf-8")
+        .body(Body::from(message.to_owned()))
+        .unwrap()
+}
+
+fn query(request: &Request<Body>) -> HashMap<String, String> {
+    let query = request.uri().query().unwrap_or_default();
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(request: Request<Body>) -> Result<T> {
+    let body = hyper::body::to_bytes(request.into_body()).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+impl State {
+    fn authorized(&self, request: &Request<Body>) -> bool {
+        let header = request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match header {
+            Some(header) => header.as_bytes().ct_eq(self.token.as_bytes()).into(),
+            None => false,
+        }
+    }
+
+    /// 记录到审计日志, 操作者为`web_admin`
+    async fn audit(&self, entry: AuditEntry) {
+        record_audit(&self.client.rq_client, entry.operator("web_admin")).await;
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let path = request.uri().path().to_owned();
+        if request.method() == Method::GET && (path == "/" || path == "/index.html") {
+            return Response::builder()
+                .header("content-type", "text/html; charset=utf-8")
+                .body(Body::from(INDEX_HTML))
+                .unwrap();
+        }
+        if !self.authorized(&request) {
+            return error(StatusCode::UNAUTHORIZED, "token错误");
+        }
+        let result = match (request.method().clone(), path.as_str()) {
+            (Method::GET, "/api/status") => Ok(self.status().await),
+            (Method::GET, "/api/events") => Ok(self.events(&request)),
+            (Method::POST, "/api/modules") => self.toggle(request).await,
+            (Method::GET, "/api/roles") => Ok(self.roles().await),
+            (Method::POST, "/api/roles") => self.set_role(request).await,
+            (Method::DELETE, "/api/roles") => self.remove_role(request).await,
+            (Method::POST, "/api/send") => self.send(request).await,
+            _ => return error(StatusCode::NOT_FOUND, "not found"),
+        };
+        result.unwrap_or_else(|err| error(StatusCode::BAD_REQUEST, &format!("{:?}", err)))
+    }
+
+    async fn status(&self) -> Response<Body> {
+        let toggles = self.client.module_toggles();
+        let status = self.client.rq_client.get_status();
+        json(&StatusResponse {
+            uin: self.client.rq_client.uin().await,
+            // NetworkStatus::Running
+            online: status == 1,
+            status,
+            modules: self
+                .client
+                .modules()
+                .into_iter()
+                .map(|(id, name)| ModuleStatus {
+                    enabled: toggles.is_enabled(&id),
+                    id,
+                    name,
+                })
+                .collect(),
+        })
+    }
+
+    fn events(&self, request: &Request<Body>) -> Response<Body> {
+        let limit = query(request)
+            .get("limit")
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(50usize)
+            .min(500);
+        let events: Vec<EventItem> = match self.client.rq_client.extensions().get::<MessageCache>()
+        {
+            Some(cache) => cache
+                .recent(None, limit)
+                .into_iter()
+                .map(|message| EventItem {
+                    time: message.time,
+                    group_code: message.group_code,
+                    from_uin: message.from_uin,
+                    sender_name: message.sender_name,
+                    content: message.elements.to_string(),
+                })
+                .collect(),
+            None => vec![],
+        };
+        json(&events)
+    }
+
+    async fn toggle(&self, request: Request<Body>) -> Result<Response<Body>> {
+        let toggle: ToggleRequest = read_json(request).await?;
+        self.client
+            .module_toggles()
+            .set_enabled(&toggle.id, toggle.enabled)
+            .await?;
+        self.audit(AuditEntry::new(AuditAction::ConfigChange).reason(format!(
+            "{}模块 {}",
+            if toggle.enabled { "启用" } else { "禁用" },
+            toggle.id
+        )))
+        .await;
+        Ok(json(&"OK"))
+    }
+
+    async fn roles(&self) -> Response<Body> {
+        let data = self.client.permissions.snapshot().await;
+        let mut roles: Vec<RoleItem> = data
+            .users
+            .into_iter()
+            .map(|(uin, role)| RoleItem {
+                uin,
+                group_code: None,
+                role,
+            })
+            .chain(data.groups.into_iter().flat_map(|(group_code, users)| {
+                users.into_iter().map(move |(uin, role)| RoleItem {
+                    uin,
+                    group_code: Some(group_code),
+                    role,
+                })
+            }))
+            .collect();
+        roles.sort_by_key(|item| (item.group_code, item.uin));
+        json(&roles)
+    }
+
+    async fn set_role(&self, request: Request<Body>) -> Result<Response<Body>> {
+        let set: SetRoleRequest = read_json(request).await?;
+        self.client
+            .permissions
+            .set_role(set.group_code, set.uin, set.role)
+            .await?;
+        self.audit(
+            AuditEntry::new(AuditAction::ConfigChange)
+                .group_code(set.group_code)
+                .target(set.uin)
+                .reason(format!("设置角色 {}", set.role)),
+        )
+        .await;
+        Ok(json(&"OK"))
+    }
+
+    async fn remove_role(&self, request: Request<Body>) -> Result<Response<Body>> {
+        let remove: RemoveRoleRequest = read_json(request).await?;
+        self.client
+            .permissions
+            .remove_role(remove.group_code, remove.uin)
+            .await?;
+        self.audit(
+            AuditEntry::new(AuditAction::ConfigChange)
+                .group_code(remove.group_code)
+                .target(remove.uin)
+                .reason("移除角色"),
+        )
+        .await;
+        Ok(json(&"OK"))
+    }
+
+    async fn send(&self, request: Request<Body>) -> Result<Response<Body>> {
+        let send: SendRequest = read_json(request).await?;
+        let target = match (send.group_code, send.uin) {
+            (Some(group_code), _) => MessageTarget::Group(group_code, 0),
+            (None, Some(uin)) => MessageTarget::Private(uin),
+            (None, None) => return Ok(error(StatusCode::BAD_REQUEST, "需要群号或QQ号")),
+        };
+        self.client
+            .rq_client
+            .send_message_to_target(&target, MessageChain::new(send.text.parse_text()))
+            .await?;
+        Ok(json(&"OK"))
+    }
+}
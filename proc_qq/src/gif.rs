@@ -0,0 +1,57 @@
+/// 是否为GIF图片 (检查文件头)
+pub fn is_gif(data: &[u8]) -> bool {
+    data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")
+}
+
+/// GIF的帧数, 不是GIF或者文件损坏时返回None
+///
+/// 只扫描数据块, 不解码图像, 可以在异步代码中直接调用
+pub fn gif_frame_count(data: &[u8]) -> Option<usize> {
+    if !is_gif(data) || data.len() < 13 {
+        return None;
+    }
+    // 逻辑屏幕描述符之后是可选的全局颜色表
+    let mut pos = 13 + color_table_size(data[10]);
+    let mut frames = 0;
+    loop {
+        match *data.get(pos)? {
+            // 图像描述符
+            0x2C => {
+                let packed = *data.get(pos + 9)?;
+                // 10字节描述符 + 局部颜色表 + 1字节LZW最小码长
+                pos = skip_sub_blocks(data, pos + 10 + color_table_size(packed) + 1)?;
+                frames += 1;
+            }
+            // 扩展块
+            0x21 => pos = skip_sub_blocks(data, pos + 2)?,
+            // 结束符
+            0x3B => return Some(frames),
+            _ => return None,
+        }
+    }
+}
+
+/// 是否为多帧的GIF动图
+pub fn is_animated_gif(data: &[u8]) -> bool {
+    gif_frame_count(data).map_or(false, |frames| frames > 1)
+}
+
+fn color_table_size(packed: u8) -> usize {
+    if packed & 0x80 != 0 {
+        3 << ((packed & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+// 跳过数据子块, 返回块结束符之后的位置
+fn skip_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let size = *data.get(pos)? as usize;
+        pos += 1;
+        if size == 0 {
+            return Some(pos);
+        }
+        pos += size;
+    }
+}
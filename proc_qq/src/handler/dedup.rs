@@ -0,0 +1,54 @@
+use super::MessageEvent;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+// (来源, seq, rand) 来源为群号或QQ号, 私聊和群使用不同的符号区分
+type MessageKey = (i64, i32, i32);
+
+/// 重连后服务器可能重新推送最近的消息, 记录最近处理过的消息, 跳过重复的消息
+pub(crate) struct EventDedup {
+    capacity: usize,
+    seen: Mutex<(HashSet<MessageKey>, VecDeque<MessageKey>)>,
+}
+
+impl EventDedup {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: Mutex::new((HashSet::new(), VecDeque::new())),
+        }
+    }
+
+    fn key(event: &MessageEvent) -> Option<MessageKey> {
+        let (source, seqs, rands) = match event {
+            MessageEvent::GroupMessage(e) => (e.inner.group_code, &e.inner.seqs, &e.inner.rands),
+            MessageEvent::FriendMessage(e) => (-e.inner.from_uin, &e.inner.seqs, &e.inner.rands),
+            MessageEvent::GroupTempMessage(e) => (-e.inner.from_uin, &e.inner.seqs, &e.inner.rands),
+        };
+        Some((
+            source,
+            *seqs.first()?,
+            rands.first().copied().unwrap_or_default(),
+        ))
+    }
+
+    /// 返回true时这条消息已经处理过
+    pub(crate) fn is_duplicate(&self, event: &MessageEvent) -> bool {
+        let key = match Self::key(event) {
+            Some(key) => key,
+            None => return false,
+        };
+        let mut seen = self.seen.lock().unwrap();
+        let (set, order) = &mut *seen;
+        if !set.insert(key) {
+            return true;
+        }
+        order.push_back(key);
+        if order.len() > self.capacity {
+            if let Some(old) = order.pop_front() {
+                set.remove(&old);
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{HandleResult, MessageEvent};
+use async_trait::async_trait;
+use futures::FutureExt;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+
+/// 处理器返回错误或panic时调用, 使用`ClientBuilder::error_handler`添加
+///
+/// ```ignore
+/// struct ReplyOnError;
+///
+/// #[async_trait]
+/// impl ErrorHandler for ReplyOnError {
+///     async fn handle(&self, error: &HandlerError<'_>) {
+///         if let Some(event) = error.message_event() {
+///             let _ = event.send_message_to_source("指令执行失败, 请稍后再试".parse_message_chain()).await;
+///         }
+///     }
+/// }
+///
+/// ClientBuilder::new().error_handler(ReplyOnError)
+/// ```
+#[async_trait]
+pub trait ErrorHandler: Send + Sync {
+    async fn handle(&self, error: &HandlerError<'_>);
+}
+
+/// 处理器的错误以及触发错误的事件
+pub struct HandlerError<'a> {
+    pub module_id: &'a str,
+    pub handle_name: &'a str,
+    /// 触发错误的事件, 使用`event::<T>()`取得具体的事件
+    pub event: &'a (dyn Any + Send + Sync),
+    pub error: &'a anyhow::Error,
+}
+
+impl HandlerError<'_> {
+    pub fn event<T: 'static>(&self) -> Option<&T> {
+        self.event.downcast_ref()
+    }
+
+    /// 触发错误的事件为消息时取得MessageEvent, 可以用来回复
+    pub fn message_event(&self) -> Option<MessageEvent> {
+        if let Some(event) = self.event::<MessageEvent>() {
+            return Some(event.clone());
+        }
+        if let Some(event) = self.event::<GroupMessageEvent>() {
+            return Some(MessageEvent::GroupMessage(event.clone()));
+        }
+        if let Some(event) = self.event::<FriendMessageEvent>() {
+            return Some(MessageEvent::FriendMessage(event.clone()));
+        }
+        self.event::<GroupTempMessageEvent>()
+            .map(|event| MessageEvent::GroupTempMessage(event.clone()))
+    }
+
+    /// 错误是否由处理器panic产生
+    pub fn is_panic(&self) -> bool {
+        self.error.is::<HandlerPanic>()
+    }
+}
+
+/// 处理器panic时产生的错误
+#[derive(Debug)]
+pub struct HandlerPanic(pub String);
+
+impl std::fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "处理器panic : {}", self.0)
+    }
+}
+
+impl std::error::Error for HandlerPanic {}
+
+/// 执行处理器, 将panic转换为`HandlerPanic`错误
+pub(crate) async fn catch_handler_panic<F>(future: F) -> anyhow::Result<HandleResult>
+where
+    F: Future<Output = anyhow::Result<HandleResult>>,
+{
+    match AssertUnwindSafe(future).catch_unwind().await {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "未知的错误".to_owned()
+            };
+            Err(HandlerPanic(message).into())
+        }
+    }
+}
@@ -12,6 +12,7 @@ pub struct LoginEvent {
     pub uin: i64,
 }
 
+#[derive(Clone)]
 pub enum MessageEvent {
     GroupMessage(GroupMessageEvent),
     FriendMessage(FriendMessageEvent),
@@ -80,4 +81,15 @@ impl MessageEvent {
 
 pub struct ConnectedAndOnlineEvent {}
 
+/// 登录完成后的账号信息, 每次登录(包括断线重连后)都会发送一次
+pub struct AccountReadyEvent {
+    pub client: Arc<ricq::Client>,
+    pub uin: i64,
+    pub nickname: String,
+    pub friend_count: usize,
+    pub group_count: usize,
+    /// 是否使用token恢复了会话
+    pub resumed_session: bool,
+}
+
 pub struct DisconnectedAndOfflineEvent {}
@@ -0,0 +1,57 @@
+use crate::ClientHandler;
+use async_trait::async_trait;
+use ricq::handler::QEvent;
+use std::sync::Arc;
+
+/// 中间件, 在模块之前处理所有事件, 可以修改事件或不再向后传递
+///
+/// 调用`next.run(event)`将事件交给下一个中间件, 最后一个中间件之后为模块.
+/// 不调用`next.run`时事件不会被模块处理.
+///
+/// ```ignore
+/// struct Logger;
+///
+/// #[async_trait]
+/// impl Middleware for Logger {
+///     async fn handle(&self, event: &mut QEvent, next: Next<'_>) -> anyhow::Result<()> {
+///         let start = std::time::Instant::now();
+///         next.run(event).await?;
+///         tracing::info!("处理事件用时 {:?}", start.elapsed());
+///         Ok(())
+///     }
+/// }
+///
+/// ClientBuilder::new().middleware(Logger)
+/// ```
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    async fn handle(&self, event: &mut QEvent, next: Next<'_>) -> anyhow::Result<()>;
+}
+
+/// 调用链中剩余的中间件和模块
+pub struct Next<'a> {
+    pub(crate) middlewares: &'a [Arc<dyn Middleware>],
+    pub(crate) handler: &'a ClientHandler,
+}
+
+impl Next<'_> {
+    pub async fn run(self, event: &mut QEvent) -> anyhow::Result<()> {
+        match self.middlewares.split_first() {
+            Some((middleware, rest)) => {
+                middleware
+                    .handle(
+                        event,
+                        Next {
+                            middlewares: rest,
+                            handler: self.handler,
+                        },
+                    )
+                    .await
+            }
+            None => {
+                self.handler.dispatch(event.clone()).await;
+                Ok(())
+            }
+        }
+    }
+}
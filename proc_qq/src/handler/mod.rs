@@ -1,24 +1,175 @@
+use crate::{
+    AuditLog, Blocklist, BotMuteTracker, Conversations, Extensions, ExtensionsTrait,
+    MembershipChange, MembershipTracker, MessageCache, MessageSendToSourceTrait, ModuleManager,
+    ModuleToggles, Permissions, Quotas, Role, Stats,
+};
+use anyhow::Context;
 use async_trait::async_trait;
+pub(crate) use dedup::EventDedup;
+pub use error_handler::*;
 #[cfg(feature = "event_args")]
 pub use event_args::*;
 pub use events::*;
+pub use middleware::*;
 pub use processes::*;
 pub use results::*;
 use ricq::handler::{Handler, QEvent};
 use std::sync::Arc;
 
+mod dedup;
+mod error_handler;
 #[cfg(feature = "event_args")]
 mod event_args;
 mod events;
+mod middleware;
 mod processes;
 mod results;
 
 pub(crate) struct ClientHandler {
-    pub(crate) modules: Arc<Vec<Module>>,
+    pub(crate) modules: Arc<Vec<Arc<Module>>>,
     pub(crate) result_handlers: Arc<Vec<EventResultHandler>>,
+    pub(crate) blocklist: Arc<Blocklist>,
+    pub(crate) quotas: Arc<Quotas>,
+    pub(crate) permissions: Arc<Permissions>,
+    pub(crate) audit_log: Arc<AuditLog>,
+    pub(crate) stats: Option<Arc<Stats>>,
+    pub(crate) membership_tracker: Option<Arc<MembershipTracker>>,
+    pub(crate) message_cache: Option<Arc<MessageCache>>,
+    pub(crate) module_toggles: Arc<ModuleToggles>,
+    pub(crate) module_manager: ModuleManager,
+    pub(crate) raw_handlers: Arc<Vec<Arc<dyn Handler + Send + Sync>>>,
+    pub(crate) middlewares: Arc<Vec<Arc<dyn Middleware>>>,
+    pub(crate) error_handlers: Arc<Vec<Arc<dyn ErrorHandler>>>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Option<Arc<crate::features::Metrics>>,
+    pub(crate) dedup: EventDedup,
+    pub(crate) help_command: bool,
+    pub(crate) ping_command: bool,
 }
 
-impl ClientHandler {}
+impl ClientHandler {
+    /// 黑白名单及封禁过滤, 返回true时事件不再传递给模块
+    async fn blocked(&self, group_code: Option<i64>, uin: i64) -> bool {
+        if self.permissions.is_master(group_code, uin).await {
+            return false;
+        }
+        self.permissions.role_of(group_code, uin).await == Role::Banned
+            || self.blocklist.is_blocked(group_code, uin).await
+    }
+
+    /// 消息事件的过滤, 黑白名单、权限和审计日志的管理指令也会在这里被拦截
+    async fn intercept_message(&self, event: &MessageEvent) -> bool {
+        match self.permissions.handle_command(event).await {
+            Ok(true) => return true,
+            Ok(false) => (),
+            Err(err) => {
+                tracing::error!(" 出现错误 : {:?}", err);
+                return true;
+            }
+        }
+        match self
+            .audit_log
+            .handle_command(event, &self.permissions)
+            .await
+        {
+            Ok(true) => return true,
+            Ok(false) => (),
+            Err(err) => {
+                tracing::error!(" 出现错误 : {:?}", err);
+                return true;
+            }
+        }
+        match self
+            .module_manager
+            .handle_command(event, &self.permissions)
+            .await
+        {
+            Ok(true) => return true,
+            Ok(false) => (),
+            Err(err) => {
+                tracing::error!(" 出现错误 : {:?}", err);
+                return true;
+            }
+        }
+        let uin = event.from_uin();
+        let group_code = match event {
+            MessageEvent::GroupMessage(e) => Some(e.inner.group_code),
+            MessageEvent::GroupTempMessage(e) => Some(e.inner.group_code),
+            MessageEvent::FriendMessage(_) => None,
+        };
+        if self.permissions.is_master(group_code, uin).await {
+            match self.quotas.handle_command(event).await {
+                Ok(true) => return true,
+                Ok(false) => (),
+                Err(err) => {
+                    tracing::error!(" 出现错误 : {:?}", err);
+                    return true;
+                }
+            }
+            return match self.blocklist.handle_command(event).await {
+                Ok(b) => b,
+                Err(err) => {
+                    tracing::error!(" 出现错误 : {:?}", err);
+                    true
+                }
+            };
+        }
+        self.blocked(group_code, uin).await
+    }
+}
+
+impl ClientHandler {
+    /// 记录通过过滤的消息以及处理它的处理器
+    fn record_stats(&self, event: &MessageEvent, result: &MapResult) {
+        if let Some(stats) = &self.stats {
+            let group_code = match event {
+                MessageEvent::GroupMessage(e) => Some(e.inner.group_code),
+                _ => None,
+            };
+            stats.record_message(group_code, event.from_uin());
+            if let MapResult::Process(module_id, handle_name) = result {
+                stats.record_command(&format!("{}.{}", module_id, handle_name));
+            }
+        }
+    }
+}
+
+impl ClientHandler {
+    /// 交给等待回复的会话, 见`ConversationTrait`
+    fn offer_to_conversation(&self, event: &MessageEvent) -> bool {
+        match event.client().extensions().get::<Conversations>() {
+            Some(conversations) => conversations.offer(event),
+            None => false,
+        }
+    }
+
+    /// 没有模块处理的消息交给内置的`/ping`和`/help`
+    async fn builtin_commands(&self, event: &MessageEvent) {
+        if self.ping_command {
+            match crate::health::handle_ping_command(event).await {
+                Ok(true) => return,
+                Ok(false) => (),
+                Err(err) => {
+                    tracing::error!(" 出现错误 : {:?}", err);
+                    return;
+                }
+            }
+        }
+        if !self.help_command {
+            return;
+        }
+        if let Err(err) = crate::help::handle_help_command(
+            event,
+            &self.modules,
+            &self.module_toggles,
+            &self.permissions,
+        )
+        .await
+        {
+            tracing::error!(" 出现错误 : {:?}", err);
+        }
+    }
+}
 
 enum MapResult<'a> {
     None,
@@ -59,16 +210,41 @@ macro_rules! map_result {
 }
 
 macro_rules! map_handlers {
-    ($self:expr $(,$event:expr, $process:path, $result_handler:path)* $(,)?) => {{
+    // group为消息所在的群, 用于判断功能是否在群中被关闭
+    ($self:expr, group = $group:expr $(,$event:expr, $process:path, $result_handler:path)* $(,)?) => {{
+        let group_code: Option<i64> = $group;
         let mut result = MapResult::None;
+        let mut stop = false;
         for m in $self.modules.as_ref() {
+            if !$self.module_toggles.is_enabled_in(group_code, &m.id) {
+                continue;
+            }
             for h in &m.handles {
+                if !$self
+                    .module_toggles
+                    .is_handle_enabled_in(group_code, &m.id, &h.name)
+                {
+                    continue;
+                }
                 match &h.process {
                     $(
-                    $process(e) => match e.handle($event).await {
-                        Ok(b) => {
-                            if b {
-                                result = MapResult::Process(&m.id, &h.name);
+                    $process(e) => match {
+                        #[cfg(feature = "metrics")]
+                        let start = std::time::Instant::now();
+                        let handled = catch_handler_panic(MODULE_DATA
+                            .scope(m.data.clone(), MODULE_ID.scope(m.id.clone(), e.handle_result($event))))
+                            .await;
+                        #[cfg(feature = "metrics")]
+                        if let Some(metrics) = &$self.metrics {
+                            metrics.observe_handler(&m.id, &h.name, start.elapsed());
+                        }
+                        handled
+                    } {
+                        Ok(handle_result) => {
+                            if handle_result.is_handled() {
+                                if let MapResult::None = result {
+                                    result = MapResult::Process(&m.id, &h.name);
+                                }
                                 let event_result = EventResult::Process(
                                     ModuleInfo{
                                         module_id: m.id.clone(),
@@ -78,10 +254,22 @@ macro_rules! map_handlers {
                                 );
                                 map_result!($self, $event, $result_handler, &event_result);
                             }
+                            stop = handle_result == HandleResult::Stop;
                         }
                         Err(err) => {
                             tracing::error!(" 出现错误 : {:?}", err);
+                            for error_handler in $self.error_handlers.iter() {
+                                error_handler
+                                    .handle(&HandlerError {
+                                        module_id: &m.id,
+                                        handle_name: &h.name,
+                                        event: $event,
+                                        error: &err,
+                                    })
+                                    .await;
+                            }
                             result = MapResult::Exception(&m.id, &h.name);
+                            stop = true;
                             let event_result = EventResult::Exception(
                                 ModuleInfo{
                                     module_id: m.id.clone(),
@@ -96,23 +284,53 @@ macro_rules! map_handlers {
                     )*
                     _ => (),
                 }
-                if let MapResult::None = result {
-                } else {
+                if stop {
                     break;
                 }
             }
-            if let MapResult::None = result {
-            } else {
+            if stop {
                 break;
             }
         }
         result
     }};
+    ($self:expr $(,$event:expr, $process:path, $result_handler:path)* $(,)?) => {
+        map_handlers!($self, group = None $(,$event, $process, $result_handler)*)
+    };
 }
 
 #[async_trait]
 impl Handler for ClientHandler {
-    async fn handle(&self, e: QEvent) {
+    async fn handle(&self, mut e: QEvent) {
+        // 原始的ricq事件先交给用户设置的handler, 不受过滤和拦截的影响
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_event(&e);
+        }
+        for raw_handler in self.raw_handlers.iter() {
+            raw_handler.handle(e.clone()).await;
+        }
+        #[cfg(feature = "onebot")]
+        crate::features::onebot::push_onebot_event(&e).await;
+        #[cfg(feature = "event_recorder")]
+        crate::features::event_recorder::record_incoming_event(&e).await;
+        if self.middlewares.is_empty() {
+            self.dispatch(e).await;
+            return;
+        }
+        let next = Next {
+            middlewares: self.middlewares.as_ref(),
+            handler: self,
+        };
+        if let Err(err) = next.run(&mut e).await {
+            tracing::error!(" 出现错误 : {:?}", err);
+        }
+    }
+}
+
+impl ClientHandler {
+    /// 经过中间件后, 将事件分发给模块
+    pub(crate) async fn dispatch(&self, e: QEvent) {
         match e {
             QEvent::Login(event) => {
                 tracing::debug!("LOGIN : (UIN={})", event,);
@@ -131,8 +349,22 @@ impl Handler for ClientHandler {
                     event.inner.elements.to_string()
                 );
                 let me = MessageEvent::GroupMessage(event.clone());
-                let _ = map_handlers!(
+                if self.dedup.is_duplicate(&me) {
+                    tracing::debug!("重复推送的消息, 已忽略");
+                    return;
+                }
+                if let Some(cache) = &self.message_cache {
+                    cache.push_event(&me);
+                }
+                if self.intercept_message(&me).await {
+                    return;
+                }
+                if self.offer_to_conversation(&me) {
+                    return;
+                }
+                let result = map_handlers!(
                     &self,
+                    group = Some(event.inner.group_code),
                     &event,
                     ModuleEventProcess::GroupMessage,
                     ResultProcess::GroupMessage,
@@ -140,6 +372,10 @@ impl Handler for ClientHandler {
                     ModuleEventProcess::Message,
                     ResultProcess::Message,
                 );
+                self.record_stats(&me, &result);
+                if let MapResult::None = result {
+                    self.builtin_commands(&me).await;
+                }
             }
             QEvent::FriendMessage(event) => {
                 tracing::debug!(
@@ -148,7 +384,20 @@ impl Handler for ClientHandler {
                     event.inner.elements.to_string()
                 );
                 let me = MessageEvent::FriendMessage(event.clone());
-                let _ = map_handlers!(
+                if self.dedup.is_duplicate(&me) {
+                    tracing::debug!("重复推送的消息, 已忽略");
+                    return;
+                }
+                if let Some(cache) = &self.message_cache {
+                    cache.push_event(&me);
+                }
+                if self.intercept_message(&me).await {
+                    return;
+                }
+                if self.offer_to_conversation(&me) {
+                    return;
+                }
+                let result = map_handlers!(
                     &self,
                     &event,
                     ModuleEventProcess::FriendMessage,
@@ -157,6 +406,10 @@ impl Handler for ClientHandler {
                     ModuleEventProcess::Message,
                     ResultProcess::Message,
                 );
+                self.record_stats(&me, &result);
+                if let MapResult::None = result {
+                    self.builtin_commands(&me).await;
+                }
             }
             QEvent::GroupTempMessage(event) => {
                 tracing::debug!(
@@ -165,7 +418,20 @@ impl Handler for ClientHandler {
                     event.inner.elements.to_string()
                 );
                 let me = MessageEvent::GroupTempMessage(event.clone());
-                let _ = map_handlers!(
+                if self.dedup.is_duplicate(&me) {
+                    tracing::debug!("重复推送的消息, 已忽略");
+                    return;
+                }
+                if let Some(cache) = &self.message_cache {
+                    cache.push_event(&me);
+                }
+                if self.intercept_message(&me).await {
+                    return;
+                }
+                if self.offer_to_conversation(&me) {
+                    return;
+                }
+                let result = map_handlers!(
                     &self,
                     &event,
                     ModuleEventProcess::GroupTempMessage,
@@ -174,8 +440,18 @@ impl Handler for ClientHandler {
                     ModuleEventProcess::Message,
                     ResultProcess::Message,
                 );
+                self.record_stats(&me, &result);
+                if let MapResult::None = result {
+                    self.builtin_commands(&me).await;
+                }
             }
             QEvent::GroupRequest(event) => {
+                if self
+                    .blocked(Some(event.inner.group_code), event.inner.req_uin)
+                    .await
+                {
+                    return;
+                }
                 tracing::debug!(
                     "REQUEST (GROUP={}, UIN={}): {}",
                     event.inner.group_code,
@@ -190,6 +466,9 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::NewFriendRequest(event) => {
+                if self.blocked(None, event.inner.req_uin).await {
+                    return;
+                }
                 tracing::debug!(
                     "REQUEST (UIN={}): {}",
                     event.inner.req_uin,
@@ -211,6 +490,9 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::FriendPoke(event) => {
+                if self.blocked(None, event.inner.sender).await {
+                    return;
+                }
                 let _ = map_handlers!(
                     &self,
                     &event,
@@ -227,6 +509,18 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::GroupMute(event) => {
+                if event.inner.target_uin == event.client.uin().await {
+                    let until = if event.inner.duration.is_zero() {
+                        0
+                    } else {
+                        chrono::Local::now().timestamp() + event.inner.duration.as_secs() as i64
+                    };
+                    event
+                        .client
+                        .extensions()
+                        .get_or_insert_with(BotMuteTracker::default)
+                        .set(event.inner.group_code, until);
+                }
                 let _ = map_handlers!(
                     &self,
                     &event,
@@ -235,6 +529,22 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::GroupLeave(event) => {
+                if let Some(tracker) = &self.membership_tracker {
+                    let change = match event.inner.operator_uin {
+                        Some(operator) if operator != event.inner.member_uin => {
+                            MembershipChange::Kick
+                        }
+                        _ => MembershipChange::Leave,
+                    };
+                    tracker
+                        .record_change(
+                            event.inner.group_code,
+                            event.inner.member_uin,
+                            change,
+                            event.inner.operator_uin,
+                        )
+                        .await;
+                }
                 let _ = map_handlers!(
                     &self,
                     &event,
@@ -307,14 +617,24 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::GroupAudioMessage(event) => {
+                if self
+                    .blocked(Some(event.inner.group_code), event.inner.from_uin)
+                    .await
+                {
+                    return;
+                }
                 let _ = map_handlers!(
                     &self,
+                    group = Some(event.inner.group_code),
                     &event,
                     ModuleEventProcess::GroupAudioMessage,
                     ResultProcess::GroupAudioMessage
                 );
             }
             QEvent::FriendAudioMessage(event) => {
+                if self.blocked(None, event.inner.from_uin).await {
+                    return;
+                }
                 let _ = map_handlers!(
                     &self,
                     &event,
@@ -323,6 +643,16 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::NewMember(event) => {
+                if let Some(tracker) = &self.membership_tracker {
+                    tracker
+                        .record_change(
+                            event.inner.group_code,
+                            event.inner.member_uin,
+                            MembershipChange::Join,
+                            None,
+                        )
+                        .await;
+                }
                 let _ = map_handlers!(
                     &self,
                     &event,
@@ -339,6 +669,12 @@ impl Handler for ClientHandler {
                 );
             }
             QEvent::GroupPoke(event) => {
+                if self
+                    .blocked(Some(event.inner.group_code), event.inner.sender)
+                    .await
+                {
+                    return;
+                }
                 let _ = map_handlers!(
                     &self,
                     &event,
@@ -354,11 +690,54 @@ pub struct Module {
     pub id: String,
     pub name: String,
     pub handles: Vec<ModuleEventHandler>,
+    /// 构造模块时注入的依赖, 见`module_data`
+    pub data: Arc<Extensions>,
+    /// 优先级, 越大越先处理事件, 相同时按照传入的顺序. 默认为0
+    pub priority: i32,
+}
+
+tokio::task_local! {
+    static MODULE_DATA: Arc<Extensions>;
+    static MODULE_ID: String;
+}
+
+/// 正在处理事件的模块id, 不在事件处理过程中时为None
+pub(crate) fn current_module_id() -> Option<String> {
+    MODULE_ID.try_with(|id| id.clone()).ok()
+}
+
+/// 取得当前模块构造时注入的依赖, 只能在事件处理过程中调用
+///
+/// ```ignore
+/// pub fn module(pool: Pool) -> Module {
+///     module!("sign_in", "签到", on_message, data = pool)
+/// }
+///
+/// #[event]
+/// async fn on_message(event: &MessageEvent) -> anyhow::Result<bool> {
+///     let pool = module_data::<Pool>()?;
+///     // ...
+/// }
+/// ```
+pub fn module_data<T: Send + Sync + 'static>() -> anyhow::Result<Arc<T>> {
+    MODULE_DATA
+        .try_with(|data| data.get::<T>())
+        .with_context(|| "只能在事件处理过程中取得模块数据")?
+        .with_context(|| {
+            format!(
+                "模块中没有注入该类型的数据 : {}",
+                std::any::type_name::<T>()
+            )
+        })
 }
 
 pub(crate) struct EventSender {
-    pub(crate) modules: Arc<Vec<Module>>,
+    pub(crate) modules: Arc<Vec<Arc<Module>>>,
     pub(crate) result_handlers: Arc<Vec<EventResultHandler>>,
+    pub(crate) module_toggles: Arc<ModuleToggles>,
+    pub(crate) error_handlers: Arc<Vec<Arc<dyn ErrorHandler>>>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Option<Arc<crate::features::Metrics>>,
 }
 
 impl EventSender {
@@ -373,6 +752,17 @@ impl EventSender {
             _ => Ok(()),
         }
     }
+    pub async fn send_account_ready(&self, event: &AccountReadyEvent) -> anyhow::Result<()> {
+        match map_handlers!(
+            &self,
+            event,
+            ModuleEventProcess::AccountReady,
+            ResultProcess::AccountReady,
+        ) {
+            MapResult::Exception(_, _) => Err(anyhow::Error::msg("err")),
+            _ => Ok(()),
+        }
+    }
     pub async fn send_disconnected_and_offline(&self) -> anyhow::Result<()> {
         match map_handlers!(
             &self,
@@ -12,6 +12,19 @@ use ricq::client::event::{
 pub struct ModuleEventHandler {
     pub name: String,
     pub process: ModuleEventProcess,
+    /// 指令的说明, 设置了bot_command的处理器由event宏生成, 用于`/help`
+    pub command: Option<CommandInfo>,
+}
+
+/// 指令的说明
+#[derive(Debug, Clone, Default)]
+pub struct CommandInfo {
+    /// 指令格式, 例如 "/删除 {idx}"
+    pub usage: String,
+    /// 说明, 取自处理器的文档注释
+    pub description: String,
+    /// `#[require(...)]`需要的角色, 角色不足的用户在`/help`中看不到该指令
+    pub require: Option<crate::Role>,
 }
 
 pub enum ModuleEventProcess {
@@ -38,6 +51,7 @@ pub enum ModuleEventProcess {
     LoginEvent(Box<dyn LoginEventProcess>),
     Message(Box<dyn MessageEventProcess>),
     ConnectedAndOnline(Box<dyn ConnectedAndOnlineEventProcess>),
+    AccountReady(Box<dyn AccountReadyEventProcess>),
     DisconnectAndOffline(Box<dyn DisconnectedAndOfflineEventProcess>),
 
     GroupDisband(Box<dyn GroupDisbandEventProcess>),
@@ -51,11 +65,47 @@ pub enum ModuleEventProcess {
     GroupPoke(Box<dyn GroupPokeEventProcess>),
 }
 
+/// 处理器的结果, 决定事件是否继续交给后面的处理器和模块
+///
+/// `#[event]`的方法也可以返回`anyhow::Result<bool>`, true相当于`Stop`, false相当于`Continue`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleResult {
+    /// 没有处理, 继续交给后面的处理器
+    Continue,
+    /// 已处理, 但仍然继续交给后面的处理器 (例如记录日志、防撤回)
+    Handled,
+    /// 已处理, 不再交给后面的处理器和模块
+    Stop,
+}
+
+impl HandleResult {
+    pub fn is_handled(&self) -> bool {
+        !matches!(self, HandleResult::Continue)
+    }
+}
+
+impl From<bool> for HandleResult {
+    fn from(handled: bool) -> Self {
+        if handled {
+            HandleResult::Stop
+        } else {
+            HandleResult::Continue
+        }
+    }
+}
+
 macro_rules! process_trait {
     ($name:ident, $event:path) => {
         #[async_trait]
         pub trait $name: Sync + Send {
+            /// Ok(true)为拦截事件, 不再向后面的处理器和模块传递
             async fn handle(&self, event: &$event) -> anyhow::Result<bool>;
+
+            /// 框架实际调用的方法, 默认将handle的结果转换为HandleResult.
+            /// 需要返回`HandleResult::Handled`时实现这个方法
+            async fn handle_result(&self, event: &$event) -> anyhow::Result<HandleResult> {
+                Ok(self.handle(event).await?.into())
+            }
         }
     };
 }
@@ -85,6 +135,7 @@ process_trait!(LoginEventProcess, LoginEvent);
 process_trait!(MessageEventProcess, MessageEvent);
 
 process_trait!(ConnectedAndOnlineEventProcess, ConnectedAndOnlineEvent);
+process_trait!(AccountReadyEventProcess, AccountReadyEvent);
 process_trait!(
     DisconnectedAndOfflineEventProcess,
     DisconnectedAndOfflineEvent
@@ -58,6 +58,7 @@ pub enum ResultProcess {
     LoginEvent(Box<dyn LoginResultHandler>),
     Message(Box<dyn MessageResultHandler>),
     ConnectedAndOnline(Box<dyn ConnectedAndOnlineResultHandler>),
+    AccountReady(Box<dyn AccountReadyResultHandler>),
     DisconnectAndOffline(Box<dyn DisconnectedAndOfflineResultHandler>),
 
     GroupDisband(Box<dyn GroupDisbandResultHandler>),
@@ -99,6 +100,7 @@ error_trait!(LoginResultHandler, LoginEvent);
 error_trait!(MessageResultHandler, MessageEvent);
 
 error_trait!(ConnectedAndOnlineResultHandler, ConnectedAndOnlineEvent);
+error_trait!(AccountReadyResultHandler, AccountReadyEvent);
 error_trait!(
     DisconnectedAndOfflineResultHandler,
     DisconnectedAndOfflineEvent
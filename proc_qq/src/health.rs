@@ -0,0 +1,137 @@
+use crate::{
+    ExtensionsTrait, MessageContentTrait, MessageEvent, MessageSendToSourceTrait, TextEleParseTrait,
+};
+use anyhow::Result;
+use ricq_core::msg::MessageChain;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// 保留最近发送的条数
+const RECENT_SENDS: usize = 100;
+
+/// 运行状态, 记录最近发送消息的耗时和结果以及重连次数, 使用`client.health()`取得报告
+pub struct Health {
+    started: Instant,
+    reconnects: AtomicU64,
+    // (耗时, 是否成功)
+    sends: Mutex<VecDeque<(Duration, bool)>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            reconnects: AtomicU64::new(0),
+            sends: Mutex::new(VecDeque::with_capacity(RECENT_SENDS)),
+        }
+    }
+}
+
+impl Health {
+    pub(crate) fn record_send(&self, latency: Duration, success: bool) {
+        let mut sends = self.sends.lock().unwrap();
+        if sends.len() >= RECENT_SENDS {
+            sends.pop_front();
+        }
+        sends.push_back((latency, success));
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn report(&self) -> HealthReport {
+        let sends = self.sends.lock().unwrap();
+        let errors = sends.iter().filter(|(_, success)| !success).count();
+        let mut latencies: Vec<Duration> = sends
+            .iter()
+            .filter(|(_, success)| *success)
+            .map(|(latency, _)| *latency)
+            .collect();
+        latencies.sort();
+        let average_latency = if latencies.is_empty() {
+            None
+        } else {
+            Some(latencies.iter().sum::<Duration>() / latencies.len() as u32)
+        };
+        HealthReport {
+            uptime: self.started.elapsed(),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            sends: sends.len(),
+            errors,
+            average_latency,
+            p95_latency: latencies
+                .get((latencies.len() * 95 / 100).min(latencies.len().saturating_sub(1)))
+                .copied(),
+        }
+    }
+}
+
+/// 运行状态报告, 发送相关的数据只统计最近100次发送
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// 构建客户端后经过的时间
+    pub uptime: Duration,
+    /// 断线重连的次数
+    pub reconnects: u64,
+    pub sends: usize,
+    /// 发送失败的次数
+    pub errors: usize,
+    /// 成功发送的平均耗时
+    pub average_latency: Option<Duration>,
+    pub p95_latency: Option<Duration>,
+}
+
+impl HealthReport {
+    /// 发送失败的比例, 没有发送过消息时为0
+    pub fn error_rate(&self) -> f64 {
+        if self.sends == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.sends as f64
+        }
+    }
+}
+
+/// 发送一次心跳, 返回往返耗时
+pub(crate) async fn ping(client: &ricq::Client) -> Result<Duration> {
+    let start = Instant::now();
+    client.do_heartbeat().await?;
+    Ok(start.elapsed())
+}
+
+pub(crate) fn health(client: &ricq::Client) -> std::sync::Arc<Health> {
+    client.extensions().get_or_insert_with(Health::default)
+}
+
+/// 内置的`/ping`, 回复心跳耗时和运行状态. 使用`ClientBuilder::ping_command(true)`开启
+pub(crate) async fn handle_ping_command(event: &MessageEvent) -> Result<bool> {
+    if event.message_content().trim() != "/ping" {
+        return Ok(false);
+    }
+    let client = event.client();
+    let mut lines = vec![match ping(&client).await {
+        Ok(latency) => format!("pong! 心跳耗时 {}ms", latency.as_millis()),
+        Err(err) => format!("心跳失败 : {}", err),
+    }];
+    let report = health(&client).report();
+    lines.push(format!(
+        "运行时间 {}分钟, 重连 {}次",
+        report.uptime.as_secs() / 60,
+        report.reconnects
+    ));
+    if let Some(latency) = report.average_latency {
+        lines.push(format!(
+            "最近{}次发送 平均耗时 {}ms, 失败率 {:.1}%",
+            report.sends,
+            latency.as_millis(),
+            report.error_rate() * 100.0
+        ));
+    }
+    event
+        .send_message_to_source(MessageChain::new(lines.join("\n").parse_text()))
+        .await?;
+    Ok(true)
+}
@@ -0,0 +1,98 @@
+use crate::{
+    CommandInfo, MessageContentTrait, MessageEvent, MessageSendToSourceTrait, Module,
+    ModuleToggles, Permissions, RoleSubjectTrait, TextEleParseTrait,
+};
+use anyhow::Result;
+use ricq_core::msg::MessageChain;
+use std::sync::Arc;
+
+/// 内置的`/help [指令]`, 列出发送者在当前群中可以使用的指令.
+/// 在模块之后处理, 模块自己处理了`/help`时不会回复, 可以使用`ClientBuilder::help_command(false)`关闭
+pub(crate) async fn handle_help_command(
+    event: &MessageEvent,
+    modules: &[Arc<Module>],
+    toggles: &ModuleToggles,
+    permissions: &Permissions,
+) -> Result<bool> {
+    let content = event.message_content();
+    let mut sp = content.split_whitespace();
+    if sp.next() != Some("/help") {
+        return Ok(false);
+    }
+    let query = sp.next();
+    let (role_group, uin) = event.role_subject();
+    let group_code = match event {
+        MessageEvent::GroupMessage(e) => Some(e.inner.group_code),
+        _ => None,
+    };
+    let mut commands: Vec<(&Module, &CommandInfo)> = vec![];
+    for module in modules {
+        if !toggles.is_enabled_in(group_code, &module.id) {
+            continue;
+        }
+        for handle in &module.handles {
+            let command = match &handle.command {
+                Some(command) => command,
+                None => continue,
+            };
+            if !toggles.is_handle_enabled_in(group_code, &module.id, &handle.name) {
+                continue;
+            }
+            if let Some(role) = command.require {
+                if !permissions.has_role(role_group, uin, role).await {
+                    continue;
+                }
+            }
+            commands.push((module.as_ref(), command));
+        }
+    }
+    let reply = match query {
+        None => {
+            if commands.is_empty() {
+                "没有可用的指令".to_owned()
+            } else {
+                let mut lines = vec!["可用的指令 (/help 指令 查看详细说明) :".to_owned()];
+                for (_, command) in &commands {
+                    match command.description.lines().next() {
+                        Some(summary) if !summary.is_empty() => {
+                            lines.push(format!("{} - {}", command.usage, summary))
+                        }
+                        _ => lines.push(command.usage.clone()),
+                    }
+                }
+                lines.join("\n")
+            }
+        }
+        Some(query) => {
+            let query = query.trim_start_matches('/');
+            let found: Vec<String> = commands
+                .iter()
+                .filter(|(_, command)| {
+                    command
+                        .usage
+                        .split_whitespace()
+                        .next()
+                        .map(|name| name.trim_start_matches('/') == query)
+                        .unwrap_or(false)
+                })
+                .map(|(module, command)| {
+                    let mut text = format!("{} ({})", command.usage, module.name);
+                    if !command.description.is_empty() {
+                        text.push('\n');
+                        text.push_str(&command.description);
+                    }
+                    text
+                })
+                .collect();
+            if found.is_empty() {
+                format!("没有找到指令 : {}", query)
+            } else {
+                found.join("\n\n")
+            }
+        }
+    };
+    event
+        .send_message_to_source(MessageChain::new(reply.parse_text()))
+        .await?;
+    Ok(true)
+}
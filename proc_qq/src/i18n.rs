@@ -0,0 +1,240 @@
+use crate::persist::{load_json, save_json};
+use crate::{
+    ExtensionsTrait, MessageEvent, MessageTarget, MessageTargetTrait, MessageTemplate,
+    TemplateValues,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq_core::msg::MessageChain;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// 多语言文本和每个群使用的语言
+///
+/// 文本按`语言 -> key -> 文本`保存, 文本可以使用消息模版的`{变量}`.
+/// 查找文本时依次使用 群设置的语言 -> 默认语言, 都没有时返回key本身.
+///
+/// ```ignore
+/// ClientBuilder::new().i18n(
+///     I18n::file("zh", "group_languages.json")
+///         .text("zh", "welcome", "欢迎 {at_sender}")
+///         .text("en", "welcome", "Welcome {at_sender}"),
+/// )
+/// ```
+pub struct I18n {
+    path: Option<String>,
+    default_language: String,
+    texts: RwLock<HashMap<String, HashMap<String, String>>>,
+    // 群号 -> 语言
+    groups: RwLock<HashMap<i64, String>>,
+}
+
+impl Default for I18n {
+    fn default() -> Self {
+        Self::memory("zh")
+    }
+}
+
+impl I18n {
+    /// 群的语言设置仅保存在内存中
+    pub fn memory(default_language: impl Into<String>) -> Self {
+        Self {
+            path: None,
+            default_language: default_language.into(),
+            texts: RwLock::new(HashMap::new()),
+            groups: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 群的语言设置保存在文件中, 构建客户端时加载
+    pub fn file(default_language: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory(default_language)
+        }
+    }
+
+    pub(crate) async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            *self.groups.write().unwrap() = load_json(path).await?;
+        }
+        Ok(())
+    }
+
+    /// 添加一条文本
+    pub fn text(self, language: &str, key: &str, text: impl Into<String>) -> Self {
+        self.insert_text(language, key, text);
+        self
+    }
+
+    /// 添加一条文本, 可以在运行时调用
+    pub fn insert_text(&self, language: &str, key: &str, text: impl Into<String>) {
+        self.texts
+            .write()
+            .unwrap()
+            .entry(language.to_owned())
+            .or_default()
+            .insert(key.to_owned(), text.into());
+    }
+
+    /// 从json文件 (`{"key": "文本"}`) 读取一种语言的文本
+    pub async fn load_texts(&self, language: &str, path: &str) -> Result<()> {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("读取文件失败 : {}", path))?;
+        let texts: HashMap<String, String> =
+            serde_json::from_str(&text).with_context(|| format!("解析文件失败 : {}", path))?;
+        self.texts
+            .write()
+            .unwrap()
+            .entry(language.to_owned())
+            .or_default()
+            .extend(texts);
+        Ok(())
+    }
+
+    pub fn default_language(&self) -> &str {
+        &self.default_language
+    }
+
+    /// 群设置的语言, 没有设置时返回None
+    pub fn group_language(&self, group_code: i64) -> Option<String> {
+        self.groups.read().unwrap().get(&group_code).cloned()
+    }
+
+    /// 设置群使用的语言, 传入None时恢复为默认语言
+    pub async fn set_group_language(&self, group_code: i64, language: Option<&str>) -> Result<()> {
+        let groups = {
+            let mut groups = self.groups.write().unwrap();
+            match language {
+                Some(language) => groups.insert(group_code, language.to_owned()),
+                None => groups.remove(&group_code),
+            };
+            groups.clone()
+        };
+        if let Some(path) = &self.path {
+            save_json(path, &groups).await?;
+        }
+        Ok(())
+    }
+
+    /// 群或私聊 (group_code为None) 使用的语言
+    pub fn language(&self, group_code: Option<i64>) -> String {
+        group_code
+            .and_then(|group_code| self.group_language(group_code))
+            .unwrap_or_else(|| self.default_language.clone())
+    }
+
+    fn lookup(&self, language: &str, key: &str) -> Option<String> {
+        let texts = self.texts.read().unwrap();
+        texts
+            .get(language)
+            .and_then(|texts| texts.get(key))
+            .or_else(|| {
+                texts
+                    .get(&self.default_language)
+                    .and_then(|texts| texts.get(key))
+            })
+            .cloned()
+    }
+}
+
+/// 当前会话使用的语言, 使用`event.locale()`取得
+#[derive(Clone)]
+pub struct Locale {
+    language: String,
+    i18n: Arc<I18n>,
+}
+
+impl Locale {
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// 取得文本, 当前语言 -> 默认语言 -> key
+    pub fn text(&self, key: &str) -> String {
+        self.i18n
+            .lookup(&self.language, key)
+            .unwrap_or_else(|| key.to_owned())
+    }
+
+    /// 取得文本并作为消息模版渲染
+    pub fn render(&self, key: &str, values: &TemplateValues) -> Result<MessageChain> {
+        MessageTemplate::parse(&self.text(key))?.render(values)
+    }
+}
+
+/// 根据消息来源取得语言
+///
+/// ```ignore
+/// #[event(bot_command = "/hello")]
+/// async fn hello(event: &MessageEvent) -> anyhow::Result<bool> {
+///     let locale = event.locale();
+///     event
+///         .send_message_to_source(locale.render("welcome", &TemplateValues::from_event(event))?)
+///         .await?;
+///     Ok(true)
+/// }
+/// ```
+#[async_trait]
+pub trait LocaleTrait: MessageTargetTrait {
+    fn locale_client(&self) -> &ricq::Client;
+
+    /// 当前群 (私聊时为默认) 使用的语言
+    fn locale(&self) -> Locale {
+        let i18n = self
+            .locale_client()
+            .extensions()
+            .get_or_insert_with(I18n::default);
+        let group_code = match self.target() {
+            MessageTarget::Group(group_code, _) => Some(group_code),
+            _ => None,
+        };
+        Locale {
+            language: i18n.language(group_code),
+            i18n,
+        }
+    }
+
+    /// 设置当前群使用的语言, 只能在群消息中使用
+    async fn set_language(&self, language: &str) -> Result<()> {
+        let group_code = match self.target() {
+            MessageTarget::Group(group_code, _) => group_code,
+            _ => return Err(anyhow!("只能设置群的语言")),
+        };
+        self.locale_client()
+            .extensions()
+            .get_or_insert_with(I18n::default)
+            .set_group_language(group_code, Some(language))
+            .await
+    }
+}
+
+impl LocaleTrait for GroupMessageEvent {
+    fn locale_client(&self) -> &ricq::Client {
+        &self.client
+    }
+}
+
+impl LocaleTrait for FriendMessageEvent {
+    fn locale_client(&self) -> &ricq::Client {
+        &self.client
+    }
+}
+
+impl LocaleTrait for GroupTempMessageEvent {
+    fn locale_client(&self) -> &ricq::Client {
+        &self.client
+    }
+}
+
+impl LocaleTrait for MessageEvent {
+    fn locale_client(&self) -> &ricq::Client {
+        match self {
+            MessageEvent::GroupMessage(e) => &e.client,
+            MessageEvent::FriendMessage(e) => &e.client,
+            MessageEvent::GroupTempMessage(e) => &e.client,
+        }
+    }
+}
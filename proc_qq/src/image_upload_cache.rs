@@ -0,0 +1,61 @@
+use crate::{MessageTarget, UploadImage};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// 已上传的图片, 以图片数据的md5为键
+///
+/// 使用`ClientBuilder::image_upload_cache`开启, 之后`upload_image`等方法上传相同的图片时
+/// 不再重复下载处理后上传, 直接使用之前的结果 (群图片可以在其他群中使用). 超过容量时丢弃最早的图片.
+pub struct ImageUploadCache {
+    capacity: usize,
+    images: Mutex<CachedImages>,
+}
+
+#[derive(Default)]
+struct CachedImages {
+    /// (是否为群图片, md5) -> 上传的图片
+    images: HashMap<(bool, [u8; 16]), UploadImage>,
+    order: VecDeque<(bool, [u8; 16])>,
+}
+
+impl ImageUploadCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            images: Mutex::new(CachedImages::default()),
+        }
+    }
+
+    pub fn get(&self, target: &MessageTarget, md5: [u8; 16]) -> Option<UploadImage> {
+        self.images
+            .lock()
+            .unwrap()
+            .images
+            .get(&(is_group(target), md5))
+            .cloned()
+    }
+
+    pub fn insert(&self, target: &MessageTarget, md5: [u8; 16], image: UploadImage) {
+        let key = (is_group(target), md5);
+        let mut cached = self.images.lock().unwrap();
+        if cached.images.insert(key, image).is_none() {
+            cached.order.push_back(key);
+        }
+        while cached.order.len() > self.capacity {
+            if let Some(key) = cached.order.pop_front() {
+                cached.images.remove(&key);
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut cached = self.images.lock().unwrap();
+        cached.images.clear();
+        cached.order.clear();
+    }
+}
+
+/// 群和临时会话上传的都是群图片
+fn is_group(target: &MessageTarget) -> bool {
+    !matches!(target, MessageTarget::Private(_))
+}
@@ -1,16 +1,84 @@
 #![feature(impl_trait_in_assoc_type)]
 
+pub use anti_recall::*;
+pub use audit::*;
+pub use blocklist::*;
+pub use bot_mute::*;
 pub use client::*;
+pub use contacts::*;
+pub use conversation::*;
+pub use delayed_send::*;
+pub use download::*;
 pub use entities::*;
+pub use extensions::*;
 #[allow(unused_imports)]
 pub use features::*;
+pub use gif::*;
 pub use handler::*;
+pub use health::*;
+pub use i18n::*;
+pub use image_upload_cache::*;
+pub use long_message::*;
+pub use membership::*;
+pub use message_cache::*;
+pub use message_template::*;
+pub use module_manager::*;
+pub use module_store::*;
+pub use module_toggles::*;
+pub use multi_client::*;
+pub use ocr::*;
+pub use permissions::*;
+pub use plugin::*;
 pub use proc_qq_codegen::*;
+pub use quota::*;
+pub use rate_limit::*;
+pub use reconnect::*;
+pub use reminder::*;
+pub use rules::*;
+pub use send_fallback::*;
+pub use stats::*;
 pub use traits::*;
+pub use wake_word::*;
 
+mod anti_recall;
+mod audit;
+mod blocklist;
+mod bot_mute;
 mod client;
+mod contacts;
+mod conversation;
+mod delayed_send;
+mod download;
 mod entities;
+mod extensions;
 pub mod features;
+mod gif;
 mod handler;
+mod health;
+mod help;
+mod i18n;
+mod image_upload_cache;
+mod long_message;
+mod membership;
+mod message_cache;
+mod message_template;
+mod module_manager;
+mod module_store;
+mod module_toggles;
+mod multi_client;
+mod ocr;
+mod permissions;
+mod persist;
+mod plugin;
+mod quota;
+mod rate_limit;
 pub mod re_exports;
+mod reconnect;
+mod reminder;
+mod rules;
+mod send_fallback;
+mod stats;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod traits;
+mod wake_word;
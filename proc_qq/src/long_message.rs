@@ -0,0 +1,162 @@
+use crate::traits::send_to_target;
+use crate::{MessageTarget, MessageTargetTrait};
+use ricq::structs::{ForwardMessage, MessageNode};
+use ricq_core::msg::elem::{RQElem, Text};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use ricq_core::RQResult;
+
+/// 长消息的发送方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongMessageMode {
+    /// 打包成合并转发, 私聊或发送失败时使用Split
+    Forward,
+    /// 拆分成多条消息
+    Split,
+}
+
+/// 长消息的处理策略
+///
+/// 只处理纯文字的消息, 超过threshold个字符时按照mode发送.
+/// 通过`ClientBuilder::long_message_policy`设置后对所有发送生效, 也可以在单次发送时使用`send`.
+///
+/// ```ignore
+/// ClientBuilder::new().long_message_policy(LongMessagePolicy::forward(500));
+/// // 单次发送
+/// LongMessagePolicy::forward(200).send(&event.client, &event, chain).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct LongMessagePolicy {
+    pub threshold: usize,
+    pub mode: LongMessageMode,
+    /// 合并转发中每个节点或拆分后每条消息的最大字符数
+    pub chunk_len: usize,
+    /// 合并转发中显示的发送者名称
+    pub sender_name: String,
+}
+
+impl LongMessagePolicy {
+    pub fn forward(threshold: usize) -> Self {
+        Self {
+            threshold,
+            mode: LongMessageMode::Forward,
+            chunk_len: 1500,
+            sender_name: "机器人".to_owned(),
+        }
+    }
+
+    pub fn split(threshold: usize) -> Self {
+        Self {
+            mode: LongMessageMode::Split,
+            ..Self::forward(threshold)
+        }
+    }
+
+    pub fn chunk_len(mut self, chunk_len: usize) -> Self {
+        self.chunk_len = chunk_len.max(1);
+        self
+    }
+
+    pub fn sender_name(mut self, sender_name: impl Into<String>) -> Self {
+        self.sender_name = sender_name.into();
+        self
+    }
+
+    // 纯文字并且超过长度时返回文字
+    pub(crate) fn long_text(&self, chain: &MessageChain) -> Option<String> {
+        let mut text = String::new();
+        for elem in chain.clone() {
+            match elem {
+                RQElem::Text(t) => text.push_str(&t.content),
+                _ => return None,
+            }
+        }
+        if text.chars().count() > self.threshold {
+            Some(text)
+        } else {
+            None
+        }
+    }
+
+    /// 按照策略发送消息, 不是长消息时直接发送
+    pub async fn send(
+        &self,
+        client: &ricq::Client,
+        target: &impl MessageTargetTrait,
+        chain: MessageChain,
+    ) -> RQResult<MessageReceipt> {
+        let target = target.target();
+        match self.long_text(&chain) {
+            Some(text) => self.send_long_text(client, target, &text).await,
+            None => send_to_target(client, target, chain).await,
+        }
+    }
+
+    pub(crate) async fn send_long_text(
+        &self,
+        client: &ricq::Client,
+        target: MessageTarget,
+        text: &str,
+    ) -> RQResult<MessageReceipt> {
+        let chunks = split_text(text, self.chunk_len);
+        if let (LongMessageMode::Forward, MessageTarget::Group(group_code, _)) = (self.mode, target)
+        {
+            let sender_id = client.uin().await;
+            let time = chrono::Utc::now().timestamp() as i32;
+            let nodes = chunks
+                .iter()
+                .map(|chunk| {
+                    ForwardMessage::Message(MessageNode {
+                        sender_id,
+                        time,
+                        sender_name: self.sender_name.clone(),
+                        elements: MessageChain::new(Text::new(chunk.clone())),
+                    })
+                })
+                .collect();
+            match client.send_group_forward_message(group_code, nodes).await {
+                Ok(receipt) => return Ok(receipt),
+                Err(err) => tracing::warn!("合并转发发送失败, 拆分发送 : {:?}", err),
+            }
+        }
+        let mut receipt = MessageReceipt::default();
+        for chunk in chunks {
+            receipt = send_to_target(client, target, MessageChain::new(Text::new(chunk))).await?;
+        }
+        Ok(receipt)
+    }
+}
+
+/// 按行拆分文字, 每段不超过chunk_len个字符, 过长的行会被截断到下一段
+pub fn split_text(text: &str, chunk_len: usize) -> Vec<String> {
+    let chunk_len = chunk_len.max(1);
+    let mut chunks = vec![];
+    let mut current = String::new();
+    let mut current_len = 0;
+    for line in text.split_inclusive('\n') {
+        let mut line: Vec<char> = line.chars().collect();
+        while !line.is_empty() {
+            if current_len == chunk_len {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            let take = line.len().min(chunk_len - current_len);
+            // 整行放不下时先开始新的一段
+            if take < line.len() && current_len > 0 {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+                continue;
+            }
+            current.extend(line.drain(..take));
+            current_len += take;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+        .into_iter()
+        .map(|chunk| chunk.trim_end_matches('\n').to_owned())
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
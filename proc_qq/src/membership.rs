@@ -0,0 +1,199 @@
+use crate::persist::{load_json, save_json};
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// 成员变动的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MembershipChange {
+    Join,
+    Leave,
+    Kick,
+}
+
+impl Display for MembershipChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MembershipChange::Join => "加入",
+            MembershipChange::Leave => "退出",
+            MembershipChange::Kick => "被踢出",
+        })
+    }
+}
+
+/// 成员变动记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipRecord {
+    /// unix毫秒
+    pub time: i64,
+    pub group_code: i64,
+    pub uin: i64,
+    pub change: MembershipChange,
+    /// 踢人的管理员
+    pub operator: Option<i64>,
+}
+
+impl MembershipRecord {
+    pub fn datetime(&self) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.time)
+            .single()
+            .unwrap_or_else(Utc::now)
+    }
+}
+
+impl Display for MembershipRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let time = Local
+            .timestamp_millis_opt(self.time)
+            .single()
+            .map(|t| t.format("%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        write!(f, "{} {} {}", time, self.uin, self.change)?;
+        if let Some(operator) = self.operator {
+            write!(f, " (操作者 {})", operator)?;
+        }
+        Ok(())
+    }
+}
+
+/// 群成员变动记录
+///
+/// 通过`ClientBuilder::membership_tracker`开启, 记录成员的加入、退出和被踢出.
+pub struct MembershipTracker {
+    path: Option<String>,
+    retention: Duration,
+    records: RwLock<Vec<MembershipRecord>>,
+}
+
+impl MembershipTracker {
+    /// 仅保存在内存中的记录
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            retention: Duration::from_secs(60 * 60 * 24 * 365),
+            records: RwLock::new(vec![]),
+        }
+    }
+
+    /// 保存在文件中的记录, 构建客户端时加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    /// 记录保留的时间, 默认一年
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub(crate) async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            *self.records.write().await = load_json(path).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn record(&self, record: MembershipRecord) -> Result<()> {
+        let oldest = Utc::now().timestamp_millis() - self.retention.as_millis() as i64;
+        let mut records = self.records.write().await;
+        records.retain(|record| record.time >= oldest);
+        records.push(record);
+        if let Some(path) = &self.path {
+            save_json(path, &*records).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn record_change(
+        &self,
+        group_code: i64,
+        uin: i64,
+        change: MembershipChange,
+        operator: Option<i64>,
+    ) {
+        let record = MembershipRecord {
+            time: Utc::now().timestamp_millis(),
+            group_code,
+            uin,
+            change,
+            operator,
+        };
+        if let Err(err) = self.record(record).await {
+            tracing::warn!("成员变动记录保存失败 : {:?}", err);
+        }
+    }
+
+    /// 按条件查询, 新的在前
+    pub async fn query<F: Fn(&MembershipRecord) -> bool>(
+        &self,
+        filter: F,
+        limit: usize,
+    ) -> Vec<MembershipRecord> {
+        self.records
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|record| filter(record))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// 群中某段时间之后的变动, 新的在前, change为None时包括所有类型
+    pub async fn changes_since(
+        &self,
+        group_code: i64,
+        since: DateTime<Utc>,
+        change: Option<MembershipChange>,
+    ) -> Vec<MembershipRecord> {
+        let since = since.timestamp_millis();
+        self.query(
+            |record| {
+                record.group_code == group_code
+                    && record.time >= since
+                    && (change.is_none() || change == Some(record.change))
+            },
+            usize::MAX,
+        )
+        .await
+    }
+
+    /// 某段时间之后离开群的成员 (包括被踢出)
+    pub async fn left_since(&self, group_code: i64, since: DateTime<Utc>) -> Vec<MembershipRecord> {
+        let mut records = self.changes_since(group_code, since, None).await;
+        records.retain(|record| record.change != MembershipChange::Join);
+        records
+    }
+
+    /// 成员最近一次加入群的时间, 没有记录时返回None
+    pub async fn joined_at(&self, group_code: i64, uin: i64) -> Option<DateTime<Utc>> {
+        self.query(
+            |record| {
+                record.group_code == group_code
+                    && record.uin == uin
+                    && record.change == MembershipChange::Join
+            },
+            1,
+        )
+        .await
+        .first()
+        .map(MembershipRecord::datetime)
+    }
+
+    /// 成员在群中的所有变动, 新的在前
+    pub async fn history(&self, group_code: i64, uin: i64) -> Vec<MembershipRecord> {
+        self.query(
+            |record| record.group_code == group_code && record.uin == uin,
+            usize::MAX,
+        )
+        .await
+    }
+}
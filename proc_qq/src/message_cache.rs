@@ -0,0 +1,123 @@
+use crate::MessageEvent;
+use ricq_core::msg::MessageChain;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// 缓存的消息
+#[derive(Debug, Clone)]
+pub struct CachedMessage {
+    /// unix秒
+    pub time: i32,
+    /// 好友消息为None, 临时会话为发起会话的群
+    pub group_code: Option<i64>,
+    /// 是否为群临时会话
+    pub temp: bool,
+    pub from_uin: i64,
+    /// 群名片或昵称
+    pub sender_name: String,
+    pub seqs: Vec<i32>,
+    pub elements: MessageChain,
+}
+
+/// 最近收到的消息
+///
+/// 通过`ClientBuilder::message_cache`开启, 调度器会缓存收到的群消息、好友消息和临时会话消息,
+/// 超过容量时丢弃最早的消息. 可以用于防撤回或查找被引用的消息.
+pub struct MessageCache {
+    capacity: usize,
+    messages: Mutex<VecDeque<CachedMessage>>,
+}
+
+impl MessageCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn push_event(&self, event: &MessageEvent) {
+        let message = match event {
+            MessageEvent::GroupMessage(e) => CachedMessage {
+                time: e.inner.time,
+                group_code: Some(e.inner.group_code),
+                temp: false,
+                from_uin: e.inner.from_uin,
+                sender_name: e.inner.group_card.clone(),
+                seqs: e.inner.seqs.clone(),
+                elements: e.inner.elements.clone(),
+            },
+            MessageEvent::FriendMessage(e) => CachedMessage {
+                time: e.inner.time,
+                group_code: None,
+                temp: false,
+                from_uin: e.inner.from_uin,
+                sender_name: e.inner.from_nick.clone(),
+                seqs: e.inner.seqs.clone(),
+                elements: e.inner.elements.clone(),
+            },
+            MessageEvent::GroupTempMessage(e) => CachedMessage {
+                time: e.inner.time,
+                group_code: Some(e.inner.group_code),
+                temp: true,
+                from_uin: e.inner.from_uin,
+                sender_name: e.inner.from_nick.clone(),
+                seqs: e.inner.seqs.clone(),
+                elements: e.inner.elements.clone(),
+            },
+        };
+        self.push(message);
+    }
+
+    pub fn push(&self, message: CachedMessage) {
+        let mut messages = self.messages.lock().unwrap();
+        messages.push_back(message);
+        while messages.len() > self.capacity {
+            messages.pop_front();
+        }
+    }
+
+    fn find<F: Fn(&CachedMessage) -> bool>(&self, filter: F) -> Option<CachedMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|message| filter(message))
+            .cloned()
+    }
+
+    /// 按序号查找群消息
+    pub fn find_group_message(&self, group_code: i64, seq: i32) -> Option<CachedMessage> {
+        self.find(|message| {
+            !message.temp && message.group_code == Some(group_code) && message.seqs.contains(&seq)
+        })
+    }
+
+    /// 按序号查找好友消息
+    pub fn find_friend_message(&self, uin: i64, seq: i32) -> Option<CachedMessage> {
+        self.find(|message| {
+            message.group_code.is_none() && message.from_uin == uin && message.seqs.contains(&seq)
+        })
+    }
+
+    /// 按序号查找临时会话消息
+    pub fn find_temp_message(&self, uin: i64, seq: i32) -> Option<CachedMessage> {
+        self.find(|message| message.temp && message.from_uin == uin && message.seqs.contains(&seq))
+    }
+
+    /// 最近的消息, 新的在前. group_code为None时为所有消息, 否则为该群的群消息
+    pub fn recent(&self, group_code: Option<i64>, limit: usize) -> Vec<CachedMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|message| {
+                group_code.is_none() || (!message.temp && message.group_code == group_code)
+            })
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
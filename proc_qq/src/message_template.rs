@@ -0,0 +1,216 @@
+use crate::{MessageEvent, MessageSendToSourceTrait, TextEleParseTrait};
+use anyhow::{anyhow, Result};
+use ricq_core::msg::elem::At;
+use ricq_core::msg::MessageChain;
+use ricq_core::pb::msg::elem::Elem;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Variable(String),
+}
+
+/// 消息模版
+///
+/// 使用`{name}`作为占位符, `{{`和`}}`表示花括号本身, 例如 `"欢迎 {at_sender} 加入 {group_name}!"`.
+/// 可以直接从配置文件中反序列化.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MessageTemplate {
+    source: String,
+    parts: Vec<TemplatePart>,
+}
+
+impl MessageTemplate {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err(anyhow!("消息模版中的占位符没有闭合 : {}", source))
+                            }
+                            Some(c) => name.push(c),
+                        }
+                    }
+                    let name = name.trim();
+                    if name.is_empty() {
+                        return Err(anyhow!("消息模版中存在空的占位符 : {}", source));
+                    }
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(TemplatePart::Variable(name.to_owned()));
+                }
+                '}' => return Err(anyhow!("消息模版中存在多余的 }} : {}", source)),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        Ok(Self {
+            source: source.to_owned(),
+            parts,
+        })
+    }
+
+    /// 模版中使用的变量名
+    pub fn variables(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                TemplatePart::Variable(name) => Some(name.as_str()),
+                TemplatePart::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// 使用变量生成消息, 缺少变量时返回错误
+    pub fn render(&self, values: &TemplateValues) -> Result<MessageChain> {
+        let mut chain = MessageChain::default();
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(t) => text.push_str(t),
+                TemplatePart::Variable(name) => match values.values.get(name) {
+                    Some(TemplateValue::Text(t)) => text.push_str(t),
+                    Some(TemplateValue::At(uin, display)) => {
+                        if !text.is_empty() {
+                            chain.push(std::mem::take(&mut text).parse_text());
+                        }
+                        let mut at = At::new(*uin);
+                        at.display = display.clone();
+                        chain.push(at);
+                    }
+                    Some(TemplateValue::Elements(elements)) => {
+                        if !text.is_empty() {
+                            chain.push(std::mem::take(&mut text).parse_text());
+                        }
+                        chain.0.extend(elements.iter().cloned());
+                    }
+                    None => return Err(anyhow!("消息模版缺少变量 : {}", name)),
+                },
+            }
+        }
+        if !text.is_empty() {
+            chain.push(text.parse_text());
+        }
+        Ok(chain)
+    }
+}
+
+impl FromStr for MessageTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for MessageTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<MessageTemplate> for String {
+    fn from(value: MessageTemplate) -> Self {
+        value.source
+    }
+}
+
+impl Display for MessageTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TemplateValue {
+    Text(String),
+    /// At(uin, 显示的文字)
+    At(i64, String),
+    /// 图片等任意消息元素
+    Elements(Vec<Elem>),
+}
+
+/// 渲染模版时使用的变量
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    values: HashMap<String, TemplateValue>,
+}
+
+impl TemplateValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据消息事件预先填充变量
+    ///
+    /// - `sender_uin` 发送者的QQ号
+    /// - `sender_name` 发送者的群名片或昵称
+    /// - `at_sender` At发送者 (私聊时为发送者的名字)
+    /// - `group_code` `group_name` 群号和群名 (仅群消息和临时会话)
+    pub fn from_event(event: &MessageEvent) -> Self {
+        let values = Self::new().text("sender_uin", event.from_uin());
+        match event {
+            MessageEvent::GroupMessage(e) => values
+                .text("sender_name", &e.inner.group_card)
+                .at(
+                    "at_sender",
+                    e.inner.from_uin,
+                    format!("@{}", e.inner.group_card),
+                )
+                .text("group_code", e.inner.group_code)
+                .text("group_name", &e.inner.group_name),
+            MessageEvent::FriendMessage(e) => values
+                .text("sender_name", &e.inner.from_nick)
+                .text("at_sender", &e.inner.from_nick),
+            MessageEvent::GroupTempMessage(e) => values
+                .text("sender_name", &e.inner.from_nick)
+                .text("at_sender", &e.inner.from_nick)
+                .text("group_code", e.inner.group_code),
+        }
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: TemplateValue) {
+        self.values.insert(name.into(), value);
+    }
+
+    pub fn text(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.insert(name, TemplateValue::Text(value.to_string()));
+        self
+    }
+
+    pub fn at(mut self, name: impl Into<String>, uin: i64, display: impl Into<String>) -> Self {
+        self.insert(name, TemplateValue::At(uin, display.into()));
+        self
+    }
+
+    /// 图片 (UploadImage, GroupImage, FriendImage...) 或其他消息元素
+    pub fn image<E: Into<Vec<Elem>>>(mut self, name: impl Into<String>, image: E) -> Self {
+        self.insert(name, TemplateValue::Elements(image.into()));
+        self
+    }
+}
@@ -0,0 +1,271 @@
+use crate::audit::record_config_change;
+use crate::{
+    MemberTrait, MessageContentTrait, MessageEvent, MessageSendToSourceTrait, Module,
+    ModuleToggles, Permissions, Role, TextEleParseTrait,
+};
+use anyhow::{Context, Result};
+use ricq_core::msg::MessageChain;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 模块的状态, 见`ModuleManager::list`
+#[derive(Debug, Clone)]
+pub struct ModuleState {
+    pub id: String,
+    pub name: String,
+    /// 实际使用的优先级, 被`ClientBuilder::module_priority`覆盖时为覆盖后的值
+    pub priority: i32,
+    /// 处理器的名称
+    pub handles: Vec<String>,
+    /// 是否开启, 传入群号时为在该群中是否开启
+    pub enabled: bool,
+}
+
+/// 在运行时管理模块, 使用`client.module_manager()`取得
+///
+/// 模块可以使用id或名称指定, 开关状态保存在`ModuleToggles`中, 持久化方式见`ClientBuilder::module_toggles`.
+/// admin以上的用户、群主和群管理员也可以在聊天中使用`/module`指令, 见`ModuleManager::handle_command`.
+///
+/// ```ignore
+/// let manager = client.module_manager();
+/// manager.disable("签到").await?;
+/// manager.enable_in(group_code, "sign_in").await?;
+/// for state in manager.list(None) {
+///     println!("{} {} {}", state.id, state.name, state.enabled);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ModuleManager {
+    modules: Arc<Vec<Arc<Module>>>,
+    /// `ClientBuilder::module_priority`设置的优先级
+    priorities: Arc<HashMap<String, i32>>,
+    toggles: Arc<ModuleToggles>,
+}
+
+/// 模块实际使用的优先级, 排序模块和`ModuleManager::list`都使用这里的结果
+pub(crate) fn effective_priority(priorities: &HashMap<String, i32>, module: &Module) -> i32 {
+    priorities
+        .get(&module.id)
+        .copied()
+        .unwrap_or(module.priority)
+}
+
+impl ModuleManager {
+    pub(crate) fn new(
+        modules: Arc<Vec<Arc<Module>>>,
+        priorities: Arc<HashMap<String, i32>>,
+        toggles: Arc<ModuleToggles>,
+    ) -> Self {
+        Self {
+            modules,
+            priorities,
+            toggles,
+        }
+    }
+
+    /// 按id或名称查找模块, 优先匹配id
+    pub fn find(&self, id_or_name: &str) -> Option<Arc<Module>> {
+        self.modules
+            .iter()
+            .find(|m| m.id == id_or_name)
+            .or_else(|| self.modules.iter().find(|m| m.name == id_or_name))
+            .cloned()
+    }
+
+    /// 所有模块的状态, 按处理事件的顺序排列. group_code为None时为全局的开关状态
+    pub fn list(&self, group_code: Option<i64>) -> Vec<ModuleState> {
+        self.modules
+            .iter()
+            .map(|m| ModuleState {
+                id: m.id.clone(),
+                name: m.name.clone(),
+                priority: effective_priority(&self.priorities, m),
+                handles: m.handles.iter().map(|h| h.name.clone()).collect(),
+                enabled: self.toggles.is_enabled_in(group_code, &m.id),
+            })
+            .collect()
+    }
+
+    pub fn is_enabled(&self, group_code: Option<i64>, id_or_name: &str) -> bool {
+        match self.find(id_or_name) {
+            Some(module) => self.toggles.is_enabled_in(group_code, &module.id),
+            None => false,
+        }
+    }
+
+    /// 全局开启或关闭模块, 也可以使用`模块.处理器名称`只开关一个处理器, 返回保存在开关中的名称
+    pub async fn set_enabled(&self, feature: &str, enabled: bool) -> Result<String> {
+        let feature = self.resolve(feature)?;
+        self.toggles.set_enabled(&feature, enabled).await?;
+        Ok(feature)
+    }
+
+    /// 只在一个群中开启或关闭模块或处理器, 返回保存在开关中的名称
+    pub async fn set_enabled_in(
+        &self,
+        group_code: i64,
+        feature: &str,
+        enabled: bool,
+    ) -> Result<String> {
+        let feature = self.resolve(feature)?;
+        self.toggles
+            .set_group_enabled(group_code, &feature, enabled)
+            .await?;
+        Ok(feature)
+    }
+
+    pub async fn enable(&self, id_or_name: &str) -> Result<String> {
+        self.set_enabled(id_or_name, true).await
+    }
+
+    pub async fn disable(&self, id_or_name: &str) -> Result<String> {
+        self.set_enabled(id_or_name, false).await
+    }
+
+    pub async fn enable_in(&self, group_code: i64, id_or_name: &str) -> Result<String> {
+        self.set_enabled_in(group_code, id_or_name, true).await
+    }
+
+    pub async fn disable_in(&self, group_code: i64, id_or_name: &str) -> Result<String> {
+        self.set_enabled_in(group_code, id_or_name, false).await
+    }
+
+    /// 模块开关, 可以用于关闭单个处理器
+    pub fn toggles(&self) -> Arc<ModuleToggles> {
+        self.toggles.clone()
+    }
+
+    /// 将模块id或名称(或者`模块id或名称.处理器名称`)转换为开关使用的名称
+    fn resolve(&self, feature: &str) -> Result<String> {
+        if let Some(module) = self.find(feature) {
+            return Ok(module.id.clone());
+        }
+        feature
+            .rsplit_once('.')
+            .and_then(|(module, handle)| {
+                let module = self.find(module)?;
+                module
+                    .handles
+                    .iter()
+                    .any(|h| h.name == handle)
+                    .then(|| format!("{}.{}", module.id, handle))
+            })
+            .with_context(|| format!("模块不存在 : {}", feature))
+    }
+
+    /// 处理`/module`指令(`/feature`为别名), 返回是否为管理指令.
+    /// admin以上的用户可以使用全部功能, 群主和群管理员只能在群中使用, 并且只能修改本群的开关.
+    /// 没有指定群号时, 在群中为本群, 私聊时为全局
+    pub(crate) async fn handle_command(
+        &self,
+        event: &MessageEvent,
+        permissions: &Permissions,
+    ) -> Result<bool> {
+        let content = event.message_content();
+        let mut sp = content.split_whitespace();
+        if !matches!(sp.next(), Some("/module" | "/feature")) {
+            return Ok(false);
+        }
+        let group_code = match event {
+            MessageEvent::GroupMessage(e) => Some(e.inner.group_code),
+            _ => None,
+        };
+        let is_admin = permissions
+            .has_role(group_code, event.from_uin(), Role::Admin)
+            .await;
+        if !is_admin && !is_group_admin(event).await {
+            return Ok(false);
+        }
+        // 群主和群管理员不能指定其他群
+        let scope = |group: &str| match group.parse() {
+            Ok(group) if is_admin || Some(group) == group_code => Some(group),
+            _ => None,
+        };
+        let args: Vec<&str> = sp.collect();
+        let reply = match args.as_slice() {
+            [] | ["list"] => self.list_reply(group_code),
+            ["list", group] => match scope(group) {
+                Some(group_code) => self.list_reply(Some(group_code)),
+                None => MODULE_USAGE.to_owned(),
+            },
+            [action @ ("on" | "off"), feature] => {
+                self.toggle_reply(group_code, feature, *action == "on")
+                    .await
+            }
+            [action @ ("on" | "off"), feature, group] => match scope(group) {
+                Some(group_code) => {
+                    self.toggle_reply(Some(group_code), feature, *action == "on")
+                        .await
+                }
+                None => MODULE_USAGE.to_owned(),
+            },
+            _ => MODULE_USAGE.to_owned(),
+        };
+        if reply.starts_with("已") {
+            record_config_change(event).await;
+        }
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+
+    async fn toggle_reply(&self, group_code: Option<i64>, feature: &str, enabled: bool) -> String {
+        let action = if enabled { "开启" } else { "关闭" };
+        match group_code {
+            None => match self.set_enabled(feature, enabled).await {
+                Ok(feature) => format!("已{}模块 : {}", action, feature),
+                Err(err) => err.to_string(),
+            },
+            Some(group_code) => match self.set_enabled_in(group_code, feature, enabled).await {
+                Ok(feature) => format!("已在群{}{}模块 : {}", group_code, action, feature),
+                Err(err) => err.to_string(),
+            },
+        }
+    }
+
+    fn list_reply(&self, group_code: Option<i64>) -> String {
+        let mut lines: Vec<String> = self
+            .list(group_code)
+            .into_iter()
+            .map(|state| {
+                let flag = if state.enabled { "开" } else { "关" };
+                format!("[{}] {} ({})", flag, state.id, state.name)
+            })
+            .collect();
+        let mut disabled = self.toggles.disabled();
+        if let Some(group_code) = group_code {
+            disabled.extend(self.toggles.group_disabled(group_code));
+        }
+        disabled.sort();
+        disabled.dedup();
+        // 被关闭的单个处理器
+        for feature in disabled.iter().filter(|f| f.contains('.')) {
+            lines.push(format!("[关] {}", feature));
+        }
+        if lines.is_empty() {
+            "没有模块".to_owned()
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
+/// 发送者是否为群主或群管理员
+async fn is_group_admin(event: &MessageEvent) -> bool {
+    match event {
+        MessageEvent::GroupMessage(e) => match e
+            .client
+            .get_group_member_info(e.inner.group_code, e.inner.from_uin)
+            .await
+        {
+            Ok(info) => !info.is_member(),
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
+
+static MODULE_USAGE: &str = "/module list [群号]\n\
+/module on|off 模块id或名称[.处理器名称] [群号]\n\
+没有群号时在群中为本群, 私聊时为全局. /feature 与 /module 相同";
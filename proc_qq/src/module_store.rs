@@ -0,0 +1,228 @@
+use crate::handler::current_module_id;
+use crate::persist::{load_json, save_json};
+use crate::ExtensionsTrait;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+
+/// 模块使用的键值存储, 每个模块使用各自的命名空间 (模块id)
+///
+/// 使用`ClientBuilder::store`设置, 未设置时为仅保存在内存中的`MemoryStore`.
+/// 内置`MemoryStore`和`FileStore`, 开启`sled_store`或`sqlite_store`特性后可以使用`SledStore`或`SqliteStore`.
+#[async_trait]
+pub trait ModuleStore: Send + Sync {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>>;
+    async fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()>;
+    /// 删除一个键, 返回键是否存在
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool>;
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>>;
+}
+
+type StoreData = HashMap<String, HashMap<String, Value>>;
+
+/// 仅保存在内存中的存储
+#[derive(Default)]
+pub struct MemoryStore {
+    data: RwLock<StoreData>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ModuleStore for MemoryStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .get(namespace)
+            .and_then(|values| values.get(key))
+            .cloned())
+    }
+
+    async fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()> {
+        self.data
+            .write()
+            .unwrap()
+            .entry(namespace.to_owned())
+            .or_default()
+            .insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool> {
+        let mut data = self.data.write().unwrap();
+        Ok(match data.get_mut(namespace) {
+            Some(values) => values.remove(key).is_some(),
+            None => false,
+        })
+    }
+
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .get(namespace)
+            .map(|values| values.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+}
+
+/// 保存在一个json文件中的存储, 第一次使用时读取, 每次修改后写入整个文件.
+/// 适合数据量较小的场景
+pub struct FileStore {
+    path: String,
+    data: Mutex<Option<StoreData>>,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            data: Mutex::new(None),
+        }
+    }
+
+    async fn with_data<R, F: FnOnce(&mut StoreData) -> (R, bool)>(&self, f: F) -> Result<R> {
+        let mut data = self.data.lock().await;
+        if data.is_none() {
+            *data = Some(load_json(&self.path).await?);
+        }
+        let data = data.as_mut().unwrap();
+        let (result, modified) = f(data);
+        if modified {
+            save_json(&self.path, data).await?;
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl ModuleStore for FileStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        self.with_data(|data| {
+            let value = data.get(namespace).and_then(|values| values.get(key));
+            (value.cloned(), false)
+        })
+        .await
+    }
+
+    async fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()> {
+        self.with_data(|data| {
+            data.entry(namespace.to_owned())
+                .or_default()
+                .insert(key.to_owned(), value);
+            ((), true)
+        })
+        .await
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool> {
+        self.with_data(|data| {
+            let removed = match data.get_mut(namespace) {
+                Some(values) => values.remove(key).is_some(),
+                None => false,
+            };
+            (removed, removed)
+        })
+        .await
+    }
+
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        self.with_data(|data| {
+            let keys = data
+                .get(namespace)
+                .map(|values| values.keys().cloned().collect())
+                .unwrap_or_default();
+            (keys, false)
+        })
+        .await
+    }
+}
+
+/// 客户端中设置的存储
+pub(crate) struct ClientStore(pub(crate) Arc<dyn ModuleStore>);
+
+/// 一个命名空间中的数据, 值以json保存
+///
+/// ```ignore
+/// #[event(bot_command = "/签到")]
+/// async fn sign_in(event: &GroupMessageEvent) -> anyhow::Result<bool> {
+///     let store = event.module_store()?;
+///     let key = event.inner.from_uin.to_string();
+///     let days: u32 = store.get(&key).await?.unwrap_or(0) + 1;
+///     store.set(&key, &days).await?;
+///     // ...
+///     Ok(true)
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ModuleStorage {
+    namespace: String,
+    store: Arc<dyn ModuleStore>,
+}
+
+impl ModuleStorage {
+    pub(crate) fn new(namespace: impl Into<String>, store: Arc<dyn ModuleStore>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            store,
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.store.get(&self.namespace, key).await? {
+            Some(value) => Ok(Some(serde_json::from_value(value).with_context(|| {
+                format!("解析存储的数据失败 : {}.{}", self.namespace, key)
+            })?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<()> {
+        self.store
+            .set(&self.namespace, key, serde_json::to_value(value)?)
+            .await
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<bool> {
+        self.store.delete(&self.namespace, key).await
+    }
+
+    pub async fn keys(&self) -> Result<Vec<String>> {
+        self.store.keys(&self.namespace).await
+    }
+}
+
+/// 取得模块的存储
+pub trait ModuleStoreTrait: ExtensionsTrait {
+    /// 当前模块的存储 (命名空间为模块id), 只能在事件处理过程中调用
+    fn module_store(&self) -> Result<ModuleStorage> {
+        let module_id =
+            current_module_id().with_context(|| "只能在事件处理过程中取得模块的存储")?;
+        Ok(self.module_store_for(&module_id))
+    }
+
+    /// 指定命名空间的存储, 可以在定时任务等事件处理之外的地方使用.
+    /// 在`ClientBuilder`上调用时需要先设置`ClientBuilder::store`
+    fn module_store_for(&self, namespace: &str) -> ModuleStorage {
+        let store = self
+            .extensions()
+            .get_or_insert_with(|| ClientStore(Arc::new(MemoryStore::new())))
+            .0
+            .clone();
+        ModuleStorage::new(namespace, store)
+    }
+}
+
+impl<T: ExtensionsTrait + ?Sized> ModuleStoreTrait for T {}
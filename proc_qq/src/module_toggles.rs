@@ -0,0 +1,188 @@
+use crate::module_store::ClientStore;
+use crate::persist::{load_json, save_json};
+use crate::{Extensions, MemoryStore, ModuleStore};
+use anyhow::{Context, Result};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// 保存在客户端存储中时使用的命名空间和键
+const STORE_NAMESPACE: &str = "proc_qq.module_toggles";
+const STORE_KEY: &str = "toggles";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ModuleTogglesData {
+    disabled: HashSet<String>,
+    /// 群号 -> 在该群中关闭的功能
+    #[serde(default)]
+    group_disabled: HashMap<i64, HashSet<String>>,
+}
+
+/// 模块开关
+///
+/// 被关闭的模块不会收到任何事件, 可以在运行时通过`client.module_toggles()`或管理界面切换.
+/// 功能名称为模块id (关闭整个模块) 或`模块id.处理器名称` (只关闭一个处理器).
+/// 也可以只在某个群中关闭, 只对群消息生效. 聊天中的`/module`指令见`ModuleManager`.
+pub struct ModuleToggles {
+    persistence: Persistence,
+    data: RwLock<ModuleTogglesData>,
+}
+
+enum Persistence {
+    Memory,
+    File(String),
+    /// 构建客户端时取得`ClientBuilder::store`设置的存储
+    Store(OnceLock<Arc<dyn ModuleStore>>),
+}
+
+impl ModuleToggles {
+    fn with_persistence(persistence: Persistence) -> Self {
+        Self {
+            persistence,
+            data: RwLock::new(ModuleTogglesData::default()),
+        }
+    }
+
+    /// 仅保存在内存中的开关
+    pub fn memory() -> Self {
+        Self::with_persistence(Persistence::Memory)
+    }
+
+    /// 保存在文件中的开关, 构建客户端时加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self::with_persistence(Persistence::File(path.into()))
+    }
+
+    /// 保存在`ClientBuilder::store`设置的存储中 (如`SledStore`、`SqliteStore`), 构建客户端时加载
+    pub fn store() -> Self {
+        Self::with_persistence(Persistence::Store(OnceLock::new()))
+    }
+
+    pub(crate) async fn load(&self, extensions: &Extensions) -> Result<()> {
+        let data = match &self.persistence {
+            Persistence::Memory => return Ok(()),
+            Persistence::File(path) => load_json(path).await?,
+            Persistence::Store(cell) => {
+                let store = extensions
+                    .get_or_insert_with(|| ClientStore(Arc::new(MemoryStore::new())))
+                    .0
+                    .clone();
+                let value = store.get(STORE_NAMESPACE, STORE_KEY).await?;
+                let _ = cell.set(store);
+                match value {
+                    Some(value) => {
+                        serde_json::from_value(value).with_context(|| "解析存储的模块开关失败")?
+                    }
+                    None => return Ok(()),
+                }
+            }
+        };
+        *self.data.write().unwrap() = data;
+        Ok(())
+    }
+
+    async fn modify<F: FnOnce(&mut ModuleTogglesData)>(&self, f: F) -> Result<()> {
+        let data = {
+            let mut data = self.data.write().unwrap();
+            f(&mut data);
+            data.clone()
+        };
+        match &self.persistence {
+            Persistence::Memory => (),
+            Persistence::File(path) => save_json(path, &data).await?,
+            Persistence::Store(cell) => {
+                if let Some(store) = cell.get() {
+                    store
+                        .set(STORE_NAMESPACE, STORE_KEY, serde_json::to_value(&data)?)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, module_id: &str) -> bool {
+        !self.data.read().unwrap().disabled.contains(module_id)
+    }
+
+    /// 模块在群中是否开启 (全局关闭时也返回false)
+    pub fn is_enabled_in(&self, group_code: Option<i64>, module_id: &str) -> bool {
+        let data = self.data.read().unwrap();
+        if data.disabled.contains(module_id) {
+            return false;
+        }
+        match group_code.and_then(|group_code| data.group_disabled.get(&group_code)) {
+            Some(disabled) => !disabled.contains(module_id),
+            None => true,
+        }
+    }
+
+    /// 处理器在群中是否开启
+    pub fn is_handle_enabled_in(
+        &self,
+        group_code: Option<i64>,
+        module_id: &str,
+        handle_name: &str,
+    ) -> bool {
+        let data = self.data.read().unwrap();
+        if data.disabled.is_empty() && data.group_disabled.is_empty() {
+            return true;
+        }
+        let feature = format!("{}.{}", module_id, handle_name);
+        if data.disabled.contains(&feature) {
+            return false;
+        }
+        match group_code.and_then(|group_code| data.group_disabled.get(&group_code)) {
+            Some(disabled) => !disabled.contains(&feature),
+            None => true,
+        }
+    }
+
+    pub async fn set_enabled(&self, module_id: &str, enabled: bool) -> Result<()> {
+        self.modify(|data| {
+            if enabled {
+                data.disabled.remove(module_id);
+            } else {
+                data.disabled.insert(module_id.to_owned());
+            }
+        })
+        .await
+    }
+
+    /// 只在一个群中开启或关闭功能
+    pub async fn set_group_enabled(
+        &self,
+        group_code: i64,
+        feature: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        self.modify(|data| {
+            let disabled = data.group_disabled.entry(group_code).or_default();
+            if enabled {
+                disabled.remove(feature);
+            } else {
+                disabled.insert(feature.to_owned());
+            }
+            if disabled.is_empty() {
+                data.group_disabled.remove(&group_code);
+            }
+        })
+        .await
+    }
+
+    /// 被关闭的模块
+    pub fn disabled(&self) -> Vec<String> {
+        self.data.read().unwrap().disabled.iter().cloned().collect()
+    }
+
+    /// 在群中被关闭的功能
+    pub fn group_disabled(&self, group_code: i64) -> Vec<String> {
+        self.data
+            .read()
+            .unwrap()
+            .group_disabled
+            .get(&group_code)
+            .map(|disabled| disabled.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
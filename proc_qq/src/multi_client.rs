@@ -0,0 +1,104 @@
+use crate::{run_client, Client, ExtensionsTrait};
+use anyhow::Result;
+use futures::future::join_all;
+use std::future::Future;
+use std::sync::Arc;
+
+/// 账号的名称, 使用`ClientBuilder::bot_name`设置.
+/// 多个账号共享模块时, 可以在事件中用`event.extensions().get::<BotName>()`区分收到事件的账号
+#[derive(Debug, Clone)]
+pub struct BotName(pub String);
+
+/// 在同一个进程中运行多个账号
+///
+/// 每个账号使用各自的ClientBuilder构造 (黑白名单、扩展容器等不会共享),
+/// 模块可以通过`Vec<Arc<Module>>`共享.
+///
+/// ```ignore
+/// let modules: Vec<Arc<Module>> = vec![Arc::new(hello_module::module())];
+/// let a = ClientBuilder::new().bot_name("a").modules(modules.clone()) /* ... */ .build().await?;
+/// let b = ClientBuilder::new().bot_name("b").modules(modules.clone()) /* ... */ .build().await?;
+/// for result in MultiClient::new().client(a).client(b).run().await {
+///     result?;
+/// }
+/// ```
+#[derive(Default)]
+pub struct MultiClient {
+    clients: Vec<Arc<Client>>,
+}
+
+impl MultiClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client<C: Into<Arc<Client>>>(mut self, client: C) -> Self {
+        self.clients.push(client.into());
+        self
+    }
+
+    pub fn clients(&self) -> &[Arc<Client>] {
+        &self.clients
+    }
+
+    /// 按QQ号查找客户端 (登录后)
+    pub async fn find_by_uin(&self, uin: i64) -> Option<Arc<Client>> {
+        for client in &self.clients {
+            if client.rq_client.uin().await == uin {
+                return Some(client.clone());
+            }
+        }
+        None
+    }
+
+    /// 按`ClientBuilder::bot_name`设置的名称查找客户端
+    pub fn find_by_name(&self, name: &str) -> Option<Arc<Client>> {
+        self.clients
+            .iter()
+            .find(|client| {
+                client
+                    .extensions()
+                    .get::<BotName>()
+                    .map(|bot_name| bot_name.0 == name)
+                    .unwrap_or(false)
+            })
+            .cloned()
+    }
+
+    /// 同时运行所有账号 (同`run_client`), 一个账号退出时不影响其他账号.
+    /// 全部退出后按添加的顺序返回每个账号的结果
+    pub async fn run(&self) -> Vec<Result<()>> {
+        join_all(self.clients.iter().map(|client| {
+            let client = client.clone();
+            async move {
+                let result = run_client(client.clone()).await;
+                if let Err(err) = &result {
+                    tracing::error!("账号 {} 已停止 : {:?}", client.rq_client.uin().await, err);
+                }
+                result
+            }
+        }))
+        .await
+    }
+
+    /// 运行所有账号, signal完成时停止全部账号
+    pub async fn run_until_shutdown<F: Future<Output = ()>>(&self, signal: F) -> Vec<Result<()>> {
+        let (handles, stops): (Vec<_>, Vec<_>) = self
+            .clients
+            .iter()
+            .map(|client| client.clone().start_in_background())
+            .unzip();
+        signal.await;
+        for stop in &stops {
+            stop.shutdown();
+        }
+        let mut results = vec![];
+        for handle in join_all(handles).await {
+            results.push(match handle {
+                Ok(result) => result,
+                Err(err) => Err(err.into()),
+            });
+        }
+        results
+    }
+}
@@ -0,0 +1,94 @@
+use crate::ImageElement;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// 识别出的一段文字
+#[derive(Debug, Clone)]
+pub struct OcrText {
+    pub text: String,
+    /// 置信度 0-100
+    pub confidence: i32,
+}
+
+/// 文字识别的结果
+#[derive(Debug, Clone, Default)]
+pub struct OcrResult {
+    pub texts: Vec<OcrText>,
+    pub language: String,
+}
+
+impl OcrResult {
+    /// 所有文字, 每段一行
+    pub fn text(&self) -> String {
+        self.texts
+            .iter()
+            .map(|text| text.text.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.texts.iter().any(|text| text.text.contains(pattern))
+    }
+}
+
+/// 图片文字识别, 可以接入其他OCR服务
+#[async_trait]
+pub trait OcrProvider: Send + Sync {
+    async fn ocr(&self, image: &ImageElement) -> Result<OcrResult>;
+}
+
+/// 使用QQ自带的图片文字识别
+pub struct QQOcr {
+    client: Arc<ricq::Client>,
+}
+
+impl QQOcr {
+    pub fn new(client: Arc<ricq::Client>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl OcrProvider for QQOcr {
+    async fn ocr(&self, image: &ImageElement) -> Result<OcrResult> {
+        let md5 = image
+            .md5()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<String>();
+        let response = self
+            .client
+            .image_ocr(
+                image.url(),
+                md5,
+                image.size() as i32,
+                image.width() as i32,
+                image.height() as i32,
+            )
+            .await?;
+        Ok(OcrResult {
+            texts: response
+                .texts
+                .into_iter()
+                .map(|text| OcrText {
+                    text: text.text,
+                    confidence: text.confidence,
+                })
+                .collect(),
+            language: response.language,
+        })
+    }
+}
+
+impl ImageElement {
+    /// 识别图片中的文字
+    pub async fn ocr<P: OcrProvider + ?Sized>(&self, provider: &P) -> Result<OcrResult> {
+        provider.ocr(self).await
+    }
+}
@@ -0,0 +1,381 @@
+use crate::audit::record_config_change;
+use crate::persist::{load_json, save_json};
+use crate::{
+    ExtensionsTrait, MessageContentTrait, MessageEvent, MessageSendToSourceTrait, TextEleParseTrait,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::GroupMemberPermission;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::RwLock;
+
+/// 角色, 从低到高排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// 被封禁, 事件不会传递给模块
+    Banned,
+    /// 没有设置角色的用户
+    User,
+    Trusted,
+    Admin,
+    Master,
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "banned" => Ok(Role::Banned),
+            "user" => Ok(Role::User),
+            "trusted" => Ok(Role::Trusted),
+            "admin" => Ok(Role::Admin),
+            "master" => Ok(Role::Master),
+            _ => Err(anyhow!("未知的角色 : {}", s)),
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Role::Banned => "banned",
+            Role::User => "user",
+            Role::Trusted => "trusted",
+            Role::Admin => "admin",
+            Role::Master => "master",
+        })
+    }
+}
+
+/// 权限数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PermissionsData {
+    /// 全局角色
+    pub users: HashMap<i64, Role>,
+    /// 群内角色 群号 -> (QQ号 -> 角色)
+    pub groups: HashMap<i64, HashMap<i64, Role>>,
+}
+
+/// 角色权限
+///
+/// 主人(ClientBuilder::masters)始终为Master. 角色可以全局设置, 也可以只在某个群中设置,
+/// 任意一处为Banned时视为被封禁, 否则取较高的角色. 可以被`#[require(admin)]`等守卫使用,
+/// Admin以上的用户可以使用`/perm`指令进行管理.
+pub struct Permissions {
+    path: Option<String>,
+    masters: OnceLock<Arc<Vec<i64>>>,
+    data: RwLock<PermissionsData>,
+}
+
+impl Permissions {
+    /// 仅保存在内存中的权限
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            masters: OnceLock::new(),
+            data: RwLock::new(PermissionsData::default()),
+        }
+    }
+
+    /// 保存在文件中的权限, 构建客户端时加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    pub(crate) async fn load(&self, masters: Arc<Vec<i64>>) -> Result<()> {
+        let _ = self.masters.set(masters);
+        if let Some(path) = &self.path {
+            *self.data.write().await = load_json(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn modify<F: FnOnce(&mut PermissionsData)>(&self, f: F) -> Result<()> {
+        let mut data = self.data.write().await;
+        let mut modified = data.clone();
+        f(&mut modified);
+        if let Some(path) = &self.path {
+            save_json(path, &modified).await?;
+        }
+        *data = modified;
+        Ok(())
+    }
+
+    /// 当前数据的快照
+    pub async fn snapshot(&self) -> PermissionsData {
+        self.data.read().await.clone()
+    }
+
+    /// 设置角色, group_code为None时为全局角色
+    pub async fn set_role(&self, group_code: Option<i64>, uin: i64, role: Role) -> Result<()> {
+        self.modify(|data| {
+            match group_code {
+                Some(group_code) => data.groups.entry(group_code).or_default().insert(uin, role),
+                None => data.users.insert(uin, role),
+            };
+        })
+        .await
+    }
+
+    /// 移除设置的角色, group_code为None时为全局角色
+    pub async fn remove_role(&self, group_code: Option<i64>, uin: i64) -> Result<()> {
+        self.modify(|data| match group_code {
+            Some(group_code) => {
+                if let Some(users) = data.groups.get_mut(&group_code) {
+                    users.remove(&uin);
+                    if users.is_empty() {
+                        data.groups.remove(&group_code);
+                    }
+                }
+            }
+            None => {
+                data.users.remove(&uin);
+            }
+        })
+        .await
+    }
+
+    /// 用户在群(或私聊, group_code为None)中的角色
+    pub async fn role_of(&self, group_code: Option<i64>, uin: i64) -> Role {
+        if let Some(masters) = self.masters.get() {
+            if masters.contains(&uin) {
+                return Role::Master;
+            }
+        }
+        let data = self.data.read().await;
+        let global = data.users.get(&uin).copied();
+        let group = group_code
+            .and_then(|group_code| data.groups.get(&group_code))
+            .and_then(|users| users.get(&uin))
+            .copied();
+        if global == Some(Role::Banned) || group == Some(Role::Banned) {
+            return Role::Banned;
+        }
+        global.max(group).unwrap_or(Role::User)
+    }
+
+    pub async fn has_role(&self, group_code: Option<i64>, uin: i64, role: Role) -> bool {
+        self.role_of(group_code, uin).await >= role
+    }
+
+    /// 是否为主人: ClientBuilder::masters中的用户, 或者被设置为Master角色的用户 (在群中设置的只在该群有效).
+    /// 框架中需要判断主人的地方(限流、配额、内置模块的管理指令等)都使用这里的结果
+    pub async fn is_master(&self, group_code: Option<i64>, uin: i64) -> bool {
+        self.role_of(group_code, uin).await == Role::Master
+    }
+
+    /// 处理`/perm`指令, 返回是否为管理指令
+    pub(crate) async fn handle_command(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let mut sp = content.split_whitespace();
+        if sp.next() != Some("/perm") {
+            return Ok(false);
+        }
+        let (group_code, uin) = event.role_subject();
+        let operator = self.role_of(group_code, uin).await;
+        if operator < Role::Admin {
+            return Ok(false);
+        }
+        let args: Vec<&str> = sp.collect();
+        // here 表示只在当前群中生效
+        let (args, scope) = if group_code.is_some() && args.last() == Some(&"here") {
+            (&args[..args.len() - 1], group_code)
+        } else {
+            (&args[..], None)
+        };
+        let reply = match args {
+            ["get", target] => match target.parse::<i64>() {
+                Ok(target) => format!("{} : {}", target, self.role_of(group_code, target).await),
+                Err(_) => PERM_USAGE.to_owned(),
+            },
+            ["set", role, target] => match (role.parse::<Role>(), target.parse::<i64>()) {
+                (Ok(role), Ok(target)) => {
+                    // 管理员只能设置比自己低的角色, 也不能修改与自己同级的用户
+                    if operator < Role::Master
+                        && (role >= operator || self.role_of(scope, target).await >= operator)
+                    {
+                        "权限不足".to_owned()
+                    } else {
+                        self.set_role(scope, target, role).await?;
+                        "OK".to_owned()
+                    }
+                }
+                _ => PERM_USAGE.to_owned(),
+            },
+            ["unset", target] => match target.parse::<i64>() {
+                Ok(target) => {
+                    if operator < Role::Master && self.role_of(scope, target).await >= operator {
+                        "权限不足".to_owned()
+                    } else {
+                        self.remove_role(scope, target).await?;
+                        "OK".to_owned()
+                    }
+                }
+                Err(_) => PERM_USAGE.to_owned(),
+            },
+            _ => PERM_USAGE.to_owned(),
+        };
+        if reply == "OK" {
+            record_config_change(event).await;
+        }
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+}
+
+static PERM_USAGE: &str = "/perm get QQ号\n\
+/perm set master|admin|trusted|banned QQ号 [here]\n\
+/perm unset QQ号 [here]\n\
+here 表示只在当前群中生效";
+
+/// 可以判断角色的事件, 返回(群号, 发送者)
+pub trait RoleSubjectTrait {
+    fn role_subject(&self) -> (Option<i64>, i64);
+    fn role_client(&self) -> Arc<ricq::Client>;
+}
+
+impl RoleSubjectTrait for GroupMessageEvent {
+    fn role_subject(&self) -> (Option<i64>, i64) {
+        (Some(self.inner.group_code), self.inner.from_uin)
+    }
+
+    fn role_client(&self) -> Arc<ricq::Client> {
+        self.client.clone()
+    }
+}
+
+impl RoleSubjectTrait for FriendMessageEvent {
+    fn role_subject(&self) -> (Option<i64>, i64) {
+        (None, self.inner.from_uin)
+    }
+
+    fn role_client(&self) -> Arc<ricq::Client> {
+        self.client.clone()
+    }
+}
+
+impl RoleSubjectTrait for GroupTempMessageEvent {
+    fn role_subject(&self) -> (Option<i64>, i64) {
+        (Some(self.inner.group_code), self.inner.from_uin)
+    }
+
+    fn role_client(&self) -> Arc<ricq::Client> {
+        self.client.clone()
+    }
+}
+
+impl RoleSubjectTrait for MessageEvent {
+    fn role_subject(&self) -> (Option<i64>, i64) {
+        match self {
+            MessageEvent::GroupMessage(e) => e.role_subject(),
+            MessageEvent::FriendMessage(e) => e.role_subject(),
+            MessageEvent::GroupTempMessage(e) => e.role_subject(),
+        }
+    }
+
+    fn role_client(&self) -> Arc<ricq::Client> {
+        self.client()
+    }
+}
+
+/// 事件的发送者是否为主人, 见`Permissions::is_master`
+pub async fn sender_is_master<E: RoleSubjectTrait>(event: &E) -> bool {
+    let (group_code, uin) = event.role_subject();
+    match event.role_client().extensions().get::<Permissions>() {
+        Some(permissions) => permissions.is_master(group_code, uin).await,
+        None => false,
+    }
+}
+
+/// `#[require(...)]` 生成的守卫, 发送者的角色不低于role时返回true
+pub async fn require_role<E: RoleSubjectTrait>(event: &E, role: Role) -> Result<bool> {
+    let (group_code, uin) = event.role_subject();
+    let permissions = event
+        .role_client()
+        .extensions()
+        .get::<Permissions>()
+        .ok_or_else(|| anyhow!("没有找到Permissions, 请使用ClientBuilder构造客户端"))?;
+    Ok(permissions.has_role(group_code, uin, role).await)
+}
+
+/// 自定义权限, 用于`#[require("权限名称")]`, 例如从数据库中读取的会员列表.
+/// 使用`ClientBuilder::permission_provider`设置
+///
+/// ```ignore
+/// struct Vip;
+///
+/// #[async_trait]
+/// impl PermissionProvider for Vip {
+///     async fn check(&self, _client: &ricq::Client, _group_code: Option<i64>, uin: i64, permission: &str) -> Result<Option<bool>> {
+///         match permission {
+///             "vip" => Ok(Some(vip_list().await?.contains(&uin))),
+///             _ => Ok(None),
+///         }
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait PermissionProvider: Send + Sync {
+    /// 判断用户是否拥有权限, 不认识的权限返回None
+    async fn check(
+        &self,
+        client: &ricq::Client,
+        group_code: Option<i64>,
+        uin: i64,
+        permission: &str,
+    ) -> Result<Option<bool>>;
+}
+
+/// `#[require(group_admin)]` `#[require("vip")]` 生成的守卫
+///
+/// - `group_admin` 群主、群管理员, 或admin以上的角色
+/// - `group_owner` 群主, 或master
+/// - 其他名称交给PermissionProvider判断
+pub async fn require_permission<E: RoleSubjectTrait>(event: &E, permission: &str) -> Result<bool> {
+    let (group_code, uin) = event.role_subject();
+    let client = event.role_client();
+    let (role, group_permission) = match permission {
+        "group_admin" => (Role::Admin, GroupMemberPermission::Administrator),
+        "group_owner" => (Role::Master, GroupMemberPermission::Owner),
+        _ => {
+            let provider = client
+                .extensions()
+                .get::<Box<dyn PermissionProvider>>()
+                .ok_or_else(|| {
+                    anyhow!("未设置PermissionProvider, 无法判断权限 : {}", permission)
+                })?;
+            return provider
+                .check(&client, group_code, uin, permission)
+                .await?
+                .ok_or_else(|| anyhow!("未知的权限 : {}", permission));
+        }
+    };
+    if require_role(event, role).await? {
+        return Ok(true);
+    }
+    let group_code = match group_code {
+        Some(group_code) => group_code,
+        None => return Ok(false),
+    };
+    let info = client.get_group_member_info(group_code, uin).await?;
+    Ok(match info.permission {
+        GroupMemberPermission::Owner => true,
+        GroupMemberPermission::Administrator => {
+            matches!(group_permission, GroupMemberPermission::Administrator)
+        }
+        GroupMemberPermission::Member => false,
+    })
+}
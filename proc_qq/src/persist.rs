@@ -0,0 +1,24 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+/// 从json文件读取数据, 文件不存在时返回默认值
+pub(crate) async fn load_json<T: DeserializeOwned + Default>(path: &str) -> Result<T> {
+    if !Path::new(path).exists() {
+        return Ok(T::default());
+    }
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("读取文件失败 : {}", path))?;
+    serde_json::from_str(&text).with_context(|| format!("解析文件失败 : {}", path))
+}
+
+/// 将数据以json格式写入文件
+pub(crate) async fn save_json<T: Serialize>(path: &str, value: &T) -> Result<()> {
+    let text = serde_json::to_string(value)?;
+    tokio::fs::write(path, text)
+        .await
+        .with_context(|| format!("写入文件失败 : {}", path))?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+/// 插件接口版本, 插件的入口或`Module`的结构发生不兼容的变化时增加
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+#[doc(hidden)]
+pub const PROC_QQ_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// 将一个返回`Module`的函数导出为动态库插件的入口
+///
+/// 插件的crate-type需要为`cdylib`, 由开启了`plugin_host`特性的宿主程序通过`PluginHost`加载.
+/// 插件与宿主需要使用相同版本的proc_qq和rustc编译.
+///
+/// ```ignore
+/// fn module() -> Module {
+///     module!("hello", "你好", on_message)
+/// }
+///
+/// proc_qq::export_plugin!(module);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($module:path) => {
+        #[no_mangle]
+        pub extern "C" fn proc_qq_plugin_api_version() -> u32 {
+            $crate::PLUGIN_API_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn proc_qq_plugin_version() -> *const ::std::os::raw::c_char {
+            $crate::PROC_QQ_VERSION.as_ptr() as *const ::std::os::raw::c_char
+        }
+
+        #[no_mangle]
+        pub fn proc_qq_plugin() -> $crate::Module {
+            $module()
+        }
+    };
+}
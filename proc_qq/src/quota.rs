@@ -0,0 +1,219 @@
+use crate::audit::record_config_change;
+use crate::persist::{load_json, save_json};
+use crate::{
+    sender_is_master, ExtensionsTrait, MessageContentTrait, MessageEvent, MessageSendToSourceTrait,
+    RoleSubjectTrait, TextEleParseTrait,
+};
+use anyhow::{anyhow, Result};
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct QuotaCounter {
+    /// 周期的序号, 与当前不同时计数重置
+    window: i64,
+    count: u32,
+}
+
+/// 指令的使用配额
+///
+/// 在事件上使用`#[quota(per_user = 5, period = "1d")]`限制每个用户在一个周期内的使用次数,
+/// 周期按照本地时间对齐 (1d为每天0点重置). 主人不受配额限制, 并且可以使用`/quota`指令重置配额.
+pub struct Quotas {
+    path: Option<String>,
+    exceeded_reply: String,
+    // "名称:QQ号" -> 计数
+    counters: RwLock<HashMap<String, QuotaCounter>>,
+}
+
+impl Quotas {
+    /// 仅保存在内存中的配额
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            exceeded_reply: "使用次数已达上限 ({limit}次), 请稍后再试".to_owned(),
+            counters: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 保存在文件中的配额, 构建客户端时加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    /// 超出配额时的回复, `{limit}`会被替换为次数上限
+    pub fn exceeded_reply(mut self, reply: impl Into<String>) -> Self {
+        self.exceeded_reply = reply.into();
+        self
+    }
+
+    pub(crate) async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            *self.counters.write().await = load_json(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn save(&self, counters: &HashMap<String, QuotaCounter>) -> Result<()> {
+        if let Some(path) = &self.path {
+            save_json(path, counters).await?;
+        }
+        Ok(())
+    }
+
+    fn window(period: u64) -> i64 {
+        let now = chrono::Local::now();
+        let local = now.timestamp() + now.offset().local_minus_utc() as i64;
+        local / period.max(1) as i64
+    }
+
+    /// 使用一次配额, 超出配额时返回false
+    pub async fn try_consume(
+        &self,
+        name: &str,
+        uin: i64,
+        per_user: u32,
+        period: u64,
+    ) -> Result<bool> {
+        let window = Self::window(period);
+        let mut counters = self.counters.write().await;
+        let counter = counters
+            .entry(format!("{}:{}", name, uin))
+            .or_insert_with(QuotaCounter::default);
+        if counter.window != window {
+            *counter = QuotaCounter { window, count: 0 };
+        }
+        if counter.count >= per_user {
+            return Ok(false);
+        }
+        counter.count += 1;
+        self.save(&counters).await?;
+        Ok(true)
+    }
+
+    /// 当前周期内剩余的次数
+    pub async fn remaining(&self, name: &str, uin: i64, per_user: u32, period: u64) -> u32 {
+        let window = Self::window(period);
+        match self.counters.read().await.get(&format!("{}:{}", name, uin)) {
+            Some(counter) if counter.window == window => per_user.saturating_sub(counter.count),
+            _ => per_user,
+        }
+    }
+
+    /// 重置配额, uin和name为None时匹配所有, 返回重置的计数个数
+    pub async fn reset(&self, uin: Option<i64>, name: Option<&str>) -> Result<usize> {
+        let mut counters = self.counters.write().await;
+        let len = counters.len();
+        counters.retain(|key, _| {
+            let (key_name, key_uin) = key.rsplit_once(':').unwrap_or((key.as_str(), ""));
+            let uin_match = uin.map(|uin| key_uin == uin.to_string()).unwrap_or(true);
+            let name_match = name
+                .map(|name| key_name == name || key_name.ends_with(&format!("::{}", name)))
+                .unwrap_or(true);
+            !(uin_match && name_match)
+        });
+        let removed = len - counters.len();
+        if removed > 0 {
+            self.save(&counters).await?;
+        }
+        Ok(removed)
+    }
+
+    /// 某个用户当前周期内使用过的配额 (名称, 次数)
+    pub async fn usage(&self, uin: i64) -> Vec<(String, u32)> {
+        let suffix = format!(":{}", uin);
+        self.counters
+            .read()
+            .await
+            .iter()
+            .filter_map(|(key, counter)| {
+                key.strip_suffix(&suffix)
+                    .map(|name| (name.to_owned(), counter.count))
+            })
+            .collect()
+    }
+
+    /// 处理主人发出的管理指令, 返回是否为管理指令
+    pub(crate) async fn handle_command(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let mut sp = content.split_whitespace();
+        if sp.next() != Some("/quota") {
+            return Ok(false);
+        }
+        let args: Vec<&str> = sp.collect();
+        let reply = match args.as_slice() {
+            ["get", uin] => match uin.parse::<i64>() {
+                Ok(uin) => {
+                    let usage = self.usage(uin).await;
+                    if usage.is_empty() {
+                        "没有使用记录".to_owned()
+                    } else {
+                        usage
+                            .iter()
+                            .map(|(name, count)| format!("{} : {}", name, count))
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    }
+                }
+                Err(_) => QUOTA_USAGE.to_owned(),
+            },
+            ["reset", target, rest @ ..] if rest.len() <= 1 => {
+                let uin = match *target {
+                    "all" => Ok(None),
+                    uin => uin.parse::<i64>().map(Some),
+                };
+                match uin {
+                    Ok(uin) => {
+                        let count = self.reset(uin, rest.first().copied()).await?;
+                        format!("已重置 {} 项配额", count)
+                    }
+                    Err(_) => QUOTA_USAGE.to_owned(),
+                }
+            }
+            _ => QUOTA_USAGE.to_owned(),
+        };
+        if reply.starts_with("已") {
+            record_config_change(event).await;
+        }
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+}
+
+static QUOTA_USAGE: &str = "/quota get QQ号\n\
+/quota reset QQ号|all [指令名称]";
+
+/// `#[quota(...)]` 生成的守卫, 使用一次配额, 超出配额时回复提示并返回false
+pub async fn check_quota<E: RoleSubjectTrait + MessageSendToSourceTrait>(
+    event: &E,
+    name: &str,
+    per_user: u32,
+    period: u64,
+) -> Result<bool> {
+    let (_, uin) = event.role_subject();
+    if sender_is_master(event).await {
+        return Ok(true);
+    }
+    let client = event.role_client();
+    let extensions = client.extensions();
+    let quotas = extensions
+        .get::<Quotas>()
+        .ok_or_else(|| anyhow!("没有找到Quotas, 请使用ClientBuilder构造客户端"))?;
+    if quotas.try_consume(name, uin, per_user, period).await? {
+        return Ok(true);
+    }
+    let reply = quotas
+        .exceeded_reply
+        .replace("{limit}", &per_user.to_string());
+    event
+        .send_message_to_source(MessageChain::new(reply.parse_text()))
+        .await?;
+    Ok(false)
+}
@@ -0,0 +1,325 @@
+use crate::{sender_is_master, ExtensionsTrait, MessageTarget, RoleSubjectTrait};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 限流器, 在period内每个key最多通过limit次 (滑动窗口)
+///
+/// 在事件上使用`#[event(rate_limit = "5/60s", scope = "per_sender")]`时由框架自动创建,
+/// 也可以直接使用
+///
+/// ```ignore
+/// let limiter = RateLimiter::parse("3/10s")?;
+/// if !limiter.try_acquire(&uin.to_string()) {
+///     return Ok(true);
+/// }
+/// ```
+pub struct RateLimiter {
+    limit: u32,
+    period: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, period: Duration) -> Self {
+        Self {
+            limit: limit.max(1),
+            period,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 解析"次数/周期"的格式, 例如 5/60s 20/m 100/1d, 周期的单位为s/m/h/d/w
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (limit, period) = parse_rate_limit(spec)
+            .ok_or_else(|| anyhow!("限流的格式为 次数/周期, 例如 5/60s : {}", spec))?;
+        Ok(Self::new(limit, Duration::from_secs(period)))
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// 占用一次, 超出限制时返回需要等待的时间
+    fn acquire_or_wait(&self, key: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        // 清理不再活跃的key
+        if hits.len() > 4096 {
+            let period = self.period;
+            hits.retain(|_, queue| {
+                queue
+                    .back()
+                    .map(|last| now.duration_since(*last) < period)
+                    .unwrap_or(false)
+            });
+        }
+        let queue = hits.entry(key.to_owned()).or_default();
+        while let Some(first) = queue.front() {
+            if now.duration_since(*first) < self.period {
+                break;
+            }
+            queue.pop_front();
+        }
+        if queue.len() < self.limit as usize {
+            queue.push_back(now);
+            return None;
+        }
+        queue
+            .front()
+            .map(|first| self.period.saturating_sub(now.duration_since(*first)))
+    }
+
+    /// 尝试占用一次, 超出限制时返回false
+    pub fn try_acquire(&self, key: &str) -> bool {
+        self.acquire_or_wait(key).is_none()
+    }
+
+    /// 占用一次, 超出限制时等待到可以通过为止
+    pub async fn acquire(&self, key: &str) {
+        while let Some(wait) = self.acquire_or_wait(key) {
+            tokio::time::sleep(wait.max(Duration::from_millis(10))).await;
+        }
+    }
+
+    /// 清除key的记录
+    pub fn reset(&self, key: &str) {
+        self.hits.lock().unwrap().remove(key);
+    }
+}
+
+/// 解析"次数/周期"为(次数, 周期的秒数), 格式错误或溢出时返回None
+///
+/// `RateLimiter::parse`和`#[event(rate_limit = "5/60s")]`共用, 宏生成的代码在编译期调用,
+/// 格式错误时编译失败. 周期的单位为s/m/h/d/w, 数字为1时可以省略.
+#[doc(hidden)]
+pub const fn parse_rate_limit(spec: &str) -> Option<(u32, u64)> {
+    let bytes = spec.as_bytes();
+    let (start, end) = trim(bytes, 0, bytes.len());
+    let mut slash = start;
+    while slash < end && bytes[slash] != b'/' {
+        slash += 1;
+    }
+    if slash == end {
+        return None;
+    }
+    let (limit_start, limit_end) = trim(bytes, start, slash);
+    let limit = match parse_number(bytes, limit_start, limit_end) {
+        Some(limit) if limit > 0 && limit <= u32::MAX as u64 => limit as u32,
+        _ => return None,
+    };
+    let (period_start, period_end) = trim(bytes, slash + 1, end);
+    if period_start == period_end {
+        return None;
+    }
+    // 单位只能是一个ASCII字符, 例如"60秒"的最后一个字节不是单位, 返回None
+    let unit = match bytes[period_end - 1] {
+        b's' => 1,
+        b'm' => 60,
+        b'h' => 3600,
+        b'd' => 86400,
+        b'w' => 604800,
+        _ => return None,
+    };
+    let number = if period_start == period_end - 1 {
+        1
+    } else {
+        match parse_number(bytes, period_start, period_end - 1) {
+            Some(number) => number,
+            None => return None,
+        }
+    };
+    match number.checked_mul(unit) {
+        Some(0) | None => None,
+        Some(secs) => Some((limit, secs)),
+    }
+}
+
+const fn trim(bytes: &[u8], mut start: usize, mut end: usize) -> (usize, usize) {
+    while start < end && bytes[start].is_ascii_whitespace() {
+        start += 1;
+    }
+    while end > start && bytes[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+    (start, end)
+}
+
+const fn parse_number(bytes: &[u8], start: usize, end: usize) -> Option<u64> {
+    if start == end {
+        return None;
+    }
+    let mut number: u64 = 0;
+    let mut i = start;
+    while i < end {
+        if !bytes[i].is_ascii_digit() {
+            return None;
+        }
+        number = match number.checked_mul(10) {
+            Some(number) => match number.checked_add((bytes[i] - b'0') as u64) {
+                Some(number) => number,
+                None => return None,
+            },
+            None => return None,
+        };
+        i += 1;
+    }
+    Some(number)
+}
+
+/// 限流的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitScope {
+    /// 每个发送者分别计数
+    PerSender,
+    /// 每个群分别计数, 私聊按照发送者计数
+    PerGroup,
+    /// 所有消息一起计数
+    Global,
+}
+
+impl FromStr for RateLimitScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "per_sender" => Ok(RateLimitScope::PerSender),
+            "per_group" => Ok(RateLimitScope::PerGroup),
+            "global" => Ok(RateLimitScope::Global),
+            _ => Err(anyhow!(
+                "限流的范围只能为 per_sender per_group global : {}",
+                s
+            )),
+        }
+    }
+}
+
+/// 处理器使用的限流器, 按照处理器的名称区分
+#[derive(Default)]
+struct RateLimits(Mutex<HashMap<String, Arc<RateLimiter>>>);
+
+impl RateLimits {
+    fn limiter(&self, name: &str, limit: u32, period: u64) -> Arc<RateLimiter> {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(name.to_owned())
+            .or_insert_with(|| Arc::new(RateLimiter::new(limit, Duration::from_secs(period))))
+            .clone()
+    }
+}
+
+/// `#[event(rate_limit = "...")]` 生成的守卫, 超出限制时返回false (消息被丢弃),
+/// queue为true时等待到可以处理为止. 主人不受限制
+pub async fn check_rate_limit<E: RoleSubjectTrait>(
+    event: &E,
+    name: &str,
+    limit: u32,
+    period: u64,
+    scope: RateLimitScope,
+    queue: bool,
+) -> bool {
+    let (group_code, uin) = event.role_subject();
+    if sender_is_master(event).await {
+        return true;
+    }
+    let client = event.role_client();
+    let extensions = client.extensions();
+    let key = match scope {
+        RateLimitScope::PerSender => uin.to_string(),
+        RateLimitScope::PerGroup => match group_code {
+            Some(group_code) => format!("group:{}", group_code),
+            None => uin.to_string(),
+        },
+        RateLimitScope::Global => String::new(),
+    };
+    let limiter = extensions
+        .get_or_insert_with(RateLimits::default)
+        .limiter(name, limit, period);
+    if queue {
+        limiter.acquire(&key).await;
+        return true;
+    }
+    if limiter.try_acquire(&key) {
+        return true;
+    }
+    tracing::debug!("超出限流, 丢弃消息 : {} ({})", name, key);
+    false
+}
+
+/// 发送消息的限流, 超出限制时等待后再发送, 减少被风控的可能.
+/// 使用`ClientBuilder::send_throttle`设置
+///
+/// ```ignore
+/// ClientBuilder::new().send_throttle(
+///     SendThrottle::new()
+///         .global(RateLimiter::parse("20/60s")?)
+///         .per_target(RateLimiter::parse("5/10s")?),
+/// )
+/// ```
+#[derive(Default)]
+pub struct SendThrottle {
+    global: Option<RateLimiter>,
+    per_target: Option<RateLimiter>,
+}
+
+impl SendThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 所有消息一起计数
+    pub fn global(mut self, limiter: RateLimiter) -> Self {
+        self.global = Some(limiter);
+        self
+    }
+
+    /// 每个群或好友分别计数
+    pub fn per_target(mut self, limiter: RateLimiter) -> Self {
+        self.per_target = Some(limiter);
+        self
+    }
+
+    pub(crate) async fn wait(&self, target: MessageTarget) {
+        if let Some(limiter) = &self.per_target {
+            let key = match target {
+                MessageTarget::Group(group_code, _) => format!("group:{}", group_code),
+                MessageTarget::Private(uin) => format!("private:{}", uin),
+                MessageTarget::GroupTemp(group_code, uin) => format!("temp:{}:{}", group_code, uin),
+            };
+            limiter.acquire(&key).await;
+        }
+        if let Some(limiter) = &self.global {
+            limiter.acquire("").await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_rate_limit("5/60s"), Some((5, 60)));
+        assert_eq!(parse_rate_limit(" 20 / m "), Some((20, 60)));
+        assert_eq!(parse_rate_limit("100/1d"), Some((100, 86400)));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(parse_rate_limit("5/60秒"), None);
+        assert_eq!(parse_rate_limit("5/秒"), None);
+        assert_eq!(parse_rate_limit("0/60s"), None);
+        assert_eq!(parse_rate_limit("5/0s"), None);
+        assert_eq!(parse_rate_limit("5"), None);
+        assert_eq!(parse_rate_limit("5/99999999999999999999w"), None);
+        assert_eq!(parse_rate_limit("99999999999/1s"), None);
+    }
+}
@@ -1,8 +1,15 @@
 pub mod anyhow;
 pub mod async_trait;
 pub mod bytes;
-#[cfg(feature = "console_qr")]
+#[cfg(any(
+    feature = "console_qr",
+    feature = "charts",
+    feature = "image_preprocess",
+    feature = "image_convert"
+))]
 pub mod image;
+#[cfg(feature = "charts")]
+pub mod plotters;
 pub mod qr2term;
 pub mod regex;
 pub mod reqwest;
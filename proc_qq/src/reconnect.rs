@@ -0,0 +1,153 @@
+use crate::Authentication;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 断线重连策略, 使用`ClientBuilder::reconnect_policy`设置
+///
+/// 默认不限重连次数, 等待时间从1秒开始每次翻倍, 最多60秒.
+/// 无法恢复会话时(token失效), 默认使用账号密码重新登录, 不会重新扫码.
+///
+/// ```ignore
+/// ClientBuilder::new().reconnect_policy(
+///     ReconnectPolicy::new()
+///         .max_retries(20)
+///         .backoff(Duration::from_secs(2), Duration::from_secs(300))
+///         .fallback_to_qr(true),
+/// )
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    max_retries: Option<u32>,
+    initial_delay: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    fallback_to_password: bool,
+    fallback_to_qr: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: None,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            fallback_to_password: true,
+            fallback_to_qr: false,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 连续重连失败的最大次数, 超过后`run_client`返回错误
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// 第一次重连前的等待时间和最长的等待时间
+    pub fn backoff(mut self, initial_delay: Duration, max_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// 每次重连失败后等待时间的倍数, 为1时每次等待相同的时间
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// 无法恢复会话时是否使用账号密码重新登录
+    pub fn fallback_to_password(mut self, fallback: bool) -> Self {
+        self.fallback_to_password = fallback;
+        self
+    }
+
+    /// 无法恢复会话时是否重新扫码登录 (需要有人扫码)
+    pub fn fallback_to_qr(mut self, fallback: bool) -> Self {
+        self.fallback_to_qr = fallback;
+        self
+    }
+
+    /// 第attempt次(从1开始)重连前等待的时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .powi(attempt.saturating_sub(1).min(i32::MAX as u32) as i32);
+        self.initial_delay.mul_f64(factor).min(self.max_delay)
+    }
+
+    pub(crate) fn exhausted(&self, attempt: u32) -> bool {
+        matches!(self.max_retries, Some(max_retries) if attempt > max_retries)
+    }
+
+    /// 无法恢复会话时能否使用设置的登录方式重新登录
+    pub(crate) fn can_relogin(&self, authentication: &Authentication) -> bool {
+        match authentication {
+            Authentication::QRCode => self.fallback_to_qr,
+            Authentication::Abandon => false,
+            _ => self.fallback_to_password,
+        }
+    }
+}
+
+/// 重连成功的信息
+#[derive(Debug, Clone)]
+pub struct ReconnectedInfo {
+    /// 重连的次数
+    pub attempts: u32,
+    /// 是否使用token恢复了会话, 为false时为重新登录
+    pub resumed_session: bool,
+}
+
+type DisconnectHook =
+    Arc<dyn Fn(Arc<ricq::Client>, String) -> BoxFuture<'static, ()> + Send + Sync>;
+type ReconnectedHook =
+    Arc<dyn Fn(Arc<ricq::Client>, ReconnectedInfo) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// `ClientBuilder::on_disconnect`和`on_reconnected`设置的回调
+#[derive(Clone, Default)]
+pub(crate) struct ReconnectHooks {
+    pub(crate) on_disconnect: Vec<DisconnectHook>,
+    pub(crate) on_reconnected: Vec<ReconnectedHook>,
+}
+
+impl ReconnectHooks {
+    pub(crate) fn push_disconnect<F, Fut>(&mut self, hook: F)
+    where
+        F: Fn(Arc<ricq::Client>, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_disconnect.push(Arc::new(move |client, reason| {
+            Box::pin(hook(client, reason))
+        }));
+    }
+
+    pub(crate) fn push_reconnected<F, Fut>(&mut self, hook: F)
+    where
+        F: Fn(Arc<ricq::Client>, ReconnectedInfo) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_reconnected
+            .push(Arc::new(move |client, info| Box::pin(hook(client, info))));
+    }
+
+    pub(crate) async fn disconnected(&self, client: &Arc<ricq::Client>, reason: &str) {
+        for hook in &self.on_disconnect {
+            hook(client.clone(), reason.to_owned()).await;
+        }
+    }
+
+    pub(crate) async fn reconnected(&self, client: &Arc<ricq::Client>, info: &ReconnectedInfo) {
+        for hook in &self.on_reconnected {
+            hook(client.clone(), info.clone()).await;
+        }
+    }
+}
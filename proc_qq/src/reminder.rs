@@ -0,0 +1,363 @@
+use crate::persist::{load_json, save_json};
+use crate::{
+    DelayedSender, ExtensionsTrait, MessageChainAppendTrait, MessageTarget, TextEleParseTrait,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use regex::{Captures, Regex};
+use ricq_core::msg::elem::At;
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Mutex;
+
+/// 提醒事项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    /// 与延时发送的id相同
+    pub id: u64,
+    /// 设置提醒的用户
+    pub uin: i64,
+    pub target: MessageTarget,
+    /// 提醒时间 (unix毫秒)
+    pub remind_at: i64,
+    pub content: String,
+}
+
+/// 提醒服务
+///
+/// 提醒通过扩展容器中的`DelayedSender`发送, 需要重启后继续提醒时,
+/// 请同时使用`DelayedSender::file`和`Reminders::file`.
+pub struct Reminders {
+    path: Option<String>,
+    items: Mutex<HashMap<u64, Reminder>>,
+}
+
+impl Reminders {
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            items: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 保存在文件中的提醒, 需要调用`load`加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            items: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn load(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let list: Vec<Reminder> = load_json(path).await?;
+            let mut items = self.items.lock().await;
+            for item in list {
+                items.insert(item.id, item);
+            }
+        }
+        Ok(())
+    }
+
+    async fn save(&self, items: &HashMap<u64, Reminder>) -> Result<()> {
+        if let Some(path) = &self.path {
+            let list: Vec<&Reminder> = items.values().collect();
+            save_json(path, &list).await?;
+        }
+        Ok(())
+    }
+
+    /// 设置提醒, 群中的提醒会At设置提醒的用户
+    pub async fn remind<Tz: TimeZone>(
+        &self,
+        client: &Arc<ricq::Client>,
+        target: MessageTarget,
+        uin: i64,
+        time: DateTime<Tz>,
+        content: impl Into<String>,
+    ) -> Result<Reminder> {
+        let remind_at = time.timestamp_millis();
+        if remind_at <= Utc::now().timestamp_millis() {
+            return Err(anyhow!("提醒时间已经过去"));
+        }
+        let content = content.into();
+        let sender = client
+            .extensions()
+            .get::<DelayedSender>()
+            .with_context(|| "没有找到DelayedSender, 请使用ClientBuilder构造客户端")?;
+        let text = format!("提醒 : {}", content).parse_text();
+        let chain = match target {
+            MessageTarget::Group(_, _) => MessageChain::default()
+                .append(At::new(uin))
+                .append(" ".parse_text())
+                .append(text),
+            _ => MessageChain::new(text),
+        };
+        let id = sender.send_at(client.clone(), target, chain, time).await?;
+        let reminder = Reminder {
+            id,
+            uin,
+            target,
+            remind_at,
+            content,
+        };
+        let mut items = self.items.lock().await;
+        items.insert(id, reminder.clone());
+        self.save(&items).await?;
+        Ok(reminder)
+    }
+
+    /// 用户还未到时间的提醒, 按时间排序
+    pub async fn list(&self, uin: i64) -> Result<Vec<Reminder>> {
+        let now = Utc::now().timestamp_millis();
+        let mut items = self.items.lock().await;
+        let len = items.len();
+        items.retain(|_, item| item.remind_at > now);
+        if items.len() != len {
+            self.save(&items).await?;
+        }
+        let mut list: Vec<Reminder> = items
+            .values()
+            .filter(|item| item.uin == uin)
+            .cloned()
+            .collect();
+        list.sort_by_key(|item| item.remind_at);
+        Ok(list)
+    }
+
+    /// 取消提醒, 只能取消自己设置的提醒
+    pub async fn cancel(&self, client: &Arc<ricq::Client>, uin: i64, id: u64) -> Result<bool> {
+        let mut items = self.items.lock().await;
+        match items.get(&id) {
+            Some(item) if item.uin == uin => (),
+            _ => return Ok(false),
+        }
+        items.remove(&id);
+        self.save(&items).await?;
+        if let Some(sender) = client.extensions().get::<DelayedSender>() {
+            sender.cancel(id).await?;
+        }
+        Ok(true)
+    }
+}
+
+fn cached_regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// 从文字开头解析提醒时间, 返回时间和剩余的文字
+///
+/// 支持 `10分钟后` `半小时后` `明天 9点` `下午3点半` `20:30` `in 10 minutes` `tomorrow 9am` `at 21:30`,
+/// 没有指定日期且时间已经过去时, 视为第二天.
+pub fn parse_remind_time(text: &str, now: DateTime<Local>) -> Option<(DateTime<Local>, String)> {
+    static ZH_RELATIVE: OnceLock<Regex> = OnceLock::new();
+    static EN_RELATIVE: OnceLock<Regex> = OnceLock::new();
+    static ZH_ABSOLUTE: OnceLock<Regex> = OnceLock::new();
+    static EN_ABSOLUTE: OnceLock<Regex> = OnceLock::new();
+    let text = text.trim_start();
+
+    let zh_relative = cached_regex(
+        &ZH_RELATIVE,
+        r"^(\d+|半|一|两)\s*(秒钟?|分钟?|个?小时|个?钟头|天)(?:之|以)?后",
+    );
+    if let Some(caps) = zh_relative.captures(text) {
+        let unit = match &caps[2] {
+            u if u.starts_with('秒') => 1,
+            u if u.starts_with('分') => 60,
+            "天" => 60 * 60 * 24,
+            _ => 60 * 60,
+        };
+        let seconds = match &caps[1] {
+            "半" => unit / 2,
+            "一" => unit,
+            "两" => unit * 2,
+            n => n.parse::<i64>().ok()?.checked_mul(unit)?,
+        };
+        let time = now.checked_add_signed(Duration::try_seconds(seconds)?)?;
+        return Some((time, rest(text, &caps)));
+    }
+
+    let en_relative = cached_regex(
+        &EN_RELATIVE,
+        r"(?i)^in\s+(\d+|an?)\s*(seconds?|secs?|s|minutes?|mins?|m|hours?|hrs?|h|days?|d)\b",
+    );
+    if let Some(caps) = en_relative.captures(text) {
+        let unit = match caps[2].to_lowercase().chars().next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            _ => 60 * 60 * 24,
+        };
+        let amount = match caps[1].parse::<i64>() {
+            Ok(amount) => amount,
+            Err(_) => 1,
+        };
+        let time = now.checked_add_signed(Duration::try_seconds(amount.checked_mul(unit)?)?)?;
+        return Some((time, rest(text, &caps)));
+    }
+
+    let zh_absolute = cached_regex(
+        &ZH_ABSOLUTE,
+        r"^(今天|明天|后天)?\s*(凌晨|早上|早晨|上午|中午|下午|傍晚|晚上)?\s*(?:(\d{1,2})\s*(?:[点时]\s*(半|\d{1,2})?\s*分?|[:：]\s*(\d{1,2})))?",
+    );
+    if let Some(caps) = zh_absolute.captures(text) {
+        if caps.get(1).is_some() || caps.get(3).is_some() {
+            let days = match caps.get(1).map(|m| m.as_str()) {
+                Some("明天") => 1,
+                Some("后天") => 2,
+                _ => 0,
+            };
+            let (hour, minute) = match caps.get(3) {
+                Some(hour) => {
+                    let minute = match caps.get(4).or(caps.get(5)).map(|m| m.as_str()) {
+                        Some("半") => 30,
+                        Some(minute) => minute.parse().ok()?,
+                        None => 0,
+                    };
+                    (hour.as_str().parse().ok()?, minute)
+                }
+                None => (9, 0),
+            };
+            let afternoon = match caps.get(2).map(|m| m.as_str()) {
+                Some("下午") | Some("傍晚") | Some("晚上") => true,
+                Some("中午") => hour < 11,
+                _ => false,
+            };
+            let hour = if afternoon && hour < 12 {
+                hour + 12
+            } else {
+                hour
+            };
+            let time = at_time(now, days, hour, minute, caps.get(1).is_none())?;
+            return Some((time, rest(text, &caps)));
+        }
+    }
+
+    let en_absolute = cached_regex(
+        &EN_ABSOLUTE,
+        r"(?i)^(today|tomorrow)?\s*(at\s+)?(?:(\d{1,2})(?::(\d{2}))?\s*(am|pm)?\b)?",
+    );
+    if let Some(caps) = en_absolute.captures(text) {
+        let has_day = caps.get(1).is_some();
+        // 单独的数字不视为时间
+        let has_time = caps.get(3).is_some()
+            && (has_day || caps.get(2).is_some() || caps.get(4).is_some() || caps.get(5).is_some());
+        if has_day || has_time {
+            let days = match caps.get(1).map(|m| m.as_str().to_lowercase()) {
+                Some(day) if day == "tomorrow" => 1,
+                _ => 0,
+            };
+            let (mut hour, minute) = match caps.get(3) {
+                Some(hour) if has_time => (
+                    hour.as_str().parse().ok()?,
+                    match caps.get(4) {
+                        Some(minute) => minute.as_str().parse().ok()?,
+                        None => 0,
+                    },
+                ),
+                _ => (9, 0),
+            };
+            match caps.get(5).map(|m| m.as_str().to_lowercase()) {
+                Some(p) if p == "pm" && hour < 12 => hour += 12,
+                Some(p) if p == "am" && hour == 12 => hour = 0,
+                _ => (),
+            }
+            let time = at_time(now, days, hour, minute, !has_day)?;
+            return Some((time, rest(text, &caps)));
+        }
+    }
+    None
+}
+
+fn at_time(
+    now: DateTime<Local>,
+    days: i64,
+    hour: u32,
+    minute: u32,
+    roll_over: bool,
+) -> Option<DateTime<Local>> {
+    let date: NaiveDate = now.date_naive() + Duration::days(days);
+    let time = Local
+        .from_local_datetime(&date.and_hms_opt(hour, minute, 0)?)
+        .single()?;
+    if roll_over && time <= now {
+        Local
+            .from_local_datetime(&(date + Duration::days(1)).and_hms_opt(hour, minute, 0)?)
+            .single()
+    } else {
+        Some(time)
+    }
+}
+
+fn rest(text: &str, caps: &Captures) -> String {
+    let rest = text[caps.get(0).unwrap().end()..]
+        .trim_start_matches(|c: char| c.is_whitespace() || ",，:：".contains(c));
+    let rest = ["提醒我", "叫我", "to "]
+        .iter()
+        .find_map(|prefix| rest.strip_prefix(prefix))
+        .unwrap_or(rest);
+    rest.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn relative_time() {
+        assert_eq!(
+            parse_remind_time("10分钟后 喝水", now()),
+            Some((now() + Duration::minutes(10), "喝水".to_owned()))
+        );
+        assert_eq!(
+            parse_remind_time("半小时后提醒我开会", now()),
+            Some((now() + Duration::minutes(30), "开会".to_owned()))
+        );
+        assert_eq!(
+            parse_remind_time("in 2 hours to stretch", now()),
+            Some((now() + Duration::hours(2), "stretch".to_owned()))
+        );
+    }
+
+    #[test]
+    fn absolute_time() {
+        assert_eq!(
+            parse_remind_time("明天 9点 开会", now()),
+            Some((at(2, 9, 0), "开会".to_owned()))
+        );
+        assert_eq!(
+            parse_remind_time("下午3点半 开会", now()),
+            Some((at(1, 15, 30), "开会".to_owned()))
+        );
+        // 已经过去的时间视为第二天
+        assert_eq!(
+            parse_remind_time("8:30 起床", now()),
+            Some((at(2, 8, 30), "起床".to_owned()))
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(parse_remind_time("9999999999999999秒后", now()), None);
+        assert_eq!(parse_remind_time("1000000000天后", now()), None);
+        assert_eq!(parse_remind_time("in 9999999999999999 days", now()), None);
+    }
+
+    #[test]
+    fn not_a_time() {
+        assert_eq!(parse_remind_time("喝水", now()), None);
+        assert_eq!(parse_remind_time("3 apples", now()), None);
+    }
+}
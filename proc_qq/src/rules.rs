@@ -0,0 +1,489 @@
+use crate::audit::record_config_change;
+use crate::{
+    sender_is_master, CommandInfo, FileStore, MessageContentTrait, MessageEvent,
+    MessageEventProcess, MessageSendToSourceTrait, MessageTemplate, Module, ModuleEventHandler,
+    ModuleEventProcess, ModuleStorage, Role, TemplateValues, TextEleParseTrait,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use regex::Regex;
+use ricq_core::msg::MessageChain;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// 规则的匹配方式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "pattern")]
+pub enum RuleMatcher {
+    /// 消息包含
+    Contains(String),
+    /// 正则表达式
+    Regex(String),
+    /// 消息完全相同 (去除首尾空白)
+    Exact(String),
+}
+
+/// 规则的生效范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleScope {
+    /// 所有消息
+    All,
+    /// 所有群
+    Groups,
+    /// 指定的群
+    Group(i64),
+    /// 私聊和临时会话
+    Private,
+}
+
+impl Default for RuleScope {
+    fn default() -> Self {
+        RuleScope::All
+    }
+}
+
+/// 关键词自动回复规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// 添加时由RuleEngine分配
+    #[serde(default)]
+    pub id: u64,
+    #[serde(rename = "match")]
+    pub matcher: RuleMatcher,
+    #[serde(default)]
+    pub scope: RuleScope,
+    /// 回复内容, 可以使用消息模版的变量
+    pub response: MessageTemplate,
+    /// 冷却时间(秒), 同一个群或私聊中触发后在冷却时间内不再触发
+    #[serde(default)]
+    pub cooldown: u64,
+}
+
+impl Rule {
+    pub fn new(matcher: RuleMatcher, response: MessageTemplate) -> Self {
+        Self {
+            id: 0,
+            matcher,
+            scope: RuleScope::All,
+            response,
+            cooldown: 0,
+        }
+    }
+
+    pub fn scope(mut self, scope: RuleScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown.as_secs();
+        self
+    }
+}
+
+struct CompiledRule {
+    rule: Rule,
+    regex: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn compile(rule: Rule) -> Result<Self> {
+        let regex = match &rule.matcher {
+            RuleMatcher::Regex(pattern) => Some(Regex::new(pattern)?),
+            _ => None,
+        };
+        Ok(Self { rule, regex })
+    }
+
+    fn is_match(&self, content: &str) -> bool {
+        match &self.rule.matcher {
+            RuleMatcher::Contains(pattern) => content.contains(pattern.as_str()),
+            RuleMatcher::Exact(pattern) => content.trim() == pattern,
+            RuleMatcher::Regex(_) => self
+                .regex
+                .as_ref()
+                .map(|regex| regex.is_match(content))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// 规则在存储中使用的键
+const STORE_KEY: &str = "rules";
+
+/// 运行时可以修改的自动回复规则
+///
+/// 通过`rule_module`加入到模块中生效, 主人可以使用`/rule`指令进行管理.
+/// 规则保存在模块的键值存储中, 与`ClientBuilder::store`使用同一个存储 (见`RuleEngine::store`).
+pub struct RuleEngine {
+    storage: Option<ModuleStorage>,
+    next_id: AtomicU64,
+    rules: RwLock<Vec<CompiledRule>>,
+    // (规则id, 群号或QQ号) -> 上次触发的时间
+    last_fired: Mutex<HashMap<(u64, i64), Instant>>,
+}
+
+impl RuleEngine {
+    /// 仅保存在内存中的规则
+    pub fn memory() -> Self {
+        Self {
+            storage: None,
+            next_id: AtomicU64::new(1),
+            rules: RwLock::new(vec![]),
+            last_fired: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 保存在模块的存储中, 需要调用`load`加载
+    ///
+    /// ```ignore
+    /// let builder = ClientBuilder::new().store(SqliteStore::open("bot.db")?);
+    /// let engine = Arc::new(RuleEngine::store(builder.module_store_for("rules")));
+    /// engine.load().await?;
+    /// ```
+    pub fn store(storage: ModuleStorage) -> Self {
+        Self {
+            storage: Some(storage),
+            ..Self::memory()
+        }
+    }
+
+    /// 保存在单独的json文件中 (`FileStore`), 需要调用`load`加载
+    pub fn file(path: impl Into<String>) -> Self {
+        Self::store(ModuleStorage::new("rules", Arc::new(FileStore::new(path))))
+    }
+
+    pub async fn load(&self) -> Result<()> {
+        if let Some(storage) = &self.storage {
+            let list: Vec<Rule> = storage.get(STORE_KEY).await?.unwrap_or_default();
+            let mut rules = vec![];
+            for rule in list {
+                self.next_id.fetch_max(rule.id + 1, Ordering::SeqCst);
+                rules.push(CompiledRule::compile(rule)?);
+            }
+            *self.rules.write().await = rules;
+        }
+        Ok(())
+    }
+
+    async fn save(&self, rules: &[CompiledRule]) -> Result<()> {
+        if let Some(storage) = &self.storage {
+            let list: Vec<&Rule> = rules.iter().map(|r| &r.rule).collect();
+            storage.set(STORE_KEY, &list).await?;
+        }
+        Ok(())
+    }
+
+    /// 添加规则, 返回规则的id. 编译和保存都成功后才会占用id
+    pub async fn add_rule(&self, rule: Rule) -> Result<u64> {
+        let mut compiled = CompiledRule::compile(rule)?;
+        let mut rules = self.rules.write().await;
+        let id = self.next_id.load(Ordering::SeqCst);
+        compiled.rule.id = id;
+        rules.push(compiled);
+        if let Err(err) = self.save(&rules).await {
+            rules.pop();
+            return Err(err);
+        }
+        self.next_id.store(id + 1, Ordering::SeqCst);
+        Ok(id)
+    }
+
+    /// 删除规则, 返回是否存在
+    pub async fn remove_rule(&self, id: u64) -> Result<bool> {
+        let mut rules = self.rules.write().await;
+        let len = rules.len();
+        rules.retain(|r| r.rule.id != id);
+        let removed = rules.len() != len;
+        if removed {
+            self.save(&rules).await?;
+        }
+        Ok(removed)
+    }
+
+    pub async fn rules(&self) -> Vec<Rule> {
+        self.rules
+            .read()
+            .await
+            .iter()
+            .map(|r| r.rule.clone())
+            .collect()
+    }
+
+    /// 找到第一个匹配的规则并回复, 返回是否回复
+    pub async fn handle(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let (group_code, source) = match event {
+            MessageEvent::GroupMessage(e) => (Some(e.inner.group_code), e.inner.group_code),
+            MessageEvent::FriendMessage(e) => (None, e.inner.from_uin),
+            MessageEvent::GroupTempMessage(e) => (None, e.inner.from_uin),
+        };
+        let response = {
+            let rules = self.rules.read().await;
+            let mut response = None;
+            for compiled in rules.iter() {
+                let rule = &compiled.rule;
+                let in_scope = match rule.scope {
+                    RuleScope::All => true,
+                    RuleScope::Groups => group_code.is_some(),
+                    RuleScope::Group(code) => group_code == Some(code),
+                    RuleScope::Private => group_code.is_none(),
+                };
+                if !in_scope || !compiled.is_match(&content) {
+                    continue;
+                }
+                if !self.fire(rule, source) {
+                    continue;
+                }
+                response = Some(rule.response.clone());
+                break;
+            }
+            response
+        };
+        match response {
+            Some(response) => {
+                let chain = response.render(&TemplateValues::from_event(event))?;
+                event.send_message_to_source(chain).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // 检查冷却, 可以触发时记录触发时间
+    fn fire(&self, rule: &Rule, source: i64) -> bool {
+        if rule.cooldown == 0 {
+            return true;
+        }
+        let mut last_fired = self.last_fired.lock().unwrap();
+        let now = Instant::now();
+        if let Some(last) = last_fired.get(&(rule.id, source)) {
+            if now.duration_since(*last) < Duration::from_secs(rule.cooldown) {
+                return false;
+            }
+        }
+        last_fired.insert((rule.id, source), now);
+        true
+    }
+
+    /// 处理主人发出的管理指令, 返回是否为管理指令
+    pub async fn handle_command(&self, event: &MessageEvent) -> Result<bool> {
+        let content = event.message_content();
+        let command = match parse_command(&content) {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+        let reply = match command {
+            RuleCommand::List => {
+                let rules = self.rules().await;
+                if rules.is_empty() {
+                    "没有规则".to_owned()
+                } else {
+                    rules
+                        .iter()
+                        .map(|rule| {
+                            format!(
+                                "{} : {:?} {:?} => {}",
+                                rule.id, rule.matcher, rule.scope, rule.response
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                }
+            }
+            RuleCommand::Del(id) => {
+                if self.remove_rule(id).await? {
+                    "OK".to_owned()
+                } else {
+                    format!("规则不存在 : {}", id)
+                }
+            }
+            RuleCommand::Add { matcher, response } => {
+                let response = response.parse::<MessageTemplate>()?;
+                let mut rule = Rule::new(matcher, response);
+                // 在群中添加的规则只在该群生效
+                if let MessageEvent::GroupMessage(e) = event {
+                    rule = rule.scope(RuleScope::Group(e.inner.group_code));
+                }
+                match self.add_rule(rule).await {
+                    Ok(id) => format!("已添加规则 : {}", id),
+                    Err(err) => format!("添加规则失败 : {}", err),
+                }
+            }
+            RuleCommand::Usage => RULE_USAGE.to_owned(),
+        };
+        if reply == "OK" || reply.starts_with("已") {
+            record_config_change(event).await;
+        }
+        event
+            .send_message_to_source(MessageChain::new(reply.parse_text()))
+            .await?;
+        Ok(true)
+    }
+}
+
+/// 解析后的`/rule`指令
+#[derive(Debug, PartialEq, Eq)]
+enum RuleCommand<'a> {
+    List,
+    Del(u64),
+    Add {
+        matcher: RuleMatcher,
+        response: &'a str,
+    },
+    /// 参数错误, 回复用法
+    Usage,
+}
+
+/// 取出第一个参数和剩余的内容, 参数之间可以有多个空白, 包含空白的参数可以使用双引号
+fn next_arg(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    if let Some(quoted) = text.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        return Some((&quoted[..end], &quoted[end + 1..]));
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+/// 不是`/rule`指令时返回None. 关键词之后的内容(去除首尾空白)都是回复
+fn parse_command(content: &str) -> Option<RuleCommand<'_>> {
+    let (name, rest) = next_arg(content)?;
+    if name != "/rule" {
+        return None;
+    }
+    let command = match next_arg(rest) {
+        None => RuleCommand::List,
+        Some(("list", rest)) if rest.trim().is_empty() => RuleCommand::List,
+        Some(("del", rest)) => match next_arg(rest) {
+            Some((id, rest)) if rest.trim().is_empty() => id
+                .parse()
+                .map(RuleCommand::Del)
+                .unwrap_or(RuleCommand::Usage),
+            _ => RuleCommand::Usage,
+        },
+        Some(("add", rest)) => parse_add(rest).unwrap_or(RuleCommand::Usage),
+        Some(_) => RuleCommand::Usage,
+    };
+    Some(command)
+}
+
+fn parse_add(args: &str) -> Option<RuleCommand<'_>> {
+    let (kind, rest) = next_arg(args)?;
+    let (pattern, response) = next_arg(rest)?;
+    let response = response.trim();
+    if pattern.is_empty() || response.is_empty() {
+        return None;
+    }
+    let pattern = pattern.to_owned();
+    let matcher = match kind {
+        "contains" => RuleMatcher::Contains(pattern),
+        "exact" => RuleMatcher::Exact(pattern),
+        "regex" => RuleMatcher::Regex(pattern),
+        _ => return None,
+    };
+    Some(RuleCommand::Add { matcher, response })
+}
+
+static RULE_USAGE: &str = "/rule list\n\
+/rule add contains|exact|regex 关键词 回复\n\
+/rule del 规则id\n\
+关键词包含空白时使用双引号, 例如 /rule add contains \"早 安\" 早安";
+
+struct RuleHandler {
+    engine: Arc<RuleEngine>,
+}
+
+#[async_trait]
+impl MessageEventProcess for RuleHandler {
+    async fn handle(&self, event: &MessageEvent) -> Result<bool> {
+        if sender_is_master(event).await && self.engine.handle_command(event).await? {
+            return Ok(true);
+        }
+        self.engine.handle(event).await
+    }
+}
+
+/// 内置的自动回复模块
+///
+/// ```ignore
+/// let builder = ClientBuilder::new().store(SqliteStore::open("bot.db")?);
+/// let engine = Arc::new(RuleEngine::store(builder.module_store_for("rules")));
+/// engine.load().await?;
+/// let builder = builder.modules(vec![rule_module(engine.clone()), ...]);
+/// ```
+pub fn rule_module(engine: Arc<RuleEngine>) -> Module {
+    Module {
+        id: "proc_qq_rules".to_owned(),
+        name: "自动回复".to_owned(),
+        handles: vec![ModuleEventHandler {
+            name: "rules".to_owned(),
+            process: ModuleEventProcess::Message(Box::new(RuleHandler { engine })),
+            command: Some(CommandInfo {
+                usage: "/rule".to_owned(),
+                description: RULE_USAGE.to_owned(),
+                require: Some(Role::Master),
+            }),
+        }],
+        data: Arc::new(Default::default()),
+        priority: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rule_commands() {
+        assert_eq!(parse_command("你好"), None);
+        assert_eq!(parse_command("/rules"), None);
+        assert_eq!(parse_command("/rule"), Some(RuleCommand::List));
+        assert_eq!(parse_command(" /rule  list "), Some(RuleCommand::List));
+        assert_eq!(parse_command("/rule del  3"), Some(RuleCommand::Del(3)));
+        assert_eq!(parse_command("/rule del x"), Some(RuleCommand::Usage));
+        assert_eq!(
+            parse_command("/rule  add   contains  早安   早安,  {at_sender} "),
+            Some(RuleCommand::Add {
+                matcher: RuleMatcher::Contains("早安".to_owned()),
+                response: "早安,  {at_sender}",
+            })
+        );
+        assert_eq!(
+            parse_command("/rule add exact \"早 安\" 早"),
+            Some(RuleCommand::Add {
+                matcher: RuleMatcher::Exact("早 安".to_owned()),
+                response: "早",
+            })
+        );
+        assert_eq!(
+            parse_command("/rule add regex \"unclosed 早"),
+            Some(RuleCommand::Usage)
+        );
+        assert_eq!(
+            parse_command("/rule add contains 早安"),
+            Some(RuleCommand::Usage)
+        );
+        assert_eq!(
+            parse_command("/rule add like 早安 早安"),
+            Some(RuleCommand::Usage)
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_rule_does_not_use_id() -> Result<()> {
+        let engine = RuleEngine::memory();
+        let invalid = Rule::new(RuleMatcher::Regex("(".to_owned()), "x".parse()?);
+        assert!(engine.add_rule(invalid).await.is_err());
+        let valid = Rule::new(RuleMatcher::Contains("早安".to_owned()), "早安".parse()?);
+        assert_eq!(engine.add_rule(valid).await?, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::traits::send_to_target;
+use crate::MessageTarget;
+use async_trait::async_trait;
+use regex::Regex;
+use ricq_core::msg::elem::{RQElem, Text};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use ricq_core::{RQError, RQResult};
+use std::sync::OnceLock;
+
+/// 发送失败的回调, 参数为目标、原始消息和最后一次发送的错误
+#[async_trait]
+pub trait SendErrorHandler: Send + Sync {
+    async fn handle(
+        &self,
+        client: &ricq::Client,
+        target: MessageTarget,
+        chain: &MessageChain,
+        err: &RQError,
+    );
+}
+
+/// 消息发送失败时的降级策略
+///
+/// 风控时图片和卡片消息更容易被拒绝, 开启后会依次尝试: 卡片转为文字, 去除图片.
+/// 仍然失败时调用`SendErrorHandler`, 然后返回错误.
+///
+/// ```ignore
+/// ClientBuilder::new().send_fallback(SendFallback::new().cards_to_text(true).strip_images(true))
+/// ```
+#[derive(Default)]
+pub struct SendFallback {
+    strip_images: bool,
+    cards_to_text: bool,
+    error_handler: Option<Box<dyn SendErrorHandler>>,
+}
+
+impl SendFallback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 含有图片的消息发送失败时, 将图片替换为`[图片]`后重试
+    pub fn strip_images(mut self, strip_images: bool) -> Self {
+        self.strip_images = strip_images;
+        self
+    }
+
+    /// 卡片消息(xml/json, 例如合并转发的卡片)发送失败时, 转为其中的文字后重试
+    pub fn cards_to_text(mut self, cards_to_text: bool) -> Self {
+        self.cards_to_text = cards_to_text;
+        self
+    }
+
+    pub fn error_handler<H: SendErrorHandler + 'static>(mut self, handler: H) -> Self {
+        self.error_handler = Some(Box::new(handler));
+        self
+    }
+
+    pub(crate) async fn retry(
+        &self,
+        client: &ricq::Client,
+        target: MessageTarget,
+        chain: MessageChain,
+        err: RQError,
+    ) -> RQResult<MessageReceipt> {
+        let mut err = err;
+        let mut current = chain.clone();
+        if self.cards_to_text && has_card(&current) {
+            tracing::warn!("卡片消息发送失败, 转为文字重试 : {:?}", err);
+            current = cards_to_text(&current);
+            match send_to_target(client, target, current.clone()).await {
+                Ok(receipt) => return Ok(receipt),
+                Err(e) => err = e,
+            }
+        }
+        if self.strip_images && has_image(&current) {
+            tracing::warn!("图片消息发送失败, 去除图片重试 : {:?}", err);
+            current = strip_images(&current);
+            match send_to_target(client, target, current).await {
+                Ok(receipt) => return Ok(receipt),
+                Err(e) => err = e,
+            }
+        }
+        if let Some(handler) = &self.error_handler {
+            handler.handle(client, target, &chain, &err).await;
+        }
+        Err(err)
+    }
+}
+
+fn has_image(chain: &MessageChain) -> bool {
+    chain.clone().into_iter().any(|elem| {
+        matches!(
+            elem,
+            RQElem::GroupImage(_) | RQElem::FriendImage(_) | RQElem::FlashImage(_)
+        )
+    })
+}
+
+fn has_card(chain: &MessageChain) -> bool {
+    chain
+        .clone()
+        .into_iter()
+        .any(|elem| matches!(elem, RQElem::LightApp(_) | RQElem::RichMsg(_)))
+}
+
+fn strip_images(chain: &MessageChain) -> MessageChain {
+    let mut result = MessageChain::default();
+    for elem in chain.clone() {
+        match elem {
+            RQElem::GroupImage(_) | RQElem::FriendImage(_) | RQElem::FlashImage(_) => {
+                result.push(Text::new("[图片]".to_owned()))
+            }
+            RQElem::Text(text) => result.push(text),
+            RQElem::At(at) => result.push(at),
+            RQElem::Face(face) => result.push(face),
+            RQElem::MarketFace(face) => result.push(face),
+            RQElem::Dice(dice) => result.push(dice),
+            RQElem::FingerGuessing(finger_guessing) => result.push(finger_guessing),
+            RQElem::LightApp(app) => result.push(app),
+            RQElem::RichMsg(msg) => result.push(msg),
+            RQElem::Reply(reply) => result.push(reply),
+            _ => {}
+        }
+    }
+    result
+}
+
+fn cards_to_text(chain: &MessageChain) -> MessageChain {
+    let mut result = MessageChain::default();
+    for elem in chain.clone() {
+        match elem {
+            RQElem::LightApp(app) => result.push(Text::new(light_app_text(&app.content))),
+            RQElem::RichMsg(msg) => result.push(Text::new(rich_msg_text(&msg.template1))),
+            RQElem::Text(text) => result.push(text),
+            RQElem::At(at) => result.push(at),
+            RQElem::Face(face) => result.push(face),
+            RQElem::MarketFace(face) => result.push(face),
+            RQElem::Dice(dice) => result.push(dice),
+            RQElem::FingerGuessing(finger_guessing) => result.push(finger_guessing),
+            RQElem::GroupImage(image) => result.push(image),
+            RQElem::FriendImage(image) => result.push(image),
+            RQElem::FlashImage(image) => result.push(image),
+            RQElem::Reply(reply) => result.push(reply),
+            _ => {}
+        }
+    }
+    result
+}
+
+// json卡片的prompt为消息列表中显示的摘要
+fn light_app_text(content: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|json| json["prompt"].as_str().map(str::to_owned))
+        .unwrap_or_else(|| "[卡片消息]".to_owned())
+}
+
+// xml卡片取出标题和摘要, 合并转发的卡片中为前几条消息的预览
+fn rich_msg_text(xml: &str) -> String {
+    static TEXT: OnceLock<Regex> = OnceLock::new();
+    static BRIEF: OnceLock<Regex> = OnceLock::new();
+    let text = TEXT
+        .get_or_init(|| Regex::new(r"(?s)<(title|summary)[^>]*>(.*?)</(title|summary)>").unwrap());
+    let lines: Vec<String> = text
+        .captures_iter(xml)
+        .map(|captures| unescape_xml(&captures[2]))
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if !lines.is_empty() {
+        return lines.join("\n");
+    }
+    let brief = BRIEF.get_or_init(|| Regex::new(r#"brief="([^"]*)""#).unwrap());
+    brief
+        .captures(xml)
+        .map(|captures| unescape_xml(&captures[1]))
+        .unwrap_or_else(|| "[卡片消息]".to_owned())
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
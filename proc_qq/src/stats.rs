@@ -0,0 +1,256 @@
+use crate::persist::{load_json, save_json};
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 统计的时间范围
+#[derive(Debug, Clone, Copy)]
+pub enum StatsRange {
+    /// 今天 (本地时间)
+    Today,
+    /// 最近一段时间
+    Last(Duration),
+    /// [开始, 结束)
+    Between(DateTime<Utc>, DateTime<Utc>),
+}
+
+impl StatsRange {
+    // 转换为小时的范围 [start, end)
+    fn hours(&self) -> (i64, i64) {
+        let now = Utc::now().timestamp();
+        let (start, end) = match self {
+            StatsRange::Today => {
+                let start = Local
+                    .from_local_datetime(&Local::now().date_naive().and_hms_opt(0, 0, 0).unwrap())
+                    .earliest()
+                    .map(|t| t.timestamp())
+                    .unwrap_or(now);
+                (start / 3600, now / 3600 + 1)
+            }
+            StatsRange::Last(duration) => {
+                ((now - duration.as_secs() as i64) / 3600, now / 3600 + 1)
+            }
+            StatsRange::Between(start, end) => {
+                (start.timestamp() / 3600, (end.timestamp() + 3599) / 3600)
+            }
+        };
+        (start, end.max(start))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StatsData {
+    // 小时 -> 群号(私聊为0) -> QQ号 -> 消息数
+    messages: BTreeMap<i64, HashMap<i64, HashMap<i64, u64>>>,
+    // 小时 -> 处理器(模块id.处理器名) -> 次数
+    commands: BTreeMap<i64, HashMap<String, u64>>,
+}
+
+/// 消息统计
+///
+/// 通过`ClientBuilder::stats`开启, 调度器会记录每条通过过滤的消息, 以及处理消息的模块和处理器.
+/// 数据按小时分桶, 超过保留时间的数据会被清除.
+pub struct Stats {
+    path: Option<String>,
+    retention: Duration,
+    dirty: AtomicBool,
+    data: Mutex<StatsData>,
+}
+
+impl Stats {
+    /// 仅保存在内存中的统计
+    pub fn memory() -> Self {
+        Self {
+            path: None,
+            retention: Duration::from_secs(60 * 60 * 24 * 30),
+            dirty: AtomicBool::new(false),
+            data: Mutex::new(StatsData::default()),
+        }
+    }
+
+    /// 保存在文件中的统计, 构建客户端时加载, 之后每分钟写入一次
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::memory()
+        }
+    }
+
+    /// 数据保留的时间, 默认30天
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub(crate) async fn load(self: &Arc<Self>) -> Result<()> {
+        if let Some(path) = &self.path {
+            *self.data.lock().unwrap() = load_json(path).await?;
+            let weak = Arc::downgrade(self);
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    let stats = match weak.upgrade() {
+                        Some(stats) => stats,
+                        None => break,
+                    };
+                    if let Err(err) = stats.save().await {
+                        tracing::warn!("统计数据保存失败 : {:?}", err);
+                    }
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// 立即写入文件
+    pub async fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            if self.dirty.swap(false, Ordering::SeqCst) {
+                let data = self.data.lock().unwrap().clone();
+                save_json(path, &data).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn modify<F: FnOnce(&mut StatsData, i64)>(&self, f: F) {
+        let hour = Utc::now().timestamp() / 3600;
+        let oldest = hour - (self.retention.as_secs() / 3600) as i64;
+        let mut data = self.data.lock().unwrap();
+        f(&mut data, hour);
+        // 清除过期数据
+        while matches!(data.messages.keys().next(), Some(h) if *h < oldest) {
+            data.messages.pop_first();
+        }
+        while matches!(data.commands.keys().next(), Some(h) if *h < oldest) {
+            data.commands.pop_first();
+        }
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// 记录一条消息, group_code为None时表示私聊
+    pub fn record_message(&self, group_code: Option<i64>, uin: i64) {
+        self.modify(|data, hour| {
+            *data
+                .messages
+                .entry(hour)
+                .or_default()
+                .entry(group_code.unwrap_or(0))
+                .or_default()
+                .entry(uin)
+                .or_default() += 1;
+        })
+    }
+
+    /// 记录一次指令(处理器)的使用
+    pub fn record_command(&self, name: &str) {
+        self.modify(|data, hour| {
+            *data
+                .commands
+                .entry(hour)
+                .or_default()
+                .entry(name.to_owned())
+                .or_default() += 1;
+        })
+    }
+
+    // 遍历范围内的消息数 (小时, 群号, QQ号, 数量)
+    fn each_message<F: FnMut(i64, i64, i64, u64)>(
+        &self,
+        group_code: Option<i64>,
+        range: StatsRange,
+        mut f: F,
+    ) {
+        let (start, end) = range.hours();
+        let data = self.data.lock().unwrap();
+        for (hour, groups) in data.messages.range(start..end) {
+            for (group, users) in groups {
+                if group_code.is_some() && group_code != Some(*group) {
+                    continue;
+                }
+                for (uin, count) in users {
+                    f(*hour, *group, *uin, *count);
+                }
+            }
+        }
+    }
+
+    /// 群中发言最多的用户, 按消息数从多到少排列
+    pub fn top_users(&self, group_code: i64, range: StatsRange) -> Vec<(i64, u64)> {
+        let mut users: HashMap<i64, u64> = HashMap::new();
+        self.each_message(Some(group_code), range, |_, _, uin, count| {
+            *users.entry(uin).or_default() += count;
+        });
+        let mut users: Vec<(i64, u64)> = users.into_iter().collect();
+        users.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        users
+    }
+
+    /// 消息最多的群, 按消息数从多到少排列
+    pub fn top_groups(&self, range: StatsRange) -> Vec<(i64, u64)> {
+        let mut groups: HashMap<i64, u64> = HashMap::new();
+        self.each_message(None, range, |_, group, _, count| {
+            if group != 0 {
+                *groups.entry(group).or_default() += count;
+            }
+        });
+        let mut groups: Vec<(i64, u64)> = groups.into_iter().collect();
+        groups.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        groups
+    }
+
+    /// 消息数量, group_code为None时为所有消息, Some(0)为私聊
+    pub fn message_count(&self, group_code: Option<i64>, range: StatsRange) -> u64 {
+        let mut total = 0;
+        self.each_message(group_code, range, |_, _, _, count| total += count);
+        total
+    }
+
+    /// 发言过的用户数量
+    pub fn active_users(&self, group_code: Option<i64>, range: StatsRange) -> usize {
+        let mut users = HashSet::new();
+        self.each_message(group_code, range, |_, _, uin, _| {
+            users.insert(uin);
+        });
+        users.len()
+    }
+
+    /// 每小时的消息数量, 按时间排列, 没有消息的小时不会出现
+    pub fn messages_per_hour(
+        &self,
+        group_code: Option<i64>,
+        range: StatsRange,
+    ) -> Vec<(DateTime<Utc>, u64)> {
+        let mut hours: BTreeMap<i64, u64> = BTreeMap::new();
+        self.each_message(group_code, range, |hour, _, _, count| {
+            *hours.entry(hour).or_default() += count;
+        });
+        hours
+            .into_iter()
+            .filter_map(|(hour, count)| {
+                Utc.timestamp_opt(hour * 3600, 0)
+                    .single()
+                    .map(|time| (time, count))
+            })
+            .collect()
+    }
+
+    /// 指令(处理器)的使用次数, 按次数从多到少排列
+    pub fn command_usage(&self, range: StatsRange) -> Vec<(String, u64)> {
+        let (start, end) = range.hours();
+        let mut commands: HashMap<String, u64> = HashMap::new();
+        let data = self.data.lock().unwrap();
+        for (_, hour) in data.commands.range(start..end) {
+            for (name, count) in hour {
+                *commands.entry(name.clone()).or_default() += count;
+            }
+        }
+        let mut commands: Vec<(String, u64)> = commands.into_iter().collect();
+        commands.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        commands
+    }
+}
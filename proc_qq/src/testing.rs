@@ -0,0 +1,316 @@
+//! 测试模块的工具, 不需要登录QQ即可验证处理器的逻辑
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn test_hello() -> anyhow::Result<()> {
+//!     let client = MockClient::with_modules(vec![hello_module::module()]).await?;
+//!     let sent = client.dispatch(MockEvent::group(10000, 12345).text("你好")).await;
+//!     assert_eq!(sent[0].text(), "世界");
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    ClientBuilder, ExtensionsTrait, MessageChainParseTrait, MessageEvent, MessageTarget, Module,
+};
+use anyhow::Result;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq::handler::{Handler, QEvent};
+use ricq::structs::{FriendMessage, GroupMessage, GroupTempMessage};
+use ricq_core::msg::MessageChain;
+use ricq_core::protocol::device::Device;
+use ricq_core::structs::MessageReceipt;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 处理器尝试发送的消息
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    pub target: MessageTarget,
+    pub message: MessageChain,
+}
+
+impl SentMessage {
+    /// 消息的文字内容
+    pub fn text(&self) -> String {
+        self.message.to_string()
+    }
+}
+
+/// 记录发送的消息, 客户端中存在时不会真正发送
+#[derive(Default)]
+pub(crate) struct MockOutbox(Mutex<Vec<SentMessage>>);
+
+impl MockOutbox {
+    pub(crate) fn push(&self, target: MessageTarget, message: MessageChain) -> MessageReceipt {
+        self.0.lock().unwrap().push(SentMessage { target, message });
+        MessageReceipt::default()
+    }
+
+    fn take(&self) -> Vec<SentMessage> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+enum MockSource {
+    Group(i64),
+    Friend,
+    GroupTemp(i64),
+}
+
+/// 构造测试用的消息事件
+pub struct MockEvent {
+    source: MockSource,
+    from_uin: i64,
+    sender_name: String,
+    group_name: String,
+    elements: MessageChain,
+}
+
+impl MockEvent {
+    fn new(source: MockSource, from_uin: i64) -> Self {
+        Self {
+            source,
+            from_uin,
+            sender_name: from_uin.to_string(),
+            group_name: String::new(),
+            elements: MessageChain::default(),
+        }
+    }
+
+    /// 群消息
+    pub fn group(group_code: i64, from_uin: i64) -> Self {
+        Self::new(MockSource::Group(group_code), from_uin)
+    }
+
+    /// 好友消息
+    pub fn friend(from_uin: i64) -> Self {
+        Self::new(MockSource::Friend, from_uin)
+    }
+
+    /// 群临时会话消息
+    pub fn group_temp(group_code: i64, from_uin: i64) -> Self {
+        Self::new(MockSource::GroupTemp(group_code), from_uin)
+    }
+
+    /// 消息的内容为文字
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.elements = text.into().parse_message_chain();
+        self
+    }
+
+    /// 消息的内容, 可以包含@、表情等元素
+    pub fn message(mut self, message: impl Into<MessageChain>) -> Self {
+        self.elements = message.into();
+        self
+    }
+
+    /// 发送者的群名片或昵称, 默认为QQ号
+    pub fn sender_name(mut self, name: impl Into<String>) -> Self {
+        self.sender_name = name.into();
+        self
+    }
+
+    pub fn group_name(mut self, name: impl Into<String>) -> Self {
+        self.group_name = name.into();
+        self
+    }
+}
+
+/// 测试用的客户端, 不会连接服务器
+///
+/// 使用ClientBuilder的设置(模块、主人、权限、中间件等)构造, 事件交给模块处理,
+/// 处理器发送的消息不会发出, 而是记录下来由`dispatch`返回.
+/// 上传图片、获取群列表等需要连接服务器的操作会失败.
+pub struct MockClient {
+    pub rq_client: Arc<ricq::Client>,
+    handler: crate::ClientHandler,
+    outbox: Arc<MockOutbox>,
+    seq: AtomicI32,
+}
+
+impl MockClient {
+    pub async fn build(builder: &ClientBuilder) -> Result<Self> {
+        let modules = builder.prepare().await?;
+        let rq_client = Arc::new(ricq::Client::new(
+            Device::random(),
+            builder.version.clone(),
+            builder.client_handler(modules.clone()),
+        ));
+        builder.register(&rq_client);
+        let outbox = Arc::new(MockOutbox::default());
+        rq_client.extensions().insert_arc(outbox.clone());
+        // 没有登录过程, 直接开始发送延时消息
+        builder.delayed_sender.restore(rq_client.clone()).await;
+        Ok(Self {
+            rq_client,
+            handler: builder.client_handler(modules),
+            outbox,
+            seq: AtomicI32::new(1),
+        })
+    }
+
+    /// 只设置了模块的客户端
+    pub async fn with_modules<I, M>(modules: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<Arc<Module>>,
+    {
+        Self::build(&ClientBuilder::new().modules(modules)).await
+    }
+
+    /// 只设置了一个模块的客户端
+    pub async fn with_module(module: impl Into<Arc<Module>>) -> Result<Self> {
+        Self::with_modules(vec![module.into()]).await
+    }
+
+    fn next_seq(&self) -> i32 {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn time() -> i32 {
+        chrono::Local::now().timestamp() as i32
+    }
+
+    /// 将MockEvent转换为消息事件, 可以直接调用处理器
+    pub fn message_event(&self, event: MockEvent) -> MessageEvent {
+        let seq = self.next_seq();
+        let client = self.rq_client.clone();
+        match event.source {
+            MockSource::Group(group_code) => MessageEvent::GroupMessage(GroupMessageEvent {
+                client,
+                inner: GroupMessage {
+                    seqs: vec![seq],
+                    rands: vec![seq],
+                    group_code,
+                    group_name: event.group_name,
+                    group_card: event.sender_name,
+                    from_uin: event.from_uin,
+                    time: Self::time(),
+                    elements: event.elements,
+                    ..Default::default()
+                },
+            }),
+            MockSource::Friend => MessageEvent::FriendMessage(FriendMessageEvent {
+                client,
+                inner: FriendMessage {
+                    seqs: vec![seq],
+                    rands: vec![seq],
+                    from_uin: event.from_uin,
+                    from_nick: event.sender_name,
+                    time: Self::time(),
+                    elements: event.elements,
+                    ..Default::default()
+                },
+            }),
+            MockSource::GroupTemp(group_code) => {
+                MessageEvent::GroupTempMessage(GroupTempMessageEvent {
+                    client,
+                    inner: GroupTempMessage {
+                        seqs: vec![seq],
+                        rands: vec![seq],
+                        group_code,
+                        from_uin: event.from_uin,
+                        from_nick: event.sender_name,
+                        time: Self::time(),
+                        elements: event.elements,
+                        ..Default::default()
+                    },
+                })
+            }
+        }
+    }
+
+    /// 将消息交给模块处理(经过中间件、黑白名单、权限等), 返回处理过程中发送的消息
+    pub async fn dispatch(&self, event: MockEvent) -> Vec<SentMessage> {
+        let event = match self.message_event(event) {
+            MessageEvent::GroupMessage(event) => QEvent::GroupMessage(event),
+            MessageEvent::FriendMessage(event) => QEvent::FriendMessage(event),
+            MessageEvent::GroupTempMessage(event) => QEvent::GroupTempMessage(event),
+        };
+        self.dispatch_event(event).await
+    }
+
+    /// 将任意事件交给模块处理, 返回处理过程中发送的消息, 包括之前还没有取出的消息
+    pub async fn dispatch_event(&self, event: QEvent) -> Vec<SentMessage> {
+        self.handler.handle(event).await;
+        self.outbox.take()
+    }
+
+    /// 取出还没有被`dispatch`返回的消息, 例如处理器在后台任务中发送的消息
+    pub fn take_sent_messages(&self) -> Vec<SentMessage> {
+        self.outbox.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ClientTrait, MessageContentTrait, MessageEventProcess, MessageSendToSourceTrait,
+        ModuleEventHandler, ModuleEventProcess,
+    };
+    use async_trait::async_trait;
+
+    struct Hello;
+
+    #[async_trait]
+    impl MessageEventProcess for Hello {
+        async fn handle(&self, event: &MessageEvent) -> anyhow::Result<bool> {
+            if event.message_content() != "你好" {
+                return Ok(false);
+            }
+            event
+                .send_message_to_source("世界".parse_message_chain())
+                .await?;
+            Ok(true)
+        }
+    }
+
+    fn hello_module() -> Module {
+        Module {
+            id: "hello".to_owned(),
+            name: "hello".to_owned(),
+            handles: vec![ModuleEventHandler {
+                name: "hello".to_owned(),
+                process: ModuleEventProcess::Message(Box::new(Hello)),
+                command: None,
+            }],
+            data: Arc::new(Default::default()),
+            priority: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn dispatch_round_trip() -> Result<()> {
+        let client = MockClient::with_module(hello_module()).await?;
+        let sent = client
+            .dispatch(MockEvent::group(10000, 12345).text("你好"))
+            .await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].text(), "世界");
+        assert!(matches!(sent[0].target, MessageTarget::Group(10000, _)));
+
+        let sent = client.dispatch(MockEvent::friend(12345).text("你好")).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].target, MessageTarget::Private(12345));
+
+        let sent = client.dispatch(MockEvent::friend(12345).text("再见")).await;
+        assert!(sent.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pending_messages_are_not_dropped() -> Result<()> {
+        let client = MockClient::with_module(hello_module()).await?;
+        client
+            .rq_client
+            .send_message_to_target(&MessageTarget::Private(1), "后台".parse_message_chain())
+            .await?;
+        let sent = client.dispatch(MockEvent::friend(12345).text("你好")).await;
+        let texts: Vec<String> = sent.iter().map(SentMessage::text).collect();
+        assert_eq!(texts, vec!["后台", "世界"]);
+        assert!(client.take_sent_messages().is_empty());
+        Ok(())
+    }
+}
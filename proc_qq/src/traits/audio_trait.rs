@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use ricq::client::event::{FriendAudioMessageEvent, GroupAudioMessageEvent};
+use ricq::structs::{FriendAudio, GroupAudio};
+use ricq_core::structs::MessageReceipt;
+use ricq_core::{RQError, RQResult};
+use std::time::Duration;
+
+use crate::{MessageTarget, MessageTargetTrait, VoiceElement};
+
+/// 语音的编码, 上传时使用
+pub const AUDIO_CODEC_AMR: u32 = 0;
+pub const AUDIO_CODEC_SILK: u32 = 1;
+
+/// 上传的语音, 使用`send_uploaded_audio`发送
+pub enum UploadAudio {
+    GroupAudio(GroupAudio),
+    FriendAudio(FriendAudio),
+}
+
+/// 上传和发送语音
+///
+/// 语音需要是silk或amr格式, 开启`silk`特性后可以使用`encode_pcm_to_silk`将PCM编码为silk.
+/// 临时会话不支持语音.
+///
+/// ```ignore
+/// let silk = tokio::fs::read("hello.silk").await?;
+/// client
+///     .send_audio_to_group(group_code, &silk, AUDIO_CODEC_SILK, Duration::from_secs(3))
+///     .await?;
+/// ```
+#[async_trait]
+pub trait AudioSendTrait: Send + Sync {
+    async fn upload_audio<S: AsRef<[u8]> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        data: S,
+        codec: u32,
+        duration: Duration,
+    ) -> RQResult<UploadAudio>;
+
+    async fn send_uploaded_audio(
+        &self,
+        target: &impl MessageTargetTrait,
+        audio: UploadAudio,
+    ) -> RQResult<MessageReceipt>;
+
+    async fn send_audio_to_target<S: AsRef<[u8]> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        data: S,
+        codec: u32,
+        duration: Duration,
+    ) -> RQResult<MessageReceipt> {
+        let audio = self.upload_audio(target, data, codec, duration).await?;
+        self.send_uploaded_audio(target, audio).await
+    }
+
+    async fn send_audio_to_group<S: AsRef<[u8]> + Send + Sync>(
+        &self,
+        group_code: i64,
+        data: S,
+        codec: u32,
+        duration: Duration,
+    ) -> RQResult<MessageReceipt> {
+        self.send_audio_to_target(&MessageTarget::Group(group_code, 0), data, codec, duration)
+            .await
+    }
+
+    async fn send_audio_to_friend<S: AsRef<[u8]> + Send + Sync>(
+        &self,
+        uin: i64,
+        data: S,
+        codec: u32,
+        duration: Duration,
+    ) -> RQResult<MessageReceipt> {
+        self.send_audio_to_target(&MessageTarget::Private(uin), data, codec, duration)
+            .await
+    }
+}
+
+#[async_trait]
+impl AudioSendTrait for ricq::Client {
+    async fn upload_audio<S: AsRef<[u8]> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        data: S,
+        codec: u32,
+        duration: Duration,
+    ) -> RQResult<UploadAudio> {
+        match target.target() {
+            MessageTarget::Group(group_code, _) => Ok(UploadAudio::GroupAudio(
+                self.upload_group_audio(group_code, data.as_ref(), codec)
+                    .await?,
+            )),
+            MessageTarget::Private(uin) => Ok(UploadAudio::FriendAudio(
+                self.upload_friend_audio(uin, data.as_ref(), duration)
+                    .await?,
+            )),
+            MessageTarget::GroupTemp(..) => Err(RQError::Other(
+                "tmp message not supported upload audio".to_owned(),
+            )),
+        }
+    }
+
+    async fn send_uploaded_audio(
+        &self,
+        target: &impl MessageTargetTrait,
+        audio: UploadAudio,
+    ) -> RQResult<MessageReceipt> {
+        match (target.target(), audio) {
+            (MessageTarget::Group(group_code, _), UploadAudio::GroupAudio(audio)) => {
+                self.send_group_audio(group_code, audio).await
+            }
+            (MessageTarget::Private(uin), UploadAudio::FriendAudio(audio)) => {
+                self.send_friend_audio(uin, audio).await
+            }
+            _ => Err(RQError::Other("audio does not match the target".to_owned())),
+        }
+    }
+}
+
+#[async_trait]
+impl AudioSendTrait for crate::Client {
+    async fn upload_audio<S: AsRef<[u8]> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        data: S,
+        codec: u32,
+        duration: Duration,
+    ) -> RQResult<UploadAudio> {
+        self.rq_client
+            .upload_audio(target, data, codec, duration)
+            .await
+    }
+
+    async fn send_uploaded_audio(
+        &self,
+        target: &impl MessageTargetTrait,
+        audio: UploadAudio,
+    ) -> RQResult<MessageReceipt> {
+        self.rq_client.send_uploaded_audio(target, audio).await
+    }
+}
+
+/// 取得收到的语音
+pub trait VoiceMessageTrait {
+    fn voice(&self) -> VoiceElement;
+}
+
+impl VoiceMessageTrait for GroupAudioMessageEvent {
+    fn voice(&self) -> VoiceElement {
+        VoiceElement::GroupAudio(self.inner.group_code, self.inner.audio.clone())
+    }
+}
+
+impl VoiceMessageTrait for FriendAudioMessageEvent {
+    fn voice(&self) -> VoiceElement {
+        VoiceElement::FriendAudio(self.inner.from_uin, self.inner.audio.clone())
+    }
+}
@@ -4,8 +4,13 @@ use ricq_core::common::group_code2uin;
 use ricq_core::msg::MessageChain;
 use ricq_core::structs::MessageReceipt;
 use ricq_core::{RQError, RQResult};
+use std::time::Instant;
 
-use crate::{MessageTarget, MessageTargetTrait};
+use crate::extensions::ClientRef;
+use crate::{
+    BotMuteTracker, DelayedSender, ExtensionsTrait, LongMessagePolicy, MessageTarget,
+    MessageTargetTrait, MutedSendPolicy, SendFallback, SendThrottle,
+};
 
 #[async_trait]
 pub trait ClientTrait: Send + Sync {
@@ -18,6 +23,104 @@ pub trait ClientTrait: Send + Sync {
     async fn bot_uin(&self) -> i64;
 }
 
+/// 发送消息, 不经过发送失败的降级策略
+pub(crate) async fn send_to_target(
+    client: &ricq::Client,
+    target: MessageTarget,
+    message: MessageChain,
+) -> RQResult<MessageReceipt> {
+    #[cfg(feature = "testing")]
+    if let Some(outbox) = client.extensions().get::<crate::testing::MockOutbox>() {
+        return Ok(outbox.push(target, message));
+    }
+    if let Some(throttle) = client.extensions().get::<SendThrottle>() {
+        throttle.wait(target).await;
+    }
+    match target {
+        MessageTarget::Group(group_code, _) => client.send_group_message(group_code, message).await,
+        MessageTarget::Private(uin) => client.send_friend_message(uin, message).await,
+        MessageTarget::GroupTemp(group_code, uin) => {
+            match client
+                .send_message(
+                    ricq_core::pb::msg::routing_head::RoutingHead::GrpTmp(
+                        ricq_core::pb::msg::GrpTmp {
+                            group_uin: Some(group_code2uin(group_code)),
+                            to_uin: Some(uin),
+                        },
+                    ),
+                    message,
+                    None,
+                )
+                .await
+            {
+                Ok(_) => RQResult::Ok(MessageReceipt::default()),
+                Err(err) => RQResult::Err(err),
+            }
+        }
+    }
+}
+
+/// 长消息策略和发送失败的降级策略
+async fn send_with_policies(
+    client: &ricq::Client,
+    target: MessageTarget,
+    message: MessageChain,
+) -> RQResult<MessageReceipt> {
+    if let Some(policy) = client.extensions().get::<LongMessagePolicy>() {
+        if let Some(text) = policy.long_text(&message) {
+            return policy.send_long_text(client, target, &text).await;
+        }
+    }
+    let fallback = match client.extensions().get::<SendFallback>() {
+        Some(fallback) => fallback,
+        None => return send_to_target(client, target, message).await,
+    };
+    match send_to_target(client, target, message.clone()).await {
+        Ok(receipt) => Ok(receipt),
+        Err(err) => fallback.retry(client, target, message, err).await,
+    }
+}
+
+/// 机器人被禁言时按照MutedSendPolicy处理, 返回None时照常发送
+async fn send_when_muted(
+    client: &ricq::Client,
+    target: MessageTarget,
+    message: &MessageChain,
+    until: chrono::DateTime<chrono::Local>,
+) -> RQResult<Option<MessageReceipt>> {
+    let extensions = client.extensions();
+    let policy = extensions
+        .get::<MutedSendPolicy>()
+        .map(|policy| *policy)
+        .unwrap_or(MutedSendPolicy::Attempt);
+    match policy {
+        MutedSendPolicy::Attempt => Ok(None),
+        MutedSendPolicy::Drop => {
+            tracing::debug!("机器人被禁言, 丢弃消息 : {:?}", target);
+            Ok(Some(MessageReceipt::default()))
+        }
+        MutedSendPolicy::Defer => {
+            let rq_client = extensions.get::<ClientRef>().and_then(|c| c.0.upgrade());
+            let delayed_sender = extensions.get::<DelayedSender>();
+            let (rq_client, delayed_sender) = match (rq_client, delayed_sender) {
+                (Some(rq_client), Some(delayed_sender)) => (rq_client, delayed_sender),
+                _ => return Ok(None),
+            };
+            tracing::debug!("机器人被禁言, 在 {} 后发送 : {:?}", until, target);
+            delayed_sender
+                .send_at(
+                    rq_client,
+                    target,
+                    message.clone(),
+                    until + chrono::Duration::seconds(1),
+                )
+                .await
+                .map_err(|err| RQError::Other(format!("{:?}", err)))?;
+            Ok(Some(MessageReceipt::default()))
+        }
+    }
+}
+
 #[async_trait]
 impl ClientTrait for ricq::Client {
     async fn send_message_to_target<S: Into<MessageChain> + Send + Sync>(
@@ -26,30 +129,43 @@ impl ClientTrait for ricq::Client {
         message: S,
     ) -> RQResult<MessageReceipt> {
         let message = message.into();
-        match source.target() {
-            MessageTarget::Group(group_code, _) => {
-                self.send_group_message(group_code, message).await
-            }
-            MessageTarget::Private(uin) => self.send_friend_message(uin, message).await,
-            MessageTarget::GroupTemp(group_code, uin) => {
-                match self
-                    .send_message(
-                        ricq_core::pb::msg::routing_head::RoutingHead::GrpTmp(
-                            ricq_core::pb::msg::GrpTmp {
-                                group_uin: Some(group_code2uin(group_code)),
-                                to_uin: Some(uin),
-                            },
-                        ),
-                        message,
-                        None,
-                    )
-                    .await
-                {
-                    Ok(_) => RQResult::Ok(MessageReceipt::default()),
-                    Err(err) => RQResult::Err(err),
+        let target = source.target();
+        let group_code = match target {
+            MessageTarget::Group(group_code, _) => Some(group_code),
+            _ => None,
+        };
+        let mute_tracker = self
+            .extensions()
+            .get_or_insert_with(BotMuteTracker::default);
+        if let Some(group_code) = group_code {
+            if let Some(until) = mute_tracker.muted_until(group_code) {
+                if let Some(receipt) = send_when_muted(self, target, &message, until).await? {
+                    return Ok(receipt);
                 }
             }
         }
+        #[cfg(feature = "event_recorder")]
+        let recorded = self
+            .extensions()
+            .get::<crate::features::EventRecorder>()
+            .map(|recorder| (recorder, message.clone()));
+        let start = Instant::now();
+        let result = send_with_policies(self, target, message).await;
+        #[cfg(feature = "event_recorder")]
+        if let (Some((recorder, message)), Ok(receipt)) = (recorded, &result) {
+            let record =
+                crate::features::EventRecord::outgoing(self.uin().await, target, receipt, &message);
+            recorder.record(record).await;
+        }
+        crate::health::health(self).record_send(start.elapsed(), result.is_ok());
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.extensions().get::<crate::features::Metrics>() {
+            metrics.record_send(result.is_ok());
+        }
+        if let (Err(_), Some(group_code)) = (&result, group_code) {
+            mute_tracker.refresh(self, group_code).await;
+        }
+        result
     }
     async fn must_find_group(&self, group_code: i64) -> RQResult<GroupInfo> {
         let group = self.get_group_info(group_code).await?;
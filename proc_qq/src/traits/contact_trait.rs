@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use ricq::client::event::EventWithClient;
+use ricq::structs::{FriendInfo, GroupInfo};
+use ricq_core::RQResult;
+
+use crate::{MessageEvent, NameMatch};
+
+/// 按名称查找群和好友, 使用联系人列表的缓存 (见`ContactCache`)
+///
+/// ```ignore
+/// let groups = event.find_group_by_name("测试群", NameMatch::Fuzzy).await?;
+/// ```
+#[async_trait]
+pub trait ContactLookupTrait: Send + Sync {
+    /// 按名称查找群, 结果按匹配程度从高到低排列
+    async fn find_group_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<GroupInfo>>;
+    /// 按备注或昵称查找好友, 结果按匹配程度从高到低排列
+    async fn find_friend_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<FriendInfo>>;
+}
+
+#[async_trait]
+impl ContactLookupTrait for ricq::Client {
+    async fn find_group_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<GroupInfo>> {
+        crate::contacts::find_groups(self, name, mode).await
+    }
+
+    async fn find_friend_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<FriendInfo>> {
+        crate::contacts::find_friends(self, name, mode).await
+    }
+}
+
+#[async_trait]
+impl ContactLookupTrait for crate::Client {
+    async fn find_group_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<GroupInfo>> {
+        self.rq_client.find_group_by_name(name, mode).await
+    }
+
+    async fn find_friend_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<FriendInfo>> {
+        self.rq_client.find_friend_by_name(name, mode).await
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync> ContactLookupTrait for EventWithClient<T> {
+    async fn find_group_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<GroupInfo>> {
+        self.client.find_group_by_name(name, mode).await
+    }
+
+    async fn find_friend_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<FriendInfo>> {
+        self.client.find_friend_by_name(name, mode).await
+    }
+}
+
+#[async_trait]
+impl ContactLookupTrait for MessageEvent {
+    async fn find_group_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<GroupInfo>> {
+        self.client().find_group_by_name(name, mode).await
+    }
+
+    async fn find_friend_by_name(&self, name: &str, mode: NameMatch) -> RQResult<Vec<FriendInfo>> {
+        self.client().find_friend_by_name(name, mode).await
+    }
+}
@@ -0,0 +1,352 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use ricq::client::event::EventWithClient;
+use ricq::structs::{ForwardMessage, ForwardNode, MessageNode};
+use ricq_core::msg::elem::{FlashImage, RQElem, Text};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use ricq_core::{RQError, RQResult};
+
+use crate::features::preprocess_upload_image;
+use crate::{
+    download_bytes, ClientTrait, DownloadOptions, ImageElement, MessageChainPointTrait,
+    MessageEvent, MessageTarget, MessageTargetTrait, UploadImage,
+};
+
+/// 将消息转换为可以发送到target的消息
+///
+/// 文字、@、表情会保留, 图片会重新上传到目标 (群图片和好友图片不通用),
+/// 发送到私聊时@会变为文字, 临时会话不支持图片时使用`[图片]`代替. 引用等与原会话相关的元素会被去除.
+pub async fn rebuild_chain_for_target(
+    client: &ricq::Client,
+    chain: &MessageChain,
+    target: MessageTarget,
+) -> Result<MessageChain> {
+    let mut result = MessageChain::default();
+    for elem in chain.clone() {
+        match elem {
+            RQElem::Text(text) => result.push(text),
+            RQElem::At(at) => match target {
+                MessageTarget::Private(_) => result.push(Text::new(at.display)),
+                _ => result.push(at),
+            },
+            RQElem::Face(face) => result.push(face),
+            RQElem::MarketFace(face) => result.push(face),
+            RQElem::Dice(dice) => result.push(dice),
+            RQElem::FingerGuessing(finger_guessing) => result.push(finger_guessing),
+            RQElem::LightApp(app) => result.push(app),
+            RQElem::RichMsg(msg) => result.push(msg),
+            RQElem::GroupImage(image) => match target {
+                // 群图片在群之间可以直接使用
+                MessageTarget::Group(..) => result.push(image),
+                _ => {
+                    push_image(client, &mut result, ImageElement::GroupImage(image), target).await?
+                }
+            },
+            RQElem::FriendImage(image) => {
+                push_image(
+                    client,
+                    &mut result,
+                    ImageElement::FriendImage(image),
+                    target,
+                )
+                .await?
+            }
+            RQElem::FlashImage(image) => {
+                push_image(client, &mut result, ImageElement::FlashImage(image), target).await?
+            }
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+async fn push_image(
+    client: &ricq::Client,
+    chain: &mut MessageChain,
+    image: ImageElement,
+    target: MessageTarget,
+) -> Result<()> {
+    if let MessageTarget::GroupTemp(..) = target {
+        chain.push(Text::new("[图片]".to_owned()));
+        return Ok(());
+    }
+    let data = download_bytes(&image.url(), &DownloadOptions::default()).await?;
+    let data = preprocess_upload_image(client, &data).await?;
+    let upload = match target {
+        MessageTarget::Group(group_code, _) => {
+            UploadImage::GroupImage(client.upload_group_image(group_code, &data).await?)
+        }
+        MessageTarget::Private(uin) => {
+            UploadImage::FriendImage(client.upload_friend_image(uin, &data).await?)
+        }
+        MessageTarget::GroupTemp(..) => unreachable!(),
+    };
+    if image.is_flash() {
+        let flash: FlashImage = upload.into_flash();
+        chain.push(flash);
+    } else {
+        chain.push(upload);
+    }
+    Ok(())
+}
+
+/// 转发收到的消息
+#[async_trait]
+pub trait MessageForwardTrait: MessageChainPointTrait {
+    /// 将这条消息转发到target, 例如转发给管理员
+    async fn forward_to<T: MessageTargetTrait>(&self, target: &T) -> Result<MessageReceipt>;
+}
+
+#[async_trait]
+impl<E: Send + Sync> MessageForwardTrait for EventWithClient<E>
+where
+    EventWithClient<E>: MessageChainPointTrait,
+{
+    async fn forward_to<T: MessageTargetTrait>(&self, target: &T) -> Result<MessageReceipt> {
+        let chain =
+            rebuild_chain_for_target(&self.client, self.message_chain(), target.target()).await?;
+        Ok(self.client.send_message_to_target(target, chain).await?)
+    }
+}
+
+#[async_trait]
+impl MessageForwardTrait for MessageEvent {
+    async fn forward_to<T: MessageTargetTrait>(&self, target: &T) -> Result<MessageReceipt> {
+        let client = self.client();
+        let chain =
+            rebuild_chain_for_target(&client, self.message_chain(), target.target()).await?;
+        Ok(client.send_message_to_target(target, chain).await?)
+    }
+}
+
+/// 合并转发消息的构造器
+///
+/// 未设置发送者的节点使用机器人自己作为发送者, 可以嵌套合并转发.
+///
+/// ```ignore
+/// let forward = ForwardMessageBuilder::new()
+///     .sender(10000, "日志")
+///     .text("第一条")
+///     .message("第二条".parse_message_chain())
+///     .message_from(master_uin, "主人", "第三条".parse_message_chain())
+///     .forward("详细信息", ForwardMessageBuilder::new().text("..."));
+/// event.client.send_forward_message_to_group(group_code, forward).await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ForwardMessageBuilder {
+    sender: Option<(i64, String)>,
+    time: Option<i32>,
+    nodes: Vec<ForwardBuilderNode>,
+}
+
+#[derive(Debug, Clone)]
+enum ForwardBuilderNode {
+    Message(Option<(i64, String)>, MessageChain),
+    // (发送者, 显示的名称, 内容)
+    Forward(Option<i64>, String, ForwardMessageBuilder),
+}
+
+impl ForwardMessageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 之后添加的节点的发送者
+    pub fn sender(mut self, sender_id: i64, sender_name: impl Into<String>) -> Self {
+        self.sender = Some((sender_id, sender_name.into()));
+        self
+    }
+
+    /// 节点显示的时间 (unix秒), 默认为发送时的时间
+    pub fn time(mut self, time: i32) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn message<S: Into<MessageChain>>(mut self, message: S) -> Self {
+        self.nodes.push(ForwardBuilderNode::Message(
+            self.sender.clone(),
+            message.into(),
+        ));
+        self
+    }
+
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.message(MessageChain::new(Text::new(text.into())))
+    }
+
+    /// 指定发送者的节点, 不影响之后的节点
+    pub fn message_from<S: Into<MessageChain>>(
+        mut self,
+        sender_id: i64,
+        sender_name: impl Into<String>,
+        message: S,
+    ) -> Self {
+        self.nodes.push(ForwardBuilderNode::Message(
+            Some((sender_id, sender_name.into())),
+            message.into(),
+        ));
+        self
+    }
+
+    /// 嵌套的合并转发, sender_name为显示的发送者名称
+    pub fn forward(
+        mut self,
+        sender_name: impl Into<String>,
+        forward: ForwardMessageBuilder,
+    ) -> Self {
+        let sender_id = self.sender.as_ref().map(|(sender_id, _)| *sender_id);
+        self.nodes.push(ForwardBuilderNode::Forward(
+            sender_id,
+            sender_name.into(),
+            forward,
+        ));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 生成ricq的合并转发节点, 未设置发送者的节点使用bot_uin
+    pub fn build(self, bot_uin: i64, bot_name: &str) -> Vec<ForwardMessage> {
+        let time = self
+            .time
+            .unwrap_or_else(|| chrono::Utc::now().timestamp() as i32);
+        self.nodes
+            .into_iter()
+            .map(|node| match node {
+                ForwardBuilderNode::Message(sender, elements) => {
+                    let (sender_id, sender_name) =
+                        sender.unwrap_or_else(|| (bot_uin, bot_name.to_owned()));
+                    ForwardMessage::Message(MessageNode {
+                        sender_id,
+                        time,
+                        sender_name,
+                        elements,
+                    })
+                }
+                ForwardBuilderNode::Forward(sender_id, sender_name, forward) => {
+                    ForwardMessage::Forward(ForwardNode {
+                        sender_id: sender_id.unwrap_or(bot_uin),
+                        time,
+                        sender_name,
+                        nodes: forward.build(bot_uin, bot_name),
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+/// 发送和下载合并转发消息
+#[async_trait]
+pub trait ForwardMessageTrait: Send + Sync {
+    async fn send_forward_message_to_group(
+        &self,
+        group_code: i64,
+        forward: ForwardMessageBuilder,
+    ) -> RQResult<MessageReceipt>;
+
+    /// 发送合并转发到消息来源, 目前只支持群
+    async fn send_forward_message_to_target(
+        &self,
+        target: &impl MessageTargetTrait,
+        forward: ForwardMessageBuilder,
+    ) -> RQResult<MessageReceipt> {
+        match target.target() {
+            MessageTarget::Group(group_code, _) => {
+                self.send_forward_message_to_group(group_code, forward)
+                    .await
+            }
+            _ => Err(RQError::Other("合并转发只能发送到群".to_owned())),
+        }
+    }
+
+    /// 下载收到的合并转发的内容, res_id见`ForwardMessageParseTrait::forward_res_id`
+    async fn download_forward_message(&self, res_id: &str) -> RQResult<Vec<ForwardMessage>>;
+}
+
+#[async_trait]
+impl ForwardMessageTrait for ricq::Client {
+    async fn send_forward_message_to_group(
+        &self,
+        group_code: i64,
+        forward: ForwardMessageBuilder,
+    ) -> RQResult<MessageReceipt> {
+        let bot_uin = self.uin().await;
+        let bot_name = self.account_info.read().await.nickname.clone();
+        self.send_group_forward_message(group_code, forward.build(bot_uin, &bot_name))
+            .await
+    }
+
+    async fn download_forward_message(&self, res_id: &str) -> RQResult<Vec<ForwardMessage>> {
+        self.download_msgs(res_id.to_owned()).await
+    }
+}
+
+#[async_trait]
+impl ForwardMessageTrait for crate::Client {
+    async fn send_forward_message_to_group(
+        &self,
+        group_code: i64,
+        forward: ForwardMessageBuilder,
+    ) -> RQResult<MessageReceipt> {
+        self.rq_client
+            .send_forward_message_to_group(group_code, forward)
+            .await
+    }
+
+    async fn download_forward_message(&self, res_id: &str) -> RQResult<Vec<ForwardMessage>> {
+        self.rq_client.download_forward_message(res_id).await
+    }
+}
+
+/// 识别收到的合并转发消息
+///
+/// ```ignore
+/// if let Some(res_id) = event.forward_res_id() {
+///     let nodes = event.client.download_forward_message(&res_id).await?;
+///     for node in flatten_forward_message(&nodes) {
+///         tracing::info!("{}: {}", node.sender_name, node.elements.message_content());
+///     }
+/// }
+/// ```
+pub trait ForwardMessageParseTrait: MessageChainPointTrait {
+    /// 消息是合并转发时返回用于下载内容的res_id
+    fn forward_res_id(&self) -> Option<String> {
+        self.message_chain()
+            .clone()
+            .into_iter()
+            .find_map(|elem| match elem {
+                RQElem::RichMsg(msg) if msg.service_id == 35 => {
+                    let start = msg.template1.find("m_resid=\"")? + "m_resid=\"".len();
+                    let end = msg.template1[start..].find('"')? + start;
+                    let res_id = &msg.template1[start..end];
+                    if res_id.is_empty() {
+                        None
+                    } else {
+                        Some(res_id.to_owned())
+                    }
+                }
+                _ => None,
+            })
+    }
+}
+
+impl<T: MessageChainPointTrait + ?Sized> ForwardMessageParseTrait for T {}
+
+/// 展开嵌套的合并转发, 按顺序返回所有消息节点
+pub fn flatten_forward_message(nodes: &[ForwardMessage]) -> Vec<&MessageNode> {
+    let mut result = vec![];
+    for node in nodes {
+        match node {
+            ForwardMessage::Message(message) => result.push(message),
+            ForwardMessage::Forward(forward) => {
+                result.extend(flatten_forward_message(&forward.nodes))
+            }
+        }
+    }
+    result
+}
@@ -1,6 +1,10 @@
+use async_trait::async_trait;
+use ricq_core::msg::elem::RQElem;
 use ricq_core::msg::MessageChain;
 use ricq_core::pb::msg::elem::Elem;
 
+use crate::{ClientTrait, MessageChainPointTrait};
+
 pub trait MessageChainAppendTrait {
     fn append<S: Into<Vec<Elem>>>(self, elem: S) -> Self;
 }
@@ -12,3 +16,71 @@ impl MessageChainAppendTrait for MessageChain {
         chain
     }
 }
+
+/// 消息中的@, 对MessageChain和消息事件都可以使用
+pub trait MessageChainAtTrait {
+    /// 被@的QQ号, 按出现的顺序, 不包括@全体成员
+    fn ats(&self) -> Vec<i64>;
+    fn is_at(&self, uin: i64) -> bool;
+    fn is_at_all(&self) -> bool;
+}
+
+impl<T: MessageChainPointTrait + ?Sized> MessageChainAtTrait for T {
+    fn ats(&self) -> Vec<i64> {
+        let mut ats = vec![];
+        for elem in self.message_chain().clone() {
+            if let RQElem::At(at) = elem {
+                if at.target != 0 && !ats.contains(&at.target) {
+                    ats.push(at.target);
+                }
+            }
+        }
+        ats
+    }
+
+    fn is_at(&self, uin: i64) -> bool {
+        self.ats().contains(&uin)
+    }
+
+    fn is_at_all(&self) -> bool {
+        self.message_chain()
+            .clone()
+            .into_iter()
+            .any(|elem| matches!(elem, RQElem::At(at) if at.target == 0))
+    }
+}
+
+/// 只包含文字的内容, 图片、@、表情等都会被忽略.
+/// `message_content`会包含这些元素的占位符, 解析指令时可以使用这里的方法.
+pub trait MessageChainTextTrait {
+    /// 直接拼接所有文字
+    fn plain_text(&self) -> String {
+        self.plain_text_with("")
+    }
+
+    /// 使用分隔符拼接所有文字
+    fn plain_text_with(&self, separator: &str) -> String;
+}
+
+impl<T: MessageChainPointTrait + ?Sized> MessageChainTextTrait for T {
+    fn plain_text_with(&self, separator: &str) -> String {
+        let mut texts = vec![];
+        for elem in self.message_chain().clone() {
+            if let RQElem::Text(text) = elem {
+                texts.push(text.content);
+            }
+        }
+        texts.join(separator)
+    }
+}
+
+#[async_trait]
+pub trait MentionMeTrait: MessageChainPointTrait + ClientTrait {
+    /// 消息中是否@了机器人, 不包括@全体成员
+    async fn mentions_me(&self) -> bool {
+        let bot_uin = self.bot_uin().await;
+        self.is_at(bot_uin)
+    }
+}
+
+impl<T: MessageChainPointTrait + ClientTrait> MentionMeTrait for T {}
@@ -7,10 +7,13 @@ use ricq_core::structs::{
     FriendMessage, GroupInfo, GroupMessage, GroupTempMessage, MessageReceipt,
 };
 use ricq_core::{RQError, RQResult};
+use serde_derive::{Deserialize, Serialize};
 use std::time::Duration;
 
-use crate::{ClientTrait, MessageEvent};
+use crate::features::preprocess_upload_image;
+use crate::{record_audit, AuditAction, AuditEntry, ClientTrait, MessageEvent};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessageTarget {
     // Group(group_code,uin)
     Group(i64, i64),
@@ -20,11 +23,22 @@ pub enum MessageTarget {
     GroupTemp(i64, i64),
 }
 
+#[derive(Clone)]
 pub enum UploadImage {
     FriendImage(FriendImage),
     GroupImage(GroupImage),
 }
 
+impl UploadImage {
+    /// 作为闪照发送
+    pub fn into_flash(self) -> FlashImage {
+        match self {
+            UploadImage::FriendImage(i) => FlashImage::FriendImage(i),
+            UploadImage::GroupImage(i) => FlashImage::GroupImage(i),
+        }
+    }
+}
+
 impl Into<Vec<Elem>> for UploadImage {
     fn into(self) -> Vec<Elem> {
         match self {
@@ -38,6 +52,12 @@ pub trait MessageTargetTrait: Send + Sync {
     fn target(&self) -> MessageTarget;
 }
 
+impl MessageTargetTrait for MessageTarget {
+    fn target(&self) -> MessageTarget {
+        *self
+    }
+}
+
 pub trait MessageChainPointTrait: Send + Sync {
     fn message_chain(&self) -> &MessageChain;
 }
@@ -162,9 +182,10 @@ impl MessageSendToSourceTrait for GroupMessageEvent {
         &self,
         data: S,
     ) -> RQResult<UploadImage> {
+        let data = preprocess_upload_image(&self.client, data.as_ref()).await?;
         Ok(UploadImage::GroupImage(
             self.client
-                .upload_group_image(self.inner.group_code, data.as_ref())
+                .upload_group_image(self.inner.group_code, &data)
                 .await?,
         ))
     }
@@ -262,7 +283,13 @@ impl MessageRecallTrait for GroupMessageEvent {
     async fn recall(&self, receipt: MessageReceipt) -> RQResult<()> {
         self.client
             .recall_group_message(self.inner.group_code, receipt.seqs, receipt.rands)
-            .await
+            .await?;
+        record_audit(
+            &self.client,
+            AuditEntry::new(AuditAction::Recall).group_code(self.inner.group_code),
+        )
+        .await;
+        Ok(())
     }
 }
 
@@ -307,9 +334,10 @@ impl MessageSendToSourceTrait for FriendMessageEvent {
         &self,
         data: S,
     ) -> RQResult<UploadImage> {
+        let data = preprocess_upload_image(&self.client, data.as_ref()).await?;
         Ok(UploadImage::FriendImage(
             self.client
-                .upload_friend_image(self.inner.from_uin, data.as_ref())
+                .upload_friend_image(self.inner.from_uin, &data)
                 .await?,
         ))
     }
@@ -1,11 +1,25 @@
+pub use audio_trait::*;
 pub use client_trait::*;
+pub use contact_trait::*;
+pub use forward_trait::*;
 pub use group_trait::*;
 pub use member_trait::*;
 pub use message_chain_trait::*;
 pub use message_trait::*;
+pub use poke_trait::*;
+pub use recall_trait::*;
+pub use reply_trait::*;
+pub use upload_trait::*;
 
+mod audio_trait;
 mod client_trait;
+mod contact_trait;
+mod forward_trait;
 mod group_trait;
 mod member_trait;
 mod message_chain_trait;
 mod message_trait;
+mod poke_trait;
+mod recall_trait;
+mod reply_trait;
+mod upload_trait;
@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use ricq::client::event::{FriendPokeEvent, GroupPokeEvent};
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use ricq_core::RQResult;
+
+use crate::{ClientTrait, MessageTarget, MessageTargetTrait};
+
+/// 戳一戳事件
+///
+/// ```ignore
+/// #[event]
+/// async fn on_poke(event: &GroupPokeEvent) -> anyhow::Result<bool> {
+///     if !event.is_poke_me().await {
+///         return Ok(false);
+///     }
+///     event.poke_back().await?;
+///     event.reply("戳我干嘛").await?;
+///     Ok(true)
+/// }
+/// ```
+#[async_trait]
+pub trait PokeTrait: MessageTargetTrait {
+    /// 发起戳一戳的人
+    fn sender(&self) -> i64;
+
+    /// 被戳的人
+    fn receiver(&self) -> i64;
+
+    fn poke_client(&self) -> &ricq::Client;
+
+    /// 被戳的是否为机器人自己
+    async fn is_poke_me(&self) -> bool {
+        self.receiver() == self.poke_client().uin().await
+    }
+
+    /// 戳回发起者, 群中戳一戳在群中戳回, 好友戳一戳在私聊中戳回
+    async fn poke_back(&self) -> RQResult<()>;
+
+    /// 发送消息到戳一戳发生的群或私聊
+    async fn reply<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        message: S,
+    ) -> RQResult<MessageReceipt> {
+        self.poke_client()
+            .send_message_to_target(&self.target(), message)
+            .await
+    }
+}
+
+impl MessageTargetTrait for GroupPokeEvent {
+    fn target(&self) -> MessageTarget {
+        MessageTarget::Group(self.inner.group_code, self.inner.sender)
+    }
+}
+
+#[async_trait]
+impl PokeTrait for GroupPokeEvent {
+    fn sender(&self) -> i64 {
+        self.inner.sender
+    }
+
+    fn receiver(&self) -> i64 {
+        self.inner.receiver
+    }
+
+    fn poke_client(&self) -> &ricq::Client {
+        &self.client
+    }
+
+    async fn poke_back(&self) -> RQResult<()> {
+        self.client
+            .group_poke(self.inner.group_code, self.inner.sender)
+            .await
+    }
+}
+
+impl MessageTargetTrait for FriendPokeEvent {
+    fn target(&self) -> MessageTarget {
+        MessageTarget::Private(self.inner.sender)
+    }
+}
+
+#[async_trait]
+impl PokeTrait for FriendPokeEvent {
+    fn sender(&self) -> i64 {
+        self.inner.sender
+    }
+
+    fn receiver(&self) -> i64 {
+        self.inner.receiver
+    }
+
+    fn poke_client(&self) -> &ricq::Client {
+        &self.client
+    }
+
+    async fn poke_back(&self) -> RQResult<()> {
+        self.client.friend_poke(self.inner.sender).await
+    }
+}
@@ -0,0 +1,193 @@
+use async_trait::async_trait;
+use ricq::client::event::GroupMessageEvent;
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use ricq_core::{RQError, RQResult};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::{
+    record_audit, AuditAction, AuditEntry, ClientTrait, MessageEvent, MessageTarget,
+    MessageTargetTrait,
+};
+
+/// 撤回自己发送的消息, 发送时的目标需要和回执一起传入
+///
+/// ```ignore
+/// let receipt = client.send_message_to_group(group_code, chain).await?;
+/// receipt.recall(&client, &MessageTarget::Group(group_code, 0)).await?;
+/// ```
+#[async_trait]
+pub trait ReceiptRecallTrait {
+    async fn recall(&self, client: &ricq::Client, target: &impl MessageTargetTrait)
+        -> RQResult<()>;
+
+    /// 在ttl后撤回, 撤回失败时只记录日志
+    fn recall_after(
+        &self,
+        client: Arc<ricq::Client>,
+        target: &impl MessageTargetTrait,
+        ttl: Duration,
+    ) -> JoinHandle<()>;
+}
+
+#[async_trait]
+impl ReceiptRecallTrait for MessageReceipt {
+    async fn recall(
+        &self,
+        client: &ricq::Client,
+        target: &impl MessageTargetTrait,
+    ) -> RQResult<()> {
+        recall_receipt(client, target.target(), self.clone()).await
+    }
+
+    fn recall_after(
+        &self,
+        client: Arc<ricq::Client>,
+        target: &impl MessageTargetTrait,
+        ttl: Duration,
+    ) -> JoinHandle<()> {
+        let target = target.target();
+        let receipt = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            if let Err(err) = recall_receipt(&client, target, receipt).await {
+                tracing::warn!("自动撤回消息失败 : {:?} : {:?}", target, err);
+            }
+        })
+    }
+}
+
+async fn recall_receipt(
+    client: &ricq::Client,
+    target: MessageTarget,
+    receipt: MessageReceipt,
+) -> RQResult<()> {
+    match target {
+        MessageTarget::Group(group_code, _) => {
+            client
+                .recall_group_message(group_code, receipt.seqs, receipt.rands)
+                .await
+        }
+        MessageTarget::Private(uin) => {
+            client
+                .recall_friend_message(uin, receipt.time, receipt.seqs, receipt.rands)
+                .await
+        }
+        MessageTarget::GroupTemp(..) => Err(RQError::Other(
+            "recall for group temp message is not supported".to_owned(),
+        )),
+    }
+}
+
+/// 发送临时消息, 在ttl后自动撤回
+///
+/// ```ignore
+/// event
+///     .client
+///     .send_message_to_group_with_ttl(group_code, "30秒后撤回", Duration::from_secs(30))
+///     .await?;
+/// ```
+#[async_trait]
+pub trait AutoRecallTrait: Send + Sync {
+    async fn send_message_with_ttl<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        message: S,
+        ttl: Duration,
+    ) -> RQResult<MessageReceipt>;
+
+    async fn send_message_to_group_with_ttl<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        group_code: i64,
+        message: S,
+        ttl: Duration,
+    ) -> RQResult<MessageReceipt> {
+        self.send_message_with_ttl(&MessageTarget::Group(group_code, 0), message, ttl)
+            .await
+    }
+
+    async fn send_message_to_friend_with_ttl<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        uin: i64,
+        message: S,
+        ttl: Duration,
+    ) -> RQResult<MessageReceipt> {
+        self.send_message_with_ttl(&MessageTarget::Private(uin), message, ttl)
+            .await
+    }
+}
+
+#[async_trait]
+impl AutoRecallTrait for Arc<ricq::Client> {
+    async fn send_message_with_ttl<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        message: S,
+        ttl: Duration,
+    ) -> RQResult<MessageReceipt> {
+        let receipt = self.send_message_to_target(target, message).await?;
+        receipt.recall_after(self.clone(), target, ttl);
+        Ok(receipt)
+    }
+}
+
+#[async_trait]
+impl AutoRecallTrait for crate::Client {
+    async fn send_message_with_ttl<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        target: &impl MessageTargetTrait,
+        message: S,
+        ttl: Duration,
+    ) -> RQResult<MessageReceipt> {
+        self.rq_client
+            .send_message_with_ttl(target, message, ttl)
+            .await
+    }
+}
+
+/// 撤回收到的消息, 机器人需要是群管理员
+///
+/// ```ignore
+/// if is_spam(&event) {
+///     event.recall_message().await?;
+/// }
+/// ```
+#[async_trait]
+pub trait ReceivedRecallTrait {
+    async fn recall_message(&self) -> RQResult<()>;
+}
+
+#[async_trait]
+impl ReceivedRecallTrait for GroupMessageEvent {
+    async fn recall_message(&self) -> RQResult<()> {
+        self.client
+            .recall_group_message(
+                self.inner.group_code,
+                self.inner.seqs.clone(),
+                self.inner.rands.clone(),
+            )
+            .await?;
+        record_audit(
+            &self.client,
+            AuditEntry::new(AuditAction::Recall)
+                .group_code(self.inner.group_code)
+                .target(self.inner.from_uin),
+        )
+        .await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReceivedRecallTrait for MessageEvent {
+    async fn recall_message(&self) -> RQResult<()> {
+        match self {
+            MessageEvent::GroupMessage(event) => event.recall_message().await,
+            _ => Err(RQError::Other(
+                "only group messages can be recalled".to_owned(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq_core::msg::elem::Reply;
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use ricq_core::RQResult;
+
+use crate::{MessageChainAppendTrait, MessageEvent, MessageSendToSourceTrait, TextEleParseTrait};
+
+/// 回复消息
+#[async_trait]
+pub trait MessageReplyTrait: MessageSendToSourceTrait {
+    /// 引用这条消息的元素
+    fn quote(&self) -> Reply;
+
+    /// 引用这条消息并回复到消息来源
+    async fn reply<S: Into<MessageChain> + Send + Sync>(
+        &self,
+        message: S,
+    ) -> RQResult<MessageReceipt> {
+        let mut chain = MessageChain::default().append(self.quote());
+        chain.0.extend(message.into().0);
+        self.send_message_to_source(chain).await
+    }
+
+    /// 直接发送文字到消息来源, 不引用
+    async fn reply_text(&self, text: &str) -> RQResult<MessageReceipt> {
+        self.send_message_to_source(MessageChain::new(text.parse_text()))
+            .await
+    }
+}
+
+impl MessageReplyTrait for GroupMessageEvent {
+    fn quote(&self) -> Reply {
+        Reply {
+            reply_seq: self.inner.seqs.first().copied().unwrap_or_default(),
+            sender: self.inner.from_uin,
+            time: self.inner.time,
+            elements: self.inner.elements.clone(),
+        }
+    }
+}
+
+impl MessageReplyTrait for FriendMessageEvent {
+    fn quote(&self) -> Reply {
+        Reply {
+            reply_seq: self.inner.seqs.first().copied().unwrap_or_default(),
+            sender: self.inner.from_uin,
+            time: self.inner.time,
+            elements: self.inner.elements.clone(),
+        }
+    }
+}
+
+impl MessageReplyTrait for GroupTempMessageEvent {
+    fn quote(&self) -> Reply {
+        Reply {
+            reply_seq: self.inner.seqs.first().copied().unwrap_or_default(),
+            sender: self.inner.from_uin,
+            time: self.inner.time,
+            elements: self.inner.elements.clone(),
+        }
+    }
+}
+
+impl MessageReplyTrait for MessageEvent {
+    fn quote(&self) -> Reply {
+        match self {
+            MessageEvent::GroupMessage(event) => event.quote(),
+            MessageEvent::FriendMessage(event) => event.quote(),
+            MessageEvent::GroupTempMessage(event) => event.quote(),
+        }
+    }
+}
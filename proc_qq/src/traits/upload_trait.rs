@@ -0,0 +1,145 @@
+use crate::{
+    download_bytes, DownloadOptions, ExtensionsTrait, ImageData, ImageUploadCache,
+    MessageSendToSourceTrait, MessageTargetTrait, UploadImage,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::Url;
+use ricq_core::msg::MessageChain;
+use ricq_core::structs::MessageReceipt;
+use std::path::{Path, PathBuf};
+
+/// 可以用于上传的数据来源: 内存中的数据, 文件路径, 或者网址
+#[async_trait]
+pub trait UploadSource: Send + Sync {
+    async fn into_bytes(self) -> Result<Bytes>;
+}
+
+#[async_trait]
+impl UploadSource for Bytes {
+    async fn into_bytes(self) -> Result<Bytes> {
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl UploadSource for Vec<u8> {
+    async fn into_bytes(self) -> Result<Bytes> {
+        Ok(Bytes::from(self))
+    }
+}
+
+#[async_trait]
+impl UploadSource for &[u8] {
+    async fn into_bytes(self) -> Result<Bytes> {
+        Ok(Bytes::copy_from_slice(self))
+    }
+}
+
+#[async_trait]
+impl UploadSource for ImageData {
+    async fn into_bytes(self) -> Result<Bytes> {
+        Ok(self.data)
+    }
+}
+
+#[async_trait]
+impl UploadSource for &Path {
+    async fn into_bytes(self) -> Result<Bytes> {
+        Ok(Bytes::from(tokio::fs::read(self).await?))
+    }
+}
+
+#[async_trait]
+impl UploadSource for PathBuf {
+    async fn into_bytes(self) -> Result<Bytes> {
+        self.as_path().into_bytes().await
+    }
+}
+
+#[async_trait]
+impl UploadSource for Url {
+    async fn into_bytes(self) -> Result<Bytes> {
+        download_bytes(self.as_str(), &DownloadOptions::default()).await
+    }
+}
+
+#[async_trait]
+impl UploadSource for &Url {
+    async fn into_bytes(self) -> Result<Bytes> {
+        download_bytes(self.as_str(), &DownloadOptions::default()).await
+    }
+}
+
+/// 从任意来源上传图片到消息来源
+///
+/// 设置了`ClientBuilder::image_upload_cache`时, 相同的图片只会上传一次.
+#[async_trait]
+pub trait UploadImageTrait:
+    MessageSendToSourceTrait + MessageTargetTrait + ExtensionsTrait
+{
+    async fn upload_image<S: UploadSource>(&self, source: S) -> Result<UploadImage> {
+        let data = source.into_bytes().await?;
+        let cache = self.extensions().get::<ImageUploadCache>();
+        let cache = match cache {
+            Some(cache) => cache,
+            None => return Ok(self.upload_image_to_source(data).await?),
+        };
+        let target = self.target();
+        let md5 = md5::compute(&data).0;
+        if let Some(image) = cache.get(&target, md5) {
+            return Ok(image);
+        }
+        let image = self.upload_image_to_source(data).await?;
+        cache.insert(&target, md5, image.clone());
+        Ok(image)
+    }
+
+    /// 下载并上传图片, 超过`DownloadOptions::max_bytes`时放弃
+    async fn upload_image_from_url(
+        &self,
+        url: &str,
+        options: &DownloadOptions,
+    ) -> Result<UploadImage> {
+        let data = download_bytes(url, options).await?;
+        self.upload_image(data).await
+    }
+
+    async fn upload_image_from_path<P: AsRef<Path> + Send>(&self, path: P) -> Result<UploadImage> {
+        self.upload_image(path.as_ref()).await
+    }
+
+    /// 上传并发送图片
+    async fn send_image_to_source<S: UploadSource>(&self, source: S) -> Result<MessageReceipt> {
+        let image = self.upload_image(source).await?;
+        Ok(self
+            .send_message_to_source(MessageChain::new(image))
+            .await?)
+    }
+
+    /// 上传并发送闪照
+    async fn send_flash_image_to_source<S: UploadSource>(
+        &self,
+        source: S,
+    ) -> Result<MessageReceipt> {
+        let image = self.upload_image(source).await?.into_flash();
+        Ok(self
+            .send_message_to_source(MessageChain::new(image))
+            .await?)
+    }
+
+    /// 上传多张图片, 在一条消息中发送
+    async fn send_images_to_source<S: UploadSource>(
+        &self,
+        sources: Vec<S>,
+    ) -> Result<MessageReceipt> {
+        let mut chain = MessageChain::default();
+        for source in sources {
+            chain.push(self.upload_image(source).await?);
+        }
+        Ok(self.send_message_to_source(chain).await?)
+    }
+}
+
+impl<T: MessageSendToSourceTrait + MessageTargetTrait + ExtensionsTrait> UploadImageTrait for T {}
@@ -0,0 +1,148 @@
+use crate::{ExtensionsTrait, MessageEvent};
+use async_trait::async_trait;
+use ricq::client::event::{FriendMessageEvent, GroupMessageEvent, GroupTempMessageEvent};
+use ricq_core::msg::elem::{RQElem, Text};
+use ricq_core::msg::MessageChain;
+
+/// 唤醒词, 在群中以唤醒词开头的消息与@机器人的消息一样, 可以触发`#[event(at_me)]`
+///
+/// ```ignore
+/// ClientBuilder::new().wake_words(WakeWords::new().word("小助手").nickname())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WakeWords {
+    words: Vec<String>,
+    nickname: bool,
+}
+
+impl WakeWords {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个唤醒词
+    pub fn word(mut self, word: impl Into<String>) -> Self {
+        self.words.push(word.into());
+        self
+    }
+
+    /// 使用机器人的昵称作为唤醒词
+    pub fn nickname(mut self) -> Self {
+        self.nickname = true;
+        self
+    }
+
+    async fn words(&self, client: &ricq::Client) -> Vec<String> {
+        let mut words = self.words.clone();
+        if self.nickname {
+            let nickname = client.account_info.read().await.nickname.clone();
+            if !nickname.is_empty() {
+                words.push(nickname);
+            }
+        }
+        // 较长的唤醒词优先匹配
+        words.sort_by(|a, b| b.len().cmp(&a.len()));
+        words
+    }
+}
+
+/// 判断消息是否在对机器人说话
+#[async_trait]
+pub trait AddressedTrait: Sized {
+    /// 群消息中@了机器人或以唤醒词开头时, 返回去掉@和唤醒词后的消息, 否则返回None.
+    /// 私聊和临时会话总是返回消息 (同样会去掉开头的唤醒词)
+    async fn addressed_to_me(&self) -> Option<Self>;
+}
+
+#[async_trait]
+impl AddressedTrait for GroupMessageEvent {
+    async fn addressed_to_me(&self) -> Option<Self> {
+        let (elements, addressed) = strip_address(&self.client, &self.inner.elements).await;
+        if !addressed {
+            return None;
+        }
+        let mut event = self.clone();
+        event.inner.elements = elements;
+        Some(event)
+    }
+}
+
+#[async_trait]
+impl AddressedTrait for FriendMessageEvent {
+    async fn addressed_to_me(&self) -> Option<Self> {
+        let (elements, _) = strip_address(&self.client, &self.inner.elements).await;
+        let mut event = self.clone();
+        event.inner.elements = elements;
+        Some(event)
+    }
+}
+
+#[async_trait]
+impl AddressedTrait for GroupTempMessageEvent {
+    async fn addressed_to_me(&self) -> Option<Self> {
+        let (elements, _) = strip_address(&self.client, &self.inner.elements).await;
+        let mut event = self.clone();
+        event.inner.elements = elements;
+        Some(event)
+    }
+}
+
+#[async_trait]
+impl AddressedTrait for MessageEvent {
+    async fn addressed_to_me(&self) -> Option<Self> {
+        Some(match self {
+            MessageEvent::GroupMessage(e) => MessageEvent::GroupMessage(e.addressed_to_me().await?),
+            MessageEvent::FriendMessage(e) => {
+                MessageEvent::FriendMessage(e.addressed_to_me().await?)
+            }
+            MessageEvent::GroupTempMessage(e) => {
+                MessageEvent::GroupTempMessage(e.addressed_to_me().await?)
+            }
+        })
+    }
+}
+
+// 去掉@机器人和开头的唤醒词, 返回(新的消息, 是否@了机器人或使用了唤醒词)
+async fn strip_address(client: &ricq::Client, chain: &MessageChain) -> (MessageChain, bool) {
+    let bot_uin = client.uin().await;
+    let words = match client.extensions().get::<WakeWords>() {
+        Some(wake_words) => wake_words.words(client).await,
+        None => vec![],
+    };
+    let mut result = MessageChain::default();
+    let mut addressed = false;
+    // 还没有遇到正文, 唤醒词只在正文的开头匹配
+    let mut leading = true;
+    for elem in &chain.0 {
+        match RQElem::from(elem.clone()) {
+            RQElem::At(at) if at.target == bot_uin => {
+                addressed = true;
+                continue;
+            }
+            RQElem::At(_) | RQElem::Reply(_) => {}
+            RQElem::Text(text) if leading => {
+                leading = false;
+                let content = text.content.trim_start();
+                let content = match words
+                    .iter()
+                    .find_map(|word| content.strip_prefix(word.as_str()))
+                {
+                    Some(rest) => {
+                        addressed = true;
+                        rest.trim_start_matches(|c: char| {
+                            c.is_whitespace() || c == ',' || c == '，' || c == ':' || c == '：'
+                        })
+                    }
+                    None => content,
+                };
+                if !content.is_empty() {
+                    result.push(Text::new(content.to_owned()));
+                }
+                continue;
+            }
+            _ => leading = false,
+        }
+        result.0.push(elem.clone());
+    }
+    (result, addressed)
+}
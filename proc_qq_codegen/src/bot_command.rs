@@ -7,27 +7,28 @@ use syn::{FnArg, ItemFn, Pat, Type};
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum BotCommandRaw {
     Command(String),
-    Param(String),
+    // (参数名, 写在bot_command中的类型)
+    Param(String, Option<String>),
     Multiple(Vec<BotCommandRawTuple>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum BotCommandRawTuple {
     Command(String),
-    Param(String),
+    Param(String, Option<String>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum BotParamsMather<'a> {
+pub enum BotParamsMather {
     Command(String),
-    Params(&'a syn::Ident, &'a syn::Type),
-    Multiple(Vec<BotParamsMatherTuple<'a>>),
+    Params(syn::Ident, syn::Type),
+    Multiple(Vec<BotParamsMatherTuple>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum BotParamsMatherTuple<'a> {
+pub enum BotParamsMatherTuple {
     Command(String),
-    Params(&'a syn::Ident, &'a syn::Type),
+    Params(syn::Ident, syn::Type),
 }
 
 // 解析命令行
@@ -37,8 +38,12 @@ pub(crate) fn parse_bot_command(
 ) -> Option<Vec<BotCommandRaw>> {
     // 由固定字符串和参数组合而成
     if let Some(bot_command) = bot_command {
-        let element_reg_str =
-            r#"([A-Za-z0-9_/\p{Han}\p{Hiragana}\p{Katakana}]+)|(\{([A-Za-z_]([A-Za-z0-9_]+)?)\})"#;
+        // 参数可以写上类型 {user: At}, 去掉冒号后的空白, 以免被当作分隔符
+        let bot_command = regex::Regex::new(r#"\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*:\s*"#)
+            .expect("bot_command正则表达式编译失败")
+            .replace_all(&bot_command, "{$1:")
+            .to_string();
+        let element_reg_str = r#"([A-Za-z0-9_/\p{Han}\p{Hiragana}\p{Katakana}]+)|(\{([A-Za-z_][A-Za-z0-9_]*)(:[^{}\s]+)?\})"#;
         let elements_reg = regex::Regex::new(format!("^({})+$", element_reg_str).as_str())
             .expect("bot_command正则表达式编译失败");
         let element_reg =
@@ -59,9 +64,13 @@ pub(crate) fn parse_bot_command(
             while let Some(element_str) = element_strs.next() {
                 let element_str = element_str.as_str();
                 if element_str.starts_with('{') {
-                    bot_command_elements.push(BotCommandRawTuple::Param(
-                        element_str[1..element_str.len() - 1].to_string(),
-                    ));
+                    let param = &element_str[1..element_str.len() - 1];
+                    bot_command_elements.push(match param.split_once(':') {
+                        Some((name, ty)) => {
+                            BotCommandRawTuple::Param(name.to_string(), Some(ty.to_string()))
+                        }
+                        None => BotCommandRawTuple::Param(param.to_string(), None),
+                    });
                 } else {
                     bot_command_elements.push(BotCommandRawTuple::Command(element_str.to_string()));
                 }
@@ -76,7 +85,9 @@ pub(crate) fn parse_bot_command(
             if bot_command_elements.len() == 1 {
                 bot_command_items.push(match bot_command_elements.first().unwrap() {
                     BotCommandRawTuple::Command(tmp) => BotCommandRaw::Command(tmp.clone()),
-                    BotCommandRawTuple::Param(tmp) => BotCommandRaw::Param(tmp.clone()),
+                    BotCommandRawTuple::Param(tmp, ty) => {
+                        BotCommandRaw::Param(tmp.clone(), ty.clone())
+                    }
                 });
             } else {
                 bot_command_items.push(BotCommandRaw::Multiple(bot_command_elements))
@@ -93,15 +104,15 @@ pub(crate) fn parse_bot_args<'a>(
     method: &'a ItemFn,
     args: &'a [&'a FnArg],
     items: Option<Vec<BotCommandRaw>>,
-) -> Option<Vec<BotParamsMather<'a>>> {
+) -> Option<Vec<BotParamsMather>> {
     if let Some(items) = items {
         let mut result = vec![];
-        let mut args_iter = args.iter();
+        let mut args_iter = args.iter().peekable();
         for item in items {
             result.push(match item {
                 BotCommandRaw::Command(tmp) => BotParamsMather::Command(tmp),
-                BotCommandRaw::Param(tmp) => {
-                    let (pat, ty) = take_param(method, args_iter.next(), tmp.as_str());
+                BotCommandRaw::Param(tmp, ty) => {
+                    let (pat, ty) = take_typed_param(method, &mut args_iter, &tmp, ty);
                     BotParamsMather::Params(pat, ty)
                 }
                 BotCommandRaw::Multiple(multiple) => {
@@ -111,8 +122,8 @@ pub(crate) fn parse_bot_args<'a>(
                             BotCommandRawTuple::Command(tmp) => {
                                 BotParamsMatherTuple::Command(tmp.clone())
                             }
-                            BotCommandRawTuple::Param(tmp) => {
-                                let (pat, ty) = take_param(method, args_iter.next(), tmp.as_str());
+                            BotCommandRawTuple::Param(tmp, ty) => {
+                                let (pat, ty) = take_typed_param(method, &mut args_iter, &tmp, ty);
                                 BotParamsMatherTuple::Params(pat, ty)
                             }
                         })
@@ -127,6 +138,50 @@ pub(crate) fn parse_bot_args<'a>(
     }
 }
 
+// bot_command中写了类型时, 方法中可以省略这个参数; 没有省略时类型必须一致
+fn take_typed_param<'a, I: Iterator<Item = &'a &'a FnArg>>(
+    method: &'a ItemFn,
+    args_iter: &mut std::iter::Peekable<I>,
+    tmp: &str,
+    ty: Option<String>,
+) -> (Ident, Type) {
+    let ty = match ty {
+        Some(ty) => ty,
+        None => {
+            let (pat, ty) = take_param(method, args_iter.next(), tmp);
+            return (pat.clone(), ty.clone());
+        }
+    };
+    let ty: Type = match syn::parse_str(&ty) {
+        Ok(ty) => ty,
+        Err(_) => abort!(
+            &method.sig.ident.span(),
+            "bot_command中的参数类型不正确 : {}: {}",
+            tmp,
+            ty
+        ),
+    };
+    let declared = match args_iter.peek() {
+        Some(FnArg::Typed(t)) => match t.pat.deref() {
+            Pat::Ident(pi) => pi.ident.to_string().eq(tmp),
+            _ => false,
+        },
+        _ => false,
+    };
+    if !declared {
+        return (Ident::new(tmp, method.sig.ident.span()), ty);
+    }
+    let (pat, declared_ty) = take_param(method, args_iter.next(), tmp);
+    if quote::quote!(#declared_ty).to_string() != quote::quote!(#ty).to_string() {
+        abort!(
+            &method.sig.ident.span(),
+            "bot_command中的参数类型与方法中的不一致 : {}",
+            tmp,
+        );
+    }
+    (pat.clone(), ty)
+}
+
 fn take_param<'a>(method: &'a ItemFn, arg: Option<&&'a FnArg>, tmp: &str) -> (&'a Ident, &'a Type) {
     if let Some(arg) = arg {
         match arg {
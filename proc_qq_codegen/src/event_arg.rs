@@ -16,6 +16,18 @@ pub(crate) enum EventArg {
     TrimRegexp(String),
     TrimEq(String),
     BotCommand(String),
+    AtMe,
+    RateLimit(String),
+    RateLimitScope(String),
+    RateLimitOnLimit(String),
+}
+
+/// rate_limit = "5/60s", scope = "per_sender", on_limit = "drop"
+pub(crate) struct RateLimitArgs {
+    // 由proc_qq::parse_rate_limit在编译期解析
+    pub(crate) spec: String,
+    pub(crate) scope: proc_macro2::TokenStream,
+    pub(crate) queue: bool,
 }
 
 // 递归匹配表达式
@@ -24,7 +36,13 @@ pub(crate) fn parse_args(children: Vec<NestedMeta>) -> Vec<EventArg> {
     for nm in children {
         match nm {
             Meta(meta) => match meta {
-                Path(_) => abort!(&meta.span(), "不支持的参数名称"),
+                Path(path) => {
+                    if path.is_ident("at_me") {
+                        children_args.push(EventArg::AtMe);
+                    } else {
+                        abort!(&path.span(), "不支持的参数名称");
+                    }
+                }
                 List(list) => {
                     if list.path.segments.len() != 1 {
                         abort!(&list.span(), "表达式有且只能有一个片段");
@@ -109,6 +127,24 @@ pub(crate) fn parse_args(children: Vec<NestedMeta>) -> Vec<EventArg> {
                             }
                             _ => abort!(&ident.span(), "bot_command只支持字符串类型参数值"),
                         },
+                        "rate_limit" => match nv.lit {
+                            Str(value) => {
+                                children_args.push(EventArg::RateLimit(value.value()));
+                            }
+                            _ => abort!(&ident.span(), "rate_limit只支持字符串类型参数值"),
+                        },
+                        "scope" => match nv.lit {
+                            Str(value) => {
+                                children_args.push(EventArg::RateLimitScope(value.value()));
+                            }
+                            _ => abort!(&ident.span(), "scope只支持字符串类型参数值"),
+                        },
+                        "on_limit" => match nv.lit {
+                            Str(value) => {
+                                children_args.push(EventArg::RateLimitOnLimit(value.value()));
+                            }
+                            _ => abort!(&ident.span(), "on_limit只支持字符串类型参数值"),
+                        },
                         _ => abort!(&ident.span(), "不支持的参数名称"),
                     }
                 }
@@ -162,6 +198,12 @@ pub(crate) fn arg_to_token(arg: EventArg) -> proc_macro2::TokenStream {
         EventArg::BotCommand(_) => {
             panic!("BotCommand 不能被序列化")
         }
+        EventArg::AtMe => {
+            panic!("AtMe 不能被序列化")
+        }
+        EventArg::RateLimit(_) | EventArg::RateLimitScope(_) | EventArg::RateLimitOnLimit(_) => {
+            panic!("RateLimit 不能被序列化")
+        }
     }
 }
 
@@ -179,7 +221,11 @@ pub(crate) fn args_to_token(all: Vec<EventArg>) -> proc_macro2::TokenStream {
 pub(crate) fn contains_bot_command(all: &Vec<EventArg>) -> bool {
     for x in all {
         match x {
-            EventArg::BotCommand(_) => {
+            EventArg::BotCommand(_)
+            | EventArg::AtMe
+            | EventArg::RateLimit(_)
+            | EventArg::RateLimitScope(_)
+            | EventArg::RateLimitOnLimit(_) => {
                 return true;
             }
             EventArg::All(args) => {
@@ -209,13 +255,25 @@ pub(crate) fn contains_bot_command(all: &Vec<EventArg>) -> bool {
 pub(crate) fn parse_args_and_command(
     method: &ItemFn,
     attrs: AttributeArgs,
-) -> (Vec<EventArg>, Option<String>) {
-    // 从众多EventArg中找到bot_command（如果存在）
+) -> (Vec<EventArg>, Option<String>, bool, Option<RateLimitArgs>) {
+    // 从众多EventArg中找到bot_command、at_me和限流参数（如果存在）
     let all: Vec<EventArg> = parse_args(attrs);
     let mut bot_command = None;
+    let mut at_me = false;
+    let mut rate_limit = None;
+    let mut scope = None;
+    let mut on_limit = None;
     let mut _all = vec![];
     for x in all {
-        if let EventArg::BotCommand(command) = x {
+        if let EventArg::AtMe = x {
+            at_me = true;
+        } else if let EventArg::RateLimit(value) = x {
+            rate_limit = Some(value);
+        } else if let EventArg::RateLimitScope(value) = x {
+            scope = Some(value);
+        } else if let EventArg::RateLimitOnLimit(value) = x {
+            on_limit = Some(value);
+        } else if let EventArg::BotCommand(command) = x {
             if bot_command.is_none() {
                 bot_command = Some(command);
             } else {
@@ -233,8 +291,45 @@ pub(crate) fn parse_args_and_command(
         // 这里是为了判断all/in之类的聚合指令内部有没有bot_command，在其指令内部包括bot_command不被允许。因为场景太少，而且逻辑复杂入不敷出。
         abort!(
             &method.sig.span(),
-            "bot_command 只能有一个，且必须是直接写在event括号中"
+            "bot_command、at_me 和 rate_limit 必须直接写在event括号中"
         );
     }
-    (all, bot_command)
+    let rate_limit = parse_rate_limit_args(method, rate_limit, scope, on_limit);
+    (all, bot_command, at_me, rate_limit)
+}
+
+pub(crate) static RATE_LIMIT_USAGE: &str = r#"格式为rate_limit = "5/60s", scope = "per_sender|per_group|global", on_limit = "drop|queue", 周期的单位为s/m/h/d/w"#;
+
+fn parse_rate_limit_args(
+    method: &ItemFn,
+    rate_limit: Option<String>,
+    scope: Option<String>,
+    on_limit: Option<String>,
+) -> Option<RateLimitArgs> {
+    let rate_limit = match rate_limit {
+        Some(rate_limit) => rate_limit,
+        None if scope.is_some() || on_limit.is_some() => {
+            abort!(
+                &method.sig.span(),
+                "scope 和 on_limit 需要与 rate_limit 一起使用"
+            )
+        }
+        None => return None,
+    };
+    let scope = match scope.as_deref().unwrap_or("per_sender") {
+        "per_sender" => quote! {::proc_qq::RateLimitScope::PerSender},
+        "per_group" => quote! {::proc_qq::RateLimitScope::PerGroup},
+        "global" => quote! {::proc_qq::RateLimitScope::Global},
+        _ => abort!(&method.sig.span(), RATE_LIMIT_USAGE),
+    };
+    let queue = match on_limit.as_deref().unwrap_or("drop") {
+        "drop" => false,
+        "queue" => true,
+        _ => abort!(&method.sig.span(), RATE_LIMIT_USAGE),
+    };
+    Some(RateLimitArgs {
+        spec: rate_limit,
+        scope,
+        queue,
+    })
 }
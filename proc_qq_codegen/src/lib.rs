@@ -2,7 +2,7 @@ use proc_macro::TokenStream;
 
 use proc_macro2::Span;
 use proc_macro_error::{abort, proc_macro_error};
-use quote::{quote, ToTokens, TokenStreamExt};
+use quote::{quote, quote_spanned, ToTokens, TokenStreamExt};
 use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
@@ -80,7 +80,10 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
     #[cfg(feature = "event_args")]
     let attrs = parse_macro_input!(args as syn::AttributeArgs);
     #[cfg(feature = "event_args")]
-    let (all_filter_without_bot_command, bot_command) = parse_args_and_command(&method, attrs);
+    let (all_filter_without_bot_command, bot_command, at_me, rate_limit) =
+        parse_args_and_command(&method, attrs);
+    #[cfg(feature = "event_args")]
+    let bot_command_usage = bot_command.clone();
     #[cfg(feature = "event_args")]
     let command_items = parse_bot_command(&method, bot_command);
     #[cfg(feature = "event_args")]
@@ -101,19 +104,60 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
     };
     // trait
     let block = &method.block;
+    // 处理器可以返回anyhow::Result<bool>或anyhow::Result<HandleResult>, 在handle_result中统一转换为HandleResult
+    let output = match &method.sig.output {
+        syn::ReturnType::Default => quote! {-> ::proc_qq::re_exports::anyhow::Result<bool>},
+        output => quote! {#output},
+    };
+    let mut require_guard = parse_require(&method, param_pat, param_ty.to_string());
+    // 先限流再扣除配额, 被限流的消息不消耗配额
+    #[cfg(feature = "event_args")]
+    require_guard.append_all(rate_limit_guard(
+        &method,
+        param_pat,
+        param_ty.to_string(),
+        rate_limit,
+    ));
+    require_guard.append_all(parse_quota(&method, param_pat, param_ty.to_string()));
+    let block = if require_guard.is_empty() {
+        quote! {#block}
+    } else {
+        quote! {{
+            #require_guard
+            #block
+        }}
+    };
+    // handle保持返回bool, 框架调用的是handle_result
+    let handle_bool = quote! {
+        async fn handle(&self, __proc_qq_event: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<bool> {
+            Ok(self.handle_result(__proc_qq_event).await? == ::proc_qq::HandleResult::Stop)
+        }
+    };
     #[cfg(not(feature = "event_args"))]
     let build_trait = quote! {
         #[::proc_qq::re_exports::async_trait::async_trait]
         impl #trait_name for #ident {
-            async fn handle(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<bool> #block
+            #handle_bool
+            async fn handle_result(&self, __proc_qq_event: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<::proc_qq::HandleResult> {
+                self.raw(__proc_qq_event).await.map(::core::convert::Into::into)
+            }
+        }
+        impl #ident {
+            async fn raw(&self, #param_pat: #param_ty) #output #block
         }
     };
     #[cfg(feature = "event_args")]
-    let build_trait = if all_filter_without_bot_command.is_empty() && bot_args.is_none() {
+    let build_trait = if all_filter_without_bot_command.is_empty() && bot_args.is_none() && !at_me {
         quote! {
             #[::proc_qq::re_exports::async_trait::async_trait]
             impl #trait_name for #ident {
-                async fn handle(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<bool> #block
+                #handle_bool
+                async fn handle_result(&self, __proc_qq_event: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<::proc_qq::HandleResult> {
+                    self.raw(__proc_qq_event).await.map(::core::convert::Into::into)
+                }
+            }
+            impl #ident {
+                async fn raw(&self, #param_pat: #param_ty) #output #block
             }
         }
     } else {
@@ -128,19 +172,33 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
             ),
         }
         let args_vec = args_to_token(all_filter_without_bot_command);
+        // at_me: 只处理@机器人或以唤醒词开头的消息, 去掉@和唤醒词后再进行匹配
+        let at_me_guard = if at_me {
+            quote! {
+                let addressed = match ::proc_qq::AddressedTrait::addressed_to_me(#param_pat).await {
+                    Some(addressed) => addressed,
+                    None => return Ok(::proc_qq::HandleResult::Continue),
+                };
+                let #param_pat = &addressed;
+            }
+        } else {
+            quote! {}
+        };
         if bot_args.is_none() {
             quote! {
                 #[::proc_qq::re_exports::async_trait::async_trait]
                 impl #trait_name for #ident {
-                    async fn handle(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<bool> {
+                    #handle_bool
+                    async fn handle_result(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<::proc_qq::HandleResult> {
+                        #at_me_guard
                         if !::proc_qq::match_event_args_all(#args_vec, #param_pat.into())? {
-                            return Ok(false);
+                            return Ok(::proc_qq::HandleResult::Continue);
                         }
-                        self.raw(#param_pat).await
+                        self.raw(#param_pat).await.map(::core::convert::Into::into)
                     }
                 }
                 impl #ident {
-                    async fn raw(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<bool> #block
+                    async fn raw(&self, #param_pat: #param_ty) #output #block
                 }
             }
         } else {
@@ -152,7 +210,7 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                     BotParamsMather::Command(command) => {
                         gets.append_all(quote! {
                             if !matcher.match_command(#command) {
-                                return Ok(false);
+                                return Ok(::proc_qq::HandleResult::Continue);
                             }
                         });
                     }
@@ -166,7 +224,7 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                         gets.append_all(quote! {
                             let #pat: #ty = match ::proc_qq::matcher_get::<#ty>(&mut matcher) {
                                 Some(value) => value,
-                                None => return Ok(false),
+                                None => return Ok(::proc_qq::HandleResult::Continue),
                             };
                         });
                     }
@@ -184,7 +242,7 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                                     mme.append_all(quote! {
                                         ::proc_qq::TupleMatcherElement::Param,
                                     });
-                                    pp.push((*p, *t));
+                                    pp.push((p, t));
                                 }
                             }
                         }
@@ -192,14 +250,14 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                             let mut ps = if let Some(ps) = matcher.tuple_matcher(vec![#mme]) {
                                 ps
                             } else {
-                                return Ok(false);
+                                return Ok(::proc_qq::HandleResult::Continue);
                             };
                             ps.reverse();
                         });
                         let len = pp.len();
                         gets.append_all(quote! {
                             if ps.len() != #len {
-                                return Ok(false);
+                                return Ok(::proc_qq::HandleResult::Continue);
                             }
                         });
                         for (pat, ty) in pp {
@@ -214,10 +272,10 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                                         let sub_matcher = ::proc_qq::TupleMatcher::new(np);
                                         match ::proc_qq::tuple_matcher_get::<#ty>(sub_matcher) {
                                             Some(value) => value,
-                                            None => return Ok(false),
+                                            None => return Ok(::proc_qq::HandleResult::Continue),
                                         }
                                     } else {
-                                        return Ok(false);
+                                        return Ok(::proc_qq::HandleResult::Continue);
                                     };
                             });
                         }
@@ -227,9 +285,11 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
             quote! {
                 #[::proc_qq::re_exports::async_trait::async_trait]
                 impl #trait_name for #ident {
-                    async fn handle(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<bool> {
+                    #handle_bool
+                    async fn handle_result(&self, #param_pat: #param_ty) -> ::proc_qq::re_exports::anyhow::Result<::proc_qq::HandleResult> {
+                        #at_me_guard
                         if !::proc_qq::match_event_args_all(#args_vec, #param_pat.into())? {
-                            return Ok(false);
+                            return Ok(::proc_qq::HandleResult::Continue);
                         }
                         // 匹配指令是否能对应
                         use ::proc_qq::MessageChainPointTrait;
@@ -241,17 +301,22 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                         let mut matcher = ::proc_qq::CommandMatcher::new(m_vec);
                         #gets
                         if matcher.not_blank() {
-                            return Ok(false);
+                            return Ok(::proc_qq::HandleResult::Continue);
                         }
-                        self.raw(#param_pat, #p_pats).await
+                        self.raw(#param_pat, #p_pats).await.map(::core::convert::Into::into)
                     }
                 }
                 impl #ident {
-                    async fn raw(&self, #param_pat: #param_ty, #command_params_in_raw) -> ::proc_qq::re_exports::anyhow::Result<bool> #block
+                    async fn raw(&self, #param_pat: #param_ty, #command_params_in_raw) #output #block
                 }
             }
         }
     };
+    // 指令说明, 用于/help
+    #[cfg(feature = "event_args")]
+    let command_info = parse_command_info(&method, bot_command_usage);
+    #[cfg(not(feature = "event_args"))]
+    let command_info = quote! {None};
     // into
     let build_into = quote! {
         impl Into<::proc_qq::ModuleEventHandler> for #ident {
@@ -259,6 +324,7 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
                 ::proc_qq::ModuleEventHandler{
                     name: #ident_str.into(),
                     process: #enum_name(Box::new(self)),
+                    command: #command_info,
                 }
             }
         }
@@ -271,6 +337,274 @@ pub fn event(args: TokenStream, input: TokenStream) -> TokenStream {
     })
 }
 
+/// 解析方法上的 #[require(admin)] , 生成角色守卫, 角色不足时不处理该事件
+fn parse_require(
+    method: &syn::ItemFn,
+    param_pat: &syn::Pat,
+    param_ty: String,
+) -> proc_macro2::TokenStream {
+    let mut guard = quote! {};
+    for attr in &method.attrs {
+        if !attr.path.is_ident("require") {
+            continue;
+        }
+        match param_ty.as_str() {
+            "& MessageEvent" => (),
+            "& GroupMessageEvent" => (),
+            "& FriendMessageEvent" => (),
+            "& GroupTempMessageEvent" => (),
+            _ => abort!(
+                &attr.span(),
+                "require 只支持消息类型事件 (MessageEvent,*MessageEvent)"
+            ),
+        }
+        let check = match parse_require_attr(attr) {
+            Require::Role(role) => {
+                let role = role_tokens(role);
+                quote! {::proc_qq::require_role(#param_pat, #role)}
+            }
+            Require::Permission(permission) => {
+                quote! {::proc_qq::require_permission(#param_pat, #permission)}
+            }
+        };
+        guard.append_all(quote! {
+            if !#check.await? {
+                return Ok(::core::convert::From::from(false));
+            }
+        });
+    }
+    guard
+}
+
+enum Require {
+    Role(&'static str),
+    /// 内置的group_admin/group_owner, 或交给PermissionProvider判断的权限
+    Permission(String),
+}
+
+static REQUIRE_USAGE: &str =
+    r#"格式为require(master|admin|trusted|group_admin|group_owner) 或 require("自定义权限")"#;
+
+/// 解析 #[require(admin)] #[require(group_admin)] #[require("vip")]
+fn parse_require_attr(attr: &syn::Attribute) -> Require {
+    let name = match attr.parse_meta() {
+        Ok(Meta::List(list)) if list.nested.len() == 1 => match list.nested.first().unwrap() {
+            NestedMeta::Meta(Meta::Path(p)) if p.segments.len() == 1 => {
+                p.segments.first().unwrap().ident.to_string()
+            }
+            NestedMeta::Lit(syn::Lit::Str(str)) if !str.value().is_empty() => {
+                return Require::Permission(str.value());
+            }
+            _ => abort!(&attr.span(), REQUIRE_USAGE),
+        },
+        _ => abort!(&attr.span(), REQUIRE_USAGE),
+    };
+    // 从低到高排列
+    match name.as_str() {
+        "trusted" => Require::Role("trusted"),
+        "admin" => Require::Role("admin"),
+        "master" => Require::Role("master"),
+        "group_admin" | "group_owner" => Require::Permission(name),
+        _ => abort!(&attr.span(), REQUIRE_USAGE),
+    }
+}
+
+fn role_tokens(role: &str) -> proc_macro2::TokenStream {
+    match role {
+        "master" => quote! {::proc_qq::Role::Master},
+        "admin" => quote! {::proc_qq::Role::Admin},
+        _ => quote! {::proc_qq::Role::Trusted},
+    }
+}
+
+/// 设置了bot_command时生成指令说明, 说明取自文档注释, 有多个require时取最高的角色
+#[cfg(feature = "event_args")]
+fn parse_command_info(
+    method: &syn::ItemFn,
+    bot_command: Option<String>,
+) -> proc_macro2::TokenStream {
+    let usage = match bot_command {
+        Some(usage) => usage.split_whitespace().collect::<Vec<&str>>().join(" "),
+        None => return quote! {None},
+    };
+    let mut lines = vec![];
+    let mut require: Option<&'static str> = None;
+    for attr in &method.attrs {
+        if attr.path.is_ident("doc") {
+            if let Ok(Meta::NameValue(nv)) = attr.parse_meta() {
+                if let syn::Lit::Str(str) = &nv.lit {
+                    lines.push(str.value().trim().to_owned());
+                }
+            }
+        } else if attr.path.is_ident("require") {
+            let role = match parse_require_attr(attr) {
+                Require::Role(role) => role,
+                Require::Permission(_) => continue,
+            };
+            let order = |role: &str| {
+                ["trusted", "admin", "master"]
+                    .iter()
+                    .position(|r| *r == role)
+            };
+            if require
+                .map(|current| order(role) > order(current))
+                .unwrap_or(true)
+            {
+                require = Some(role);
+            }
+        }
+    }
+    let description = lines.join("\n").trim().to_owned();
+    let require = match require {
+        Some(role) => {
+            let role = role_tokens(role);
+            quote! {Some(#role)}
+        }
+        None => quote! {None},
+    };
+    quote! {
+        Some(::proc_qq::CommandInfo {
+            usage: #usage.to_owned(),
+            description: #description.to_owned(),
+            require: #require,
+        })
+    }
+}
+
+/// 解析方法上的 #[quota(per_user = 5, period = "1d")] , 生成配额守卫, 超出配额时回复提示并拦截事件
+fn parse_quota(
+    method: &syn::ItemFn,
+    param_pat: &syn::Pat,
+    param_ty: String,
+) -> proc_macro2::TokenStream {
+    let mut guard = quote! {};
+    for attr in &method.attrs {
+        if !attr.path.is_ident("quota") {
+            continue;
+        }
+        match param_ty.as_str() {
+            "& MessageEvent" => (),
+            "& GroupMessageEvent" => (),
+            "& FriendMessageEvent" => (),
+            "& GroupTempMessageEvent" => (),
+            _ => abort!(
+                &attr.span(),
+                "quota 只支持消息类型事件 (MessageEvent,*MessageEvent)"
+            ),
+        }
+        let usage = "格式为quota(per_user = 5, period = \"1d\"), 周期的单位为s/m/h/d/w";
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => abort!(&attr.span(), usage),
+        };
+        let mut per_user: Option<u32> = None;
+        let mut period: Option<u64> = None;
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("per_user") => {
+                    match &nv.lit {
+                        syn::Lit::Int(int) => match int.base10_parse::<u32>() {
+                            Ok(value) if value > 0 => per_user = Some(value),
+                            _ => abort!(&nv.lit.span(), usage),
+                        },
+                        _ => abort!(&nv.lit.span(), usage),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("period") => {
+                    match &nv.lit {
+                        syn::Lit::Str(str) => match parse_period(&str.value()) {
+                            Some(value) => period = Some(value),
+                            None => abort!(&nv.lit.span(), usage),
+                        },
+                        _ => abort!(&nv.lit.span(), usage),
+                    }
+                }
+                _ => abort!(&nested.span(), usage),
+            }
+        }
+        let per_user = per_user.unwrap_or_else(|| abort!(&attr.span(), usage));
+        // 默认每天
+        let period = period.unwrap_or(86400);
+        let name = method.sig.ident.to_string();
+        guard.append_all(quote! {
+            if !::proc_qq::check_quota(
+                #param_pat,
+                concat!(module_path!(), "::", #name),
+                #per_user,
+                #period,
+            ).await? {
+                return Ok(::core::convert::From::from(true));
+            }
+        });
+    }
+    guard
+}
+
+/// event参数中设置了rate_limit时生成限流守卫, 超出限制时丢弃消息或等待
+#[cfg(feature = "event_args")]
+fn rate_limit_guard(
+    method: &syn::ItemFn,
+    param_pat: &syn::Pat,
+    param_ty: String,
+    rate_limit: Option<RateLimitArgs>,
+) -> proc_macro2::TokenStream {
+    let RateLimitArgs { spec, scope, queue } = match rate_limit {
+        Some(rate_limit) => rate_limit,
+        None => return quote! {},
+    };
+    match param_ty.as_str() {
+        "& MessageEvent" => (),
+        "& GroupMessageEvent" => (),
+        "& FriendMessageEvent" => (),
+        "& GroupTempMessageEvent" => (),
+        _ => abort!(
+            &method.sig.span(),
+            "rate_limit 只支持消息类型事件 (MessageEvent,*MessageEvent)"
+        ),
+    }
+    let name = method.sig.ident.to_string();
+    // 与RateLimiter::parse使用同一个解析函数, 在编译期求值, 格式错误时编译失败并提示用法
+    let rate_limit = quote_spanned! {method.sig.span()=>
+        const RATE_LIMIT: (u32, u64) = match ::proc_qq::parse_rate_limit(#spec) {
+            ::core::option::Option::Some(rate_limit) => rate_limit,
+            ::core::option::Option::None => panic!(#RATE_LIMIT_USAGE),
+        };
+    };
+    quote! {
+        #rate_limit
+        if !::proc_qq::check_rate_limit(
+            #param_pat,
+            concat!(module_path!(), "::", #name),
+            RATE_LIMIT.0,
+            RATE_LIMIT.1,
+            #scope,
+            #queue,
+        ).await {
+            return Ok(::core::convert::From::from(true));
+        }
+    }
+}
+
+/// 解析 30s 10m 12h 1d 1w 为秒数, 格式错误或溢出时返回None, 由调用者提示用法
+fn parse_period(period: &str) -> Option<u64> {
+    let period = period.trim();
+    // 单位可能是多字节字符 (例如 1天), 不能按字节切分
+    let (index, unit) = period.char_indices().last()?;
+    let number = period[..index].parse::<u64>().ok()?;
+    let unit = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 604800,
+        _ => return None,
+    };
+    match number.checked_mul(unit)? {
+        0 => None,
+        secs => Some(secs),
+    }
+}
+
 fn struct_name(
     pt: &PatType,
     param_ty: String,
@@ -348,6 +682,10 @@ fn struct_name(
             quote! {::proc_qq::ConnectedAndOnlineEventProcess},
             quote! {::proc_qq::ModuleEventProcess::ConnectedAndOnline},
         ),
+        "& AccountReadyEvent" => (
+            quote! {::proc_qq::AccountReadyEventProcess},
+            quote! {::proc_qq::ModuleEventProcess::AccountReady},
+        ),
         "& DisconnectedAndOfflineEvent" => (
             quote! {::proc_qq::DisconnectedAndOfflineEventProcess},
             quote! {::proc_qq::ModuleEventProcess::DisconnectedAndOffline},
@@ -380,8 +718,8 @@ fn struct_name(
             quote! {::proc_qq::ClientDisconnectProcess},
             quote! {::proc_qq::ModuleEventProcess::ClientDisconnect},
         ),
-        "& GroupPoke" => (
-            quote! {::proc_qq::GroupPokeProcess},
+        "& GroupPokeEvent" => (
+            quote! {::proc_qq::GroupPokeEventProcess},
             quote! {::proc_qq::ModuleEventProcess::GroupPoke},
         ),
         t => abort!(
@@ -469,16 +807,45 @@ pub fn module(input: TokenStream) -> TokenStream {
     let id = syn::parse_str::<Expr>(&params.expressions[0]).expect("id 解析错误");
     let name = syn::parse_str::<Expr>(&params.expressions[1]).expect("name 解析错误");
     let mut handle_builder = String::new();
+    // data = expr 为注入模块的依赖, priority = expr 为模块的优先级, 其他为事件处理器
+    let mut data_inserts = quote! {};
+    let mut priority = quote! {0};
     for i in 2..params.expressions.len() {
+        let expr = syn::parse_str::<Expr>(&params.expressions[i]).expect("参数解析错误");
+        if let Expr::Assign(assign) = &expr {
+            let name = match assign.left.as_ref() {
+                Expr::Path(path) if path.path.is_ident("data") => "data",
+                Expr::Path(path) if path.path.is_ident("priority") => "priority",
+                _ => abort!(
+                    params.span,
+                    "只支持 data = 表达式 的形式注入依赖, 或 priority = 表达式 设置优先级"
+                ),
+            };
+            let value = assign.right.as_ref();
+            if name == "priority" {
+                priority = quote! {#value};
+            } else {
+                data_inserts.append_all(quote! {
+                    __proc_qq_module_data.insert(#value);
+                });
+            }
+            continue;
+        }
         handle_builder.push_str(&format!("{} {{}}.into(),", params.expressions[i]));
     }
     let handle_invoker =
         syn::parse_str::<Expr>(&format!("vec![{handle_builder}]")).expect("handle invoker解析错误");
     TokenStream::from(quote! {
-        ::proc_qq::Module {
-            id: #id.to_owned(),
-            name: #name.to_owned(),
-            handles: #handle_invoker,
+        {
+            let __proc_qq_module_data = ::proc_qq::Extensions::new();
+            #data_inserts
+            ::proc_qq::Module {
+                id: #id.to_owned(),
+                name: #name.to_owned(),
+                handles: #handle_invoker,
+                data: ::std::sync::Arc::new(__proc_qq_module_data),
+                priority: #priority,
+            }
         }
     })
 }
@@ -5,7 +5,7 @@ use crate::database::mongo::init_mongo;
 use crate::database::redis::init_redis;
 use proc_qq::re_exports::ricq::version::ANDROID_WATCH;
 use proc_qq::Authentication::UinPasswordMd5;
-use proc_qq::{run_client, ClientBuilder, DeviceSource, FileSessionStore};
+use proc_qq::{run_client, ClientBuilder, DelayedSender, DeviceSource, FileSessionStore};
 use std::sync::Arc;
 use tracing::Level;
 use tracing_subscriber::layer::SubscriberExt;
@@ -33,6 +33,7 @@ async fn main() -> anyhow::Result<()> {
         .authentication(UinPasswordMd5(config.account.uin, password))
         .show_slider_pop_menu_if_possible()
         .modules(modules::all_modules())
+        .delayed_sender(DelayedSender::file("delayed_send.json"))
         .build()
         .await
         .unwrap();
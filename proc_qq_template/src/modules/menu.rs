@@ -16,7 +16,7 @@ async fn on_message(event: &MessageEvent) -> anyhow::Result<bool> {
     let content = event.message_content();
     if content.eq(NAME) {
         let mut result = vec!["菜单 (请直接回复功能名) : ".to_owned()];
-        for m in all_modules().as_ref() {
+        for m in all_modules().iter() {
             if m.name != "" {
                 result.push(format!("\n ❤️ {}", m.name));
             }
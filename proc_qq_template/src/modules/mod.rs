@@ -9,21 +9,23 @@ mod query;
 mod tools;
 
 lazy_static! {
-    static ref MODULES: Arc<Vec<Module>> = Arc::new(vec![
-        ignore::module(),
-        menu::module(),
-        game::group_sign_in::module(),
-        tools::group_admin::module(),
-        query::image_lib::module(),
-        query::daily_english::module(),
-    ]);
+    static ref MODULES: Vec<Arc<Module>> = vec![
+        Arc::new(ignore::module()),
+        Arc::new(menu::module()),
+        Arc::new(game::group_sign_in::module()),
+        Arc::new(tools::group_admin::module()),
+        Arc::new(tools::reminder::module()),
+        Arc::new(query::image_lib::module()),
+        Arc::new(query::daily_english::module()),
+    ];
 }
 
-pub(crate) fn all_modules() -> Arc<Vec<Module>> {
+pub(crate) fn all_modules() -> Vec<Arc<Module>> {
     MODULES.clone()
 }
 
 pub(crate) async fn init_modules() -> anyhow::Result<()> {
     game::group_sign_in::init_data_base().await?;
+    tools::reminder::init_reminders().await?;
     Ok(())
 }
@@ -4,7 +4,7 @@ use proc_qq::re_exports::ricq::msg::MessageChain;
 use proc_qq::re_exports::{bytes, reqwest};
 use proc_qq::{
     event, module, MessageChainAppendTrait, MessageContentTrait, MessageEvent,
-    MessageSendToSourceTrait, Module, TextEleParseTrait,
+    MessageSendToSourceTrait, Module, TextEleParseTrait, UploadImageTrait,
 };
 use regex::Regex;
 
@@ -38,8 +38,7 @@ async fn on_message(event: &MessageEvent) -> anyhow::Result<bool> {
             event.send_message_to_source(no_temp_message()).await?;
             return Ok(true);
         }
-        let img = get_laopo_img().await?.to_vec();
-        let img = event.upload_image_to_source(img).await?;
+        let img = event.upload_image(get_laopo_img().await?).await?;
         event
             .send_message_to_source(event.make_reply_chain().await.append(img))
             .await?;
@@ -49,8 +48,7 @@ async fn on_message(event: &MessageEvent) -> anyhow::Result<bool> {
             event.send_message_to_source(no_temp_message()).await?;
             return Ok(true);
         }
-        let img = get_dongman_img().await?.to_vec();
-        let img = event.upload_image_to_source(img).await?;
+        let img = event.upload_image(get_dongman_img().await?).await?;
         event
             .send_message_to_source(event.make_reply_chain().await.append(img))
             .await?;
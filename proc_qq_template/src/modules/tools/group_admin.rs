@@ -2,7 +2,8 @@ use crate::utils::CanReply;
 use lazy_static::lazy_static;
 use proc_qq::re_exports::ricq_core::msg::elem::RQElem;
 use proc_qq::{
-    event, module, ClientTrait, GroupTrait, MemberTrait, MessageContentTrait, MessageEvent, Module,
+    event, module, AuditedActionsTrait, ClientTrait, GroupTrait, MemberTrait, MessageContentTrait,
+    MessageEvent, Module,
 };
 use regex::Regex;
 use std::time::Duration;
@@ -90,10 +91,12 @@ async fn on_message(event: &MessageEvent) -> anyhow::Result<bool> {
                 RQElem::At(id) => {
                     event
                         .client()
-                        .group_mute(
+                        .audited_mute(
+                            Some(event.from_uin()),
                             group_message.inner.group_code,
                             id.target,
                             Duration::from_secs(time),
+                            "",
                         )
                         .await?;
                 }
@@ -1 +1,2 @@
 pub(crate) mod group_admin;
+pub(crate) mod reminder;
@@ -0,0 +1,110 @@
+use crate::utils::CanReply;
+use chrono::{Local, TimeZone};
+use lazy_static::lazy_static;
+use proc_qq::{
+    event, module, parse_remind_time, MessageContentTrait, MessageEvent, MessageSendToSourceTrait,
+    MessageTargetTrait, Module, Reminders,
+};
+
+static ID: &'static str = "reminder";
+static NAME: &'static str = "提醒";
+
+lazy_static! {
+    static ref REMINDERS: Reminders = Reminders::file("reminders.json");
+}
+
+pub fn module() -> Module {
+    module!(ID, NAME, on_message)
+}
+
+pub(crate) async fn init_reminders() -> anyhow::Result<()> {
+    REMINDERS.load().await
+}
+
+#[event]
+async fn on_message(event: &MessageEvent) -> anyhow::Result<bool> {
+    let content = event.message_content();
+    let content = content.trim();
+    if content == NAME {
+        event
+            .reply_text(
+                &("".to_owned()
+                    + "/remind me 时间 事项\n\n"
+                    + "比如 : /remind me 明天 9点 开会\n\n"
+                    + "比如 : /remind me 10分钟后 收衣服\n\n"
+                    + "/remind list 查看提醒\n\n"
+                    + "/remind cancel 编号 取消提醒"),
+            )
+            .await?;
+        return Ok(true);
+    }
+    let args = match content.strip_prefix("/remind") {
+        Some(args) => args.trim(),
+        None => return Ok(false),
+    };
+    let uin = event.from_uin();
+    if args == "list" {
+        let list = REMINDERS.list(uin).await?;
+        if list.is_empty() {
+            event.reply_text("没有提醒").await?;
+        } else {
+            let text = list
+                .iter()
+                .map(|r| {
+                    let time = Local
+                        .timestamp_millis_opt(r.remind_at)
+                        .single()
+                        .map(|t| t.format("%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    format!("{} : {} {}", r.id, time, r.content)
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            event.reply_text(&text).await?;
+        }
+        return Ok(true);
+    }
+    if let Some(id) = args.strip_prefix("cancel") {
+        let reply = match id.trim().parse::<u64>() {
+            Ok(id) => {
+                if REMINDERS.cancel(&event.client(), uin, id).await? {
+                    "已取消"
+                } else {
+                    "没有找到这个提醒"
+                }
+            }
+            Err(_) => "请输入提醒的编号",
+        };
+        event.reply_text(reply).await?;
+        return Ok(true);
+    }
+    let args = args.strip_prefix("me").unwrap_or(args);
+    match parse_remind_time(args, Local::now()) {
+        Some((_, text)) if text.is_empty() => {
+            event.reply_text("请输入提醒的事项").await?;
+        }
+        Some((time, text)) => {
+            match REMINDERS
+                .remind(&event.client(), event.target(), uin, time, text)
+                .await
+            {
+                Ok(reminder) => {
+                    event
+                        .reply_text(&format!(
+                            "好的, 将在 {} 提醒你 (编号 {})",
+                            time.format("%m-%d %H:%M"),
+                            reminder.id
+                        ))
+                        .await?
+                }
+                Err(err) => event.reply_text(&format!("{}", err)).await?,
+            }
+        }
+        None => {
+            event
+                .reply_text("没有识别出时间, 比如 : 明天 9点, 10分钟后")
+                .await?;
+        }
+    }
+    Ok(true)
+}